@@ -23,7 +23,10 @@ use palette::rgb::*;
 use serde_derive::{Deserialize, Serialize};
 use strum::Display;
 
-/// A enum to define what axis of the pole figure should be plotted.
+use std::fs;
+use std::path::Path;
+
+/// A enum to define what color gradient is used for the figures.
 #[derive(Display, Serialize, Deserialize, Debug, Clone)]
 pub enum ColorGradient {
     Vik,
@@ -32,14 +35,207 @@ pub enum ColorGradient {
     Imola,
     Hawaii,
     Roma,
+    /// A color map read from a file, given as `{ File = { path = "lapaz.cpt" } }`. A file with the extension
+    /// `.cpt` is read as a GMT color palette table with RGB colors, any other file as a table with the red, green
+    /// and blue values of evenly spaced colors on every line, either between 0 and 1 or between 0 and 255.
+    File {
+        path: String,
+    },
+    /// A list of evenly spaced colors like a matplotlib colormap, given as
+    /// `{ List = ["#2a0bd9", [1.0, 1.0, 0.75], "#a60021"] }`.
+    List(Vec<ListColor>),
+}
+
+impl ColorGradient {
+    /// Return the name of the color gradient in the file names of the figures, which is the name of the file
+    /// for a color map read from a file. The name of a reversed color gradient ends with `_r`.
+    pub fn file_label(&self, reverse: bool) -> String {
+        let name = match self {
+            ColorGradient::File { path } => Path::new(path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| self.to_string()),
+            _ => self.to_string(),
+        };
+        if reverse {
+            name + "_r"
+        } else {
+            name
+        }
+    }
+}
+
+/// A color in a list of colors, either a hex string like `"#ff8800"` or the red, green and blue values between 0
+/// and 1.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ListColor {
+    Hex(String),
+    Rgb([f32; 3]),
 }
 
 /// This function sets the color gradient based on the a provided string. Available options are `Vik`,
 /// `Batlow`, `Simple`, `Imola`, `Hawaii` and `Roma`, which are all, except for `Simple`, from
-/// [http://www.fabiocrameri.ch/colourmaps.php](http://www.fabiocrameri.ch/colourmaps.php).
-pub fn set_color_gradient(color_gradient_selection: &ColorGradient) -> Gradient<LinSrgb> {
-    match &color_gradient_selection {
-        ColorGradient::Vik => Gradient::with_domain(vec![
+/// [http://www.fabiocrameri.ch/colourmaps.php](http://www.fabiocrameri.ch/colourmaps.php), or a color map read
+/// from a file or given as a list of colors. The other maps of that set, like lapaz, oslo and tokyo, are not built
+/// in, but can be used by reading their `.cpt` or `.txt` files from the Scientific colour maps distribution. If
+/// `reverse` is set the color gradient runs from the last to the first color.
+pub fn set_color_gradient(
+    color_gradient_selection: &ColorGradient,
+    reverse: bool,
+) -> Result<Gradient<LinSrgb>, Box<dyn std::error::Error>> {
+    let stops = match color_gradient_selection {
+        ColorGradient::File { path } => read_color_map_file(Path::new(path))?,
+        ColorGradient::List(colors) => list_color_stops(colors)?,
+        _ => built_in_color_stops(color_gradient_selection),
+    };
+    if stops.len() < 2 {
+        return Err(format!(
+            "a color gradient needs at least two colors, but {} has {}",
+            color_gradient_selection.file_label(false),
+            stops.len()
+        )
+        .into());
+    }
+
+    // the positions of the colors are scaled to the range from zero to one
+    let first = stops[0].0;
+    let last = stops[stops.len() - 1].0;
+    if last <= first || stops.windows(2).any(|pair| pair[1].0 < pair[0].0) {
+        return Err(format!(
+            "the positions of the colors of {} are not increasing",
+            color_gradient_selection.file_label(false)
+        )
+        .into());
+    }
+    let stops = stops
+        .into_iter()
+        .map(|(position, color)| ((position - first) / (last - first), color));
+    Ok(Gradient::with_domain(if reverse {
+        stops
+            .rev()
+            .map(|(position, color)| (1.0 - position, color))
+            .collect()
+    } else {
+        stops.collect()
+    }))
+}
+
+/// Read a color map file, either a GMT color palette table if the extension is `.cpt` or a table of evenly spaced
+/// RGB colors.
+fn read_color_map_file(path: &Path) -> Result<Vec<(f32, LinSrgb)>, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|error| format!("could not read color map {}: {}", path.display(), error))?;
+    let stops = if path.extension().is_some_and(|extension| extension == "cpt") {
+        parse_cpt(&contents)
+    } else {
+        parse_rgb_table(&contents)
+    };
+    stops.map_err(|error| format!("could not parse color map {}: {}", path.display(), error).into())
+}
+
+/// Parse a GMT color palette table. Every line with a slice of the palette has the start value and color and the
+/// end value and color, as `z0 r0 g0 b0 z1 r1 g1 b1` or `z0 r0/g0/b0 z1 r1/g1/b1` with RGB values between 0 and
+/// 255. The comments and the colors for the background, foreground and NaN values are skipped.
+fn parse_cpt(contents: &str) -> Result<Vec<(f32, LinSrgb)>, String> {
+    let mut stops = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with(['B', 'F', 'N']) {
+            continue;
+        }
+        let values = line
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .filter(|value| !value.is_empty())
+            .take(8)
+            .map(|value| value.parse::<f32>())
+            .collect::<Result<Vec<f32>, _>>()
+            .map_err(|error| format!("invalid value in line '{}': {}", line, error))?;
+        if values.len() < 8 {
+            return Err(format!("expected 8 values in line '{}'", line));
+        }
+        stops.push((
+            values[0],
+            LinSrgb::new(values[1] / 255., values[2] / 255., values[3] / 255.),
+        ));
+        stops.push((
+            values[4],
+            LinSrgb::new(values[5] / 255., values[6] / 255., values[7] / 255.),
+        ));
+    }
+    Ok(stops)
+}
+
+/// Parse a table with the red, green and blue values of a color on every line. The values are between 0 and 1,
+/// or between 0 and 255 if any of them is larger than 1.
+fn parse_rgb_table(contents: &str) -> Result<Vec<(f32, LinSrgb)>, String> {
+    let mut colors = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let values = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|value| !value.is_empty())
+            .map(|value| value.parse::<f32>())
+            .collect::<Result<Vec<f32>, _>>()
+            .map_err(|error| format!("invalid value in line '{}': {}", line, error))?;
+        if values.len() != 3 {
+            return Err(format!("expected 3 values in line '{}'", line));
+        }
+        colors.push([values[0], values[1], values[2]]);
+    }
+    let scale = if colors.iter().flatten().any(|value| *value > 1.) {
+        255.
+    } else {
+        1.
+    };
+    Ok(evenly_spaced(colors.iter().map(|color| {
+        LinSrgb::new(color[0] / scale, color[1] / scale, color[2] / scale)
+    })))
+}
+
+/// Return the stops of a list of colors given in the configuration.
+fn list_color_stops(colors: &[ListColor]) -> Result<Vec<(f32, LinSrgb)>, String> {
+    let colors = colors
+        .iter()
+        .map(|color| match color {
+            ListColor::Rgb([red, green, blue]) => Ok(LinSrgb::new(*red, *green, *blue)),
+            ListColor::Hex(hex) => {
+                let digits = hex.trim_start_matches('#');
+                let channel = |i: usize| {
+                    digits
+                        .get(2 * i..2 * i + 2)
+                        .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+                        .map(|channel| channel as f32 / 255.)
+                };
+                match (digits.len(), channel(0), channel(1), channel(2)) {
+                    (6, Some(red), Some(green), Some(blue)) => Ok(LinSrgb::new(red, green, blue)),
+                    _ => Err(format!("invalid hex color '{}'", hex)),
+                }
+            }
+        })
+        .collect::<Result<Vec<LinSrgb>, String>>()?;
+    Ok(evenly_spaced(colors.into_iter()))
+}
+
+/// Return the stops of colors which are evenly spaced between zero and one.
+fn evenly_spaced<I: ExactSizeIterator<Item = LinSrgb>>(colors: I) -> Vec<(f32, LinSrgb)> {
+    let step = 1. / (colors.len().max(2) - 1) as f32;
+    colors
+        .enumerate()
+        .map(|(i, color)| (i as f32 * step, color))
+        .collect()
+}
+
+/// Return the stops of the built in color gradients.
+fn built_in_color_stops(color_gradient_selection: &ColorGradient) -> Vec<(f32, LinSrgb)> {
+    match color_gradient_selection {
+        ColorGradient::Vik => vec![
             (0.000000, LinSrgb::new(0.0013282, 0.069836, 0.37953)),
             (0.003922, LinSrgb::new(0.0023664, 0.076475, 0.38352)),
             (0.007843, LinSrgb::new(0.0033042, 0.083083, 0.38749)),
@@ -296,8 +492,8 @@ pub fn set_color_gradient(color_gradient_selection: &ColorGradient) -> Gradient<
             (0.992157, LinSrgb::new(0.36316, 0.0082428, 0.030115)),
             (0.996078, LinSrgb::new(0.35679, 0.0040345, 0.030327)),
             (1.000000, LinSrgb::new(0.35042, 6.1141e-05, 0.030499)),
-        ]),
-        ColorGradient::Batlow => Gradient::with_domain(vec![
+        ],
+        ColorGradient::Batlow => vec![
             (0.000000, LinSrgb::new(0.0051932, 0.098238, 0.34984)),
             (0.003922, LinSrgb::new(0.0090652, 0.10449, 0.35093)),
             (0.007843, LinSrgb::new(0.012963, 0.11078, 0.35199)),
@@ -554,10 +750,10 @@ pub fn set_color_gradient(color_gradient_selection: &ColorGradient) -> Gradient<
             (0.992157, LinSrgb::new(0.9827, 0.79407, 0.9629)),
             (0.996078, LinSrgb::new(0.98205, 0.79723, 0.97207)),
             (1.000000, LinSrgb::new(0.98135, 0.80041, 0.98127)),
-        ]),
+        ],
         ColorGradient::Simple => {
             let one_eleventh = 1. / 11.;
-            Gradient::with_domain(vec![
+            vec![
                 (0.0, LinSrgb::new(0.164, 0.043, 0.85)),
                 (1. * one_eleventh, LinSrgb::new(0.15, 0.306, 1.0)),
                 (2. * one_eleventh, LinSrgb::new(0.25, 0.63, 1.0)),
@@ -570,9 +766,9 @@ pub fn set_color_gradient(color_gradient_selection: &ColorGradient) -> Gradient<
                 (9. * one_eleventh, LinSrgb::new(0.97, 0.430, 0.37)),
                 (10. * one_eleventh, LinSrgb::new(0.85, 0.15, 0.196)),
                 (1., LinSrgb::new(0.65, 0.0, 0.13)),
-            ])
+            ]
         }
-        ColorGradient::Imola => Gradient::with_domain(vec![
+        ColorGradient::Imola => vec![
             (0.000000, LinSrgb::new(0.10144, 0.20011, 0.70019)),
             (0.003922, LinSrgb::new(0.10328, 0.20301, 0.69881)),
             (0.007843, LinSrgb::new(0.10496, 0.2059, 0.69742)),
@@ -829,8 +1025,8 @@ pub fn set_color_gradient(color_gradient_selection: &ColorGradient) -> Gradient<
             (0.992157, LinSrgb::new(0.98357, 0.9945, 0.40014)),
             (0.996078, LinSrgb::new(0.9918, 0.99724, 0.40012)),
             (1.000000, LinSrgb::new(1.0000, 0.99999, 0.40009)),
-        ]),
-        ColorGradient::Hawaii => Gradient::with_domain(vec![
+        ],
+        ColorGradient::Hawaii => vec![
             (0.000000, LinSrgb::new(0.55054, 0.006842, 0.45198)),
             (0.003922, LinSrgb::new(0.55149, 0.015367, 0.44797)),
             (0.007843, LinSrgb::new(0.55243, 0.023795, 0.444)),
@@ -1087,8 +1283,8 @@ pub fn set_color_gradient(color_gradient_selection: &ColorGradient) -> Gradient<
             (0.992157, LinSrgb::new(0.68228, 0.94832, 0.98777)),
             (0.996078, LinSrgb::new(0.69306, 0.94866, 0.9908)),
             (1.000000, LinSrgb::new(0.70378, 0.94898, 0.99377)),
-        ]),
-        ColorGradient::Roma => Gradient::with_domain(vec![
+        ],
+        ColorGradient::Roma => vec![
             (0.000000, LinSrgb::new(0.49684, 0.099626, 0.000000)),
             (0.003922, LinSrgb::new(0.50141, 0.11159, 0.0038271)),
             (0.007843, LinSrgb::new(0.50595, 0.12281, 0.0075362)),
@@ -1345,7 +1541,9 @@ pub fn set_color_gradient(color_gradient_selection: &ColorGradient) -> Gradient<
             (0.992157, LinSrgb::new(0.11142, 0.21209, 0.60511)),
             (0.996078, LinSrgb::new(0.10761, 0.20611, 0.60255)),
             (1.000000, LinSrgb::new(0.1037, 0.20006, 0.59999)),
-        ]),
+        ],
+        // these are not built in, but read by `set_color_gradient`
+        ColorGradient::File { .. } | ColorGradient::List(_) => vec![],
     }
 }
 
@@ -1355,7 +1553,7 @@ mod tests {
 
     #[test]
     fn test_colors() {
-        let color_gradient = set_color_gradient(&ColorGradient::Batlow);
+        let color_gradient = set_color_gradient(&ColorGradient::Batlow, false).unwrap();
         assert_eq!(
             color_gradient.get(0.0),
             Rgb {
@@ -1383,7 +1581,7 @@ mod tests {
                 standard: std::marker::PhantomData
             }
         );
-        let color_gradient = set_color_gradient(&ColorGradient::Hawaii);
+        let color_gradient = set_color_gradient(&ColorGradient::Hawaii, false).unwrap();
         assert_eq!(
             color_gradient.get(0.0),
             Rgb {
@@ -1411,7 +1609,7 @@ mod tests {
                 standard: std::marker::PhantomData
             }
         );
-        let color_gradient = set_color_gradient(&ColorGradient::Imola);
+        let color_gradient = set_color_gradient(&ColorGradient::Imola, false).unwrap();
         assert_eq!(
             color_gradient.get(0.0),
             Rgb {
@@ -1439,7 +1637,7 @@ mod tests {
                 standard: std::marker::PhantomData
             }
         );
-        let color_gradient = set_color_gradient(&ColorGradient::Roma);
+        let color_gradient = set_color_gradient(&ColorGradient::Roma, false).unwrap();
         assert_eq!(
             color_gradient.get(0.0),
             Rgb {
@@ -1467,7 +1665,7 @@ mod tests {
                 standard: std::marker::PhantomData
             }
         );
-        let color_gradient = set_color_gradient(&ColorGradient::Simple);
+        let color_gradient = set_color_gradient(&ColorGradient::Simple, false).unwrap();
         let one_eleventh = 1. / 11.;
         assert_eq!(
             color_gradient.get(0.0),
@@ -1496,7 +1694,7 @@ mod tests {
                 standard: std::marker::PhantomData
             }
         );
-        let color_gradient = set_color_gradient(&ColorGradient::Vik);
+        let color_gradient = set_color_gradient(&ColorGradient::Vik, false).unwrap();
         assert_eq!(
             color_gradient.get(0.0),
             Rgb {
@@ -1525,4 +1723,100 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_reverse() {
+        let color_gradient = set_color_gradient(&ColorGradient::Batlow, false).unwrap();
+        let reversed = set_color_gradient(&ColorGradient::Batlow, true).unwrap();
+        assert_eq!(reversed.get(0.0), color_gradient.get(1.0));
+        assert_eq!(reversed.get(1.0), color_gradient.get(0.0));
+        assert_eq!(reversed.get(0.25), color_gradient.get(0.75));
+    }
+
+    #[test]
+    fn test_list_colors() {
+        let colors: ColorGradient =
+            toml::from_str::<std::collections::HashMap<String, ColorGradient>>(
+                r##"color_scale = { List = ["#000000", [1.0, 0.5, 0.0], "#ffffff"] }"##,
+            )
+            .unwrap()["color_scale"]
+                .clone();
+        let color_gradient = set_color_gradient(&colors, false).unwrap();
+        assert_eq!(color_gradient.get(0.0), LinSrgb::new(0.0, 0.0, 0.0));
+        assert_eq!(color_gradient.get(0.5), LinSrgb::new(1.0, 0.5, 0.0));
+        assert_eq!(color_gradient.get(0.75), LinSrgb::new(1.0, 0.75, 0.5));
+        assert_eq!(colors.file_label(true), "List_r");
+
+        assert!(set_color_gradient(
+            &ColorGradient::List(vec![ListColor::Hex("#00ff".to_string())]),
+            false
+        )
+        .is_err());
+        assert!(set_color_gradient(
+            &ColorGradient::List(vec![ListColor::Rgb([0.0, 0.0, 0.0])]),
+            false
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_cpt() {
+        let stops = parse_cpt(
+            "# COLOR_MODEL = RGB\n\
+             0 0 0 0 1 255 0 0\n\
+             1 255/0/0 3 255/255/255 L\n\
+             B 0 0 0\n\
+             F 255 255 255\n\
+             N 128 128 128\n",
+        )
+        .unwrap();
+        assert_eq!(stops.len(), 4);
+        assert_eq!(stops[1], (1.0, LinSrgb::new(1.0, 0.0, 0.0)));
+        assert_eq!(stops[3], (3.0, LinSrgb::new(1.0, 1.0, 1.0)));
+        assert!(parse_cpt("0 0 0 0 1 255").is_err());
+
+        // the positions are scaled to the range from zero to one
+        let path = std::env::temp_dir().join("cpo_analyzer_test_color_map.cpt");
+        fs::write(&path, "0 0 0 0 1 255 0 0\n1 255 0 0 3 255 255 255\n").unwrap();
+        let color_gradient = set_color_gradient(
+            &ColorGradient::File {
+                path: path.to_string_lossy().into_owned(),
+            },
+            false,
+        )
+        .unwrap();
+        let color = color_gradient.get(2.0 / 3.0);
+        assert_eq!(color.red, 1.0);
+        assert!((color.green - 0.5).abs() < 1e-6 && (color.blue - 0.5).abs() < 1e-6);
+        fs::remove_file(&path).unwrap();
+        assert!(set_color_gradient(
+            &ColorGradient::File {
+                path: path.to_string_lossy().into_owned(),
+            },
+            false,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_rgb_table() {
+        let stops = parse_rgb_table("0.0 0.0 0.0\n0.5 0.5 0.5\n1.0 1.0 1.0\n").unwrap();
+        assert_eq!(stops[1], (0.5, LinSrgb::new(0.5, 0.5, 0.5)));
+        // values up to 255
+        let stops = parse_rgb_table("0 0 0\n255 51 0\n").unwrap();
+        assert_eq!(stops[1], (1.0, LinSrgb::new(1.0, 0.2, 0.0)));
+        assert!(parse_rgb_table("0 0\n").is_err());
+    }
+
+    #[test]
+    fn test_file_label() {
+        assert_eq!(ColorGradient::Batlow.file_label(false), "Batlow");
+        assert_eq!(
+            ColorGradient::File {
+                path: "maps/lapaz.cpt".to_string()
+            }
+            .file_label(true),
+            "lapaz_r"
+        );
+    }
 }
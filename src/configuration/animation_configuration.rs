@@ -0,0 +1,98 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use serde_derive::Deserialize;
+
+/// The file format of an animation.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum AnimationFormat {
+    /// An animated GIF.
+    Gif,
+    /// A PNG file for every frame and a text file listing the frames in order, for an external encoder like
+    /// `convert -delay 20 @frames.txt animation.gif` or `ffmpeg -i ..._frame%05d.png animation.mp4`.
+    PngFrames,
+}
+
+/// The configuration of the animation of the pole figures of a particle over time. Every time step of the time data
+/// file in the time range is a frame. The pole figures are drawn with the settings of the pole figure configuration,
+/// except that the header only shows the particle id and the time, and that all the frames have the same color
+/// scale: the fixed range of the pole figure configuration, or otherwise from zero to the maximum of all the frames.
+#[derive(Deserialize, Clone)]
+pub struct AnimationConfiguration {
+    /// The id of the particle to animate.
+    pub particle_id: usize,
+
+    /// Optional value of the time of the first frame. The closest time step is used.
+    /// The default is the first time step.
+    pub start_time: Option<f64>,
+
+    /// Optional value of the time of the last frame. The closest time step is used.
+    /// The default is the last time step.
+    pub end_time: Option<f64>,
+
+    /// Optional value of the number of time steps from one frame to the next.
+    /// The default value is 1.
+    #[serde(default = "time_step_interval")]
+    pub time_step_interval: usize,
+
+    /// Optional value of the time every frame of the GIF is shown, in milliseconds.
+    /// The default value is 200.
+    #[serde(default = "frame_delay")]
+    pub frame_delay: u32,
+
+    /// Optional value of the output format, `Gif` or `PngFrames`.
+    /// The default value is `Gif`.
+    #[serde(default = "format")]
+    pub format: AnimationFormat,
+}
+
+impl Default for AnimationConfiguration {
+    fn default() -> Self {
+        AnimationConfiguration {
+            particle_id: 0,
+            start_time: None,
+            end_time: None,
+            time_step_interval: 1,
+            frame_delay: 200,
+            format: AnimationFormat::Gif,
+        }
+    }
+}
+
+fn time_step_interval() -> usize {
+    AnimationConfiguration {
+        ..AnimationConfiguration::default()
+    }
+    .time_step_interval
+}
+
+fn frame_delay() -> u32 {
+    AnimationConfiguration {
+        ..AnimationConfiguration::default()
+    }
+    .frame_delay
+}
+
+fn format() -> AnimationFormat {
+    AnimationConfiguration {
+        ..AnimationConfiguration::default()
+    }
+    .format
+}
@@ -0,0 +1,74 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use serde_derive::Deserialize;
+
+/// A dimension along which the pole figures of a comparison figure are laid out. The values of a dimension are
+/// the `particle_ids`, `times`, `minerals` and `axes` of the pole figure configuration and the
+/// `experiment_dirs` of the configuration.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ComparisonDimension {
+    Particle,
+    Time,
+    Experiment,
+    Mineral,
+    Axis,
+}
+
+/// The configuration of figures which compare the pole figures of several particles, times or experiments. The
+/// pole figures along the `rows` dimension are drawn below each other and the pole figures along the `columns`
+/// dimension next to each other. A figure is made for every combination of the values of the other three
+/// dimensions, and all the pole figures of a figure share the color scale: the fixed range of the pole figure
+/// configuration, or otherwise from zero to the maximum of the figure.
+#[derive(Deserialize, Clone)]
+pub struct ComparisonConfiguration {
+    /// Optional value of the dimension of the rows.
+    /// The default value is `Particle`.
+    #[serde(default = "rows")]
+    pub rows: ComparisonDimension,
+
+    /// Optional value of the dimension of the columns, which has to differ from the rows.
+    /// The default value is `Time`.
+    #[serde(default = "columns")]
+    pub columns: ComparisonDimension,
+}
+
+impl Default for ComparisonConfiguration {
+    fn default() -> Self {
+        ComparisonConfiguration {
+            rows: ComparisonDimension::Particle,
+            columns: ComparisonDimension::Time,
+        }
+    }
+}
+
+fn rows() -> ComparisonDimension {
+    ComparisonConfiguration {
+        ..ComparisonConfiguration::default()
+    }
+    .rows
+}
+
+fn columns() -> ComparisonDimension {
+    ComparisonConfiguration {
+        ..ComparisonConfiguration::default()
+    }
+    .columns
+}
@@ -18,7 +18,13 @@
   <http://www.gnu.org/licenses/>.
 */
 
+use crate::configuration::animation_configuration::AnimationConfiguration;
+use crate::configuration::comparison_configuration::ComparisonConfiguration;
+use crate::configuration::elasticity_configuration::ElasticityConfiguration;
+use crate::configuration::fabric_configuration::FabricConfiguration;
+use crate::configuration::inverse_pole_figure_configuration::InversePoleFiguresConfiguration;
 use crate::configuration::pole_figure_configuration::PoleFiguresConfiguration;
+use crate::configuration::sks_splitting_configuration::SksSplittingConfiguration;
 use serde_derive::Deserialize;
 /// Global configuration file containing the information on where to find, read and how to analyze the CPO data.
 #[derive(Deserialize)]
@@ -29,6 +35,33 @@ pub struct Config {
     pub experiment_dirs: Vec<String>,
     /// Pole figure configuration options.
     pub pole_figures: Option<PoleFiguresConfiguration>,
+    /// Inverse pole figure configuration options.
+    pub inverse_pole_figures: Option<InversePoleFiguresConfiguration>,
+    /// Fabric analysis configuration options.
+    pub fabric: Option<FabricConfiguration>,
+    /// Elasticity analysis configuration options.
+    pub elasticity: Option<ElasticityConfiguration>,
+    /// SKS splitting configuration options.
+    pub sks_splitting: Option<SksSplittingConfiguration>,
+    /// Animation configuration options.
+    pub animation: Option<AnimationConfiguration>,
+    /// Comparison figure configuration options.
+    pub comparison: Option<ComparisonConfiguration>,
     /// Whether the CPO data has been compressed.
     pub compressed: bool,
+    /// Optional number of threads used to process the particles. All combinations of experiments, times and
+    /// particles are processed in parallel. By default the number of logical cores is used.
+    pub number_of_threads: Option<usize>,
+    /// Optional global limit in megabytes on the memory of all the tasks which run at the same time. The memory of a
+    /// task is estimated from the grains, the (inverse) pole figures, the temporary array of the densities and the
+    /// ODF of its particle, and a task only starts when its estimate fits in this limit together with the estimates
+    /// of the running tasks. The data of the files from which the grains are read is not included. By default the
+    /// number of tasks which run at the same time is only limited by the number of threads.
+    pub max_memory_mb: Option<usize>,
+    /// Optional size in megabytes of the temporary array of the angles between the grains and the grid points,
+    /// which every task uses to compute the densities of the (inverse) pole figures. The grains of a particle
+    /// are processed in chunks for which this array fits in this size. By default it is `max_memory_mb` divided by
+    /// the number of threads if a memory limit is set, and otherwise all the grains of a particle are processed at
+    /// once.
+    pub density_chunk_memory_mb: Option<usize>,
 }
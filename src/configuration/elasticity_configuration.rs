@@ -0,0 +1,173 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use crate::pole_figures::minerals::Mineral;
+
+use ndarray::{arr2, Array2};
+use serde_derive::Deserialize;
+/// The configuration of the elasticity analysis. The stiffness of the aggregate is computed from the grain
+/// orientations of both olivine and enstatite for the times and particles set in the pole figure configuration.
+#[derive(Deserialize, Clone)]
+pub struct ElasticityConfiguration {
+    /// Optional value of the file, relative to the experiment dir, to which the 21 independent components of the
+    /// Voigt, Reuss and Hill averaged stiffness of every particle and time are written.
+    /// The default value is `elastic_tensor.dat`.
+    #[serde(default = "output_file")]
+    pub output_file: String,
+
+    /// Optional value of the file, relative to the experiment dir, to which the hexagonal symmetry axis of the
+    /// Voigt averaged stiffness of every particle and time is written, as a unit vector and as trend and plunge.
+    /// The default value is `hexagonal_axis.dat`.
+    #[serde(default = "hexagonal_axis_output_file")]
+    pub hexagonal_axis_output_file: String,
+
+    /// Optional value of the file, relative to the experiment dir, to which the radial and azimuthal anisotropy
+    /// parameters (Montagner and Nataf, 1986) of the Voigt averaged stiffness of every particle and time are written.
+    /// The default value is `seismic_anisotropy.dat`.
+    #[serde(default = "seismic_anisotropy_output_file")]
+    pub seismic_anisotropy_output_file: String,
+
+    /// Optional value of the vertical direction in the sample frame, used for the radial and azimuthal anisotropy.
+    /// The azimuth is measured from the projection of the X axis onto the horizontal plane (or of the Y axis if
+    /// the vertical direction is X). The default value is `[0, 0, 1]`, the Z axis.
+    #[serde(default = "vertical_direction")]
+    pub vertical_direction: [f64; 3],
+
+    /// Optional value of the volume fraction of olivine. The rest of the aggregate is enstatite.
+    /// The default value is 0.7.
+    #[serde(default = "olivine_volume_fraction")]
+    pub olivine_volume_fraction: f64,
+
+    /// Optional value of the single crystal stiffness of olivine in GPa, as a 6x6 matrix in Voigt notation in
+    /// the crystal frame. The default values are the ones used by D-Rex and ASPECT.
+    pub olivine_stiffness: Option<[[f64; 6]; 6]>,
+
+    /// Optional value of the single crystal stiffness of enstatite in GPa, as a 6x6 matrix in Voigt notation in
+    /// the crystal frame. The default values are the ones used by D-Rex and ASPECT.
+    pub enstatite_stiffness: Option<[[f64; 6]; 6]>,
+
+    /// Optional value whether the elasticity information in the header of the pole figures is computed from the
+    /// Voigt averaged stiffness of the grains with the symmetry decomposition of Browaeys and Chevrot (2004),
+    /// instead of being read from the norm columns of the ASPECT particle data files.
+    /// The default value is false.
+    #[serde(default = "default_false")]
+    pub compute_header: bool,
+
+    /// Optional value of the density of the aggregate in kg/m³, used to compute the seismic velocities. The default
+    /// is the volume weighted average of the densities of olivine (3355 kg/m³) and enstatite (3200 kg/m³).
+    pub density: Option<f64>,
+
+    /// Optional value whether to make figures of the seismic velocities (Vp, Vs1, Vs2 and the shear wave
+    /// anisotropy with the polarization of the fast shear wave) of the Voigt averaged stiffness, computed with the
+    /// Christoffel equation on the grid of the pole figures. The figures are written to the figure output dir of
+    /// the pole figure configuration.
+    /// The default value is false.
+    #[serde(default = "default_false")]
+    pub velocity_figures: bool,
+}
+
+impl ElasticityConfiguration {
+    /// Return the single crystal stiffness of a mineral, either from the configuration or the default values.
+    pub fn stiffness(&self, mineral: &Mineral) -> Array2<f64> {
+        let configured = match mineral {
+            Mineral::Olivine => self.olivine_stiffness,
+            Mineral::Enstatite => self.enstatite_stiffness,
+        };
+        match configured {
+            Some(stiffness) => arr2(&stiffness),
+            None => mineral.stiffness(),
+        }
+    }
+
+    /// Return the density of the aggregate, either from the configuration or from the densities of the minerals.
+    pub fn density(&self) -> f64 {
+        match self.density {
+            Some(density) => density,
+            None => [Mineral::Olivine, Mineral::Enstatite]
+                .iter()
+                .map(|mineral| self.volume_fraction(mineral) * mineral.density())
+                .sum(),
+        }
+    }
+
+    /// Return the volume fraction of a mineral.
+    pub fn volume_fraction(&self, mineral: &Mineral) -> f64 {
+        match mineral {
+            Mineral::Olivine => self.olivine_volume_fraction,
+            Mineral::Enstatite => 1. - self.olivine_volume_fraction,
+        }
+    }
+}
+
+impl Default for ElasticityConfiguration {
+    fn default() -> Self {
+        ElasticityConfiguration {
+            output_file: "elastic_tensor.dat".to_string(),
+            hexagonal_axis_output_file: "hexagonal_axis.dat".to_string(),
+            seismic_anisotropy_output_file: "seismic_anisotropy.dat".to_string(),
+            vertical_direction: [0., 0., 1.],
+            olivine_volume_fraction: 0.7,
+            olivine_stiffness: None,
+            enstatite_stiffness: None,
+            compute_header: false,
+            density: None,
+            velocity_figures: false,
+        }
+    }
+}
+
+fn output_file() -> String {
+    ElasticityConfiguration {
+        ..ElasticityConfiguration::default()
+    }
+    .output_file
+}
+
+fn hexagonal_axis_output_file() -> String {
+    ElasticityConfiguration {
+        ..ElasticityConfiguration::default()
+    }
+    .hexagonal_axis_output_file
+}
+
+fn seismic_anisotropy_output_file() -> String {
+    ElasticityConfiguration {
+        ..ElasticityConfiguration::default()
+    }
+    .seismic_anisotropy_output_file
+}
+
+fn vertical_direction() -> [f64; 3] {
+    ElasticityConfiguration {
+        ..ElasticityConfiguration::default()
+    }
+    .vertical_direction
+}
+
+fn olivine_volume_fraction() -> f64 {
+    ElasticityConfiguration {
+        ..ElasticityConfiguration::default()
+    }
+    .olivine_volume_fraction
+}
+
+fn default_false() -> bool {
+    false
+}
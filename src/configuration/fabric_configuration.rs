@@ -0,0 +1,179 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use serde_derive::Deserialize;
+/// The configuration of the fabric analysis. The analysis is done for the times, particles and minerals set in the
+/// pole figure configuration.
+#[derive(Deserialize, Clone)]
+pub struct FabricConfiguration {
+    /// Optional value of the file, relative to the experiment dir, to which the fabric strength indices (J-index,
+    /// M-index, ODF entropy and maximum ODF density) of every particle, time and mineral are written. The default
+    /// value is `fabric_strength.dat`.
+    #[serde(default = "strength_output_file")]
+    pub strength_output_file: String,
+
+    /// Optional value of the file, relative to the experiment dir, to which the eigenvalues and eigenvectors of the
+    /// orientation tensor of every crystal axis, and the Vollmer point (P), girdle (G) and random (R) proportions and
+    /// cylindricity index (B) derived from them, are written. The default value is `orientation_tensor.dat`.
+    #[serde(default = "orientation_tensor_output_file")]
+    pub orientation_tensor_output_file: String,
+
+    /// Optional value of the file, relative to the experiment dir, to which the olivine BA-index and fabric type of
+    /// every particle and time are written, together with the olivine deformation type of the particle in the
+    /// simulation. The default value is `fabric_type.dat`.
+    #[serde(default = "fabric_type_output_file")]
+    pub fabric_type_output_file: String,
+
+    /// Optional value of the shear direction in the sample frame used to classify the olivine fabric type.
+    /// The default value is the X direction, which is horizontal in the pole figures.
+    #[serde(default = "shear_direction")]
+    pub shear_direction: [f64; 3],
+
+    /// Optional value of the shear plane normal in the sample frame used to classify the olivine fabric type.
+    /// The default value is the Z direction, which is vertical in the pole figures.
+    #[serde(default = "shear_plane_normal")]
+    pub shear_plane_normal: [f64; 3],
+
+    /// Optional value of the resolution in degree of the grid in Euler space on which the orientation distribution
+    /// function (ODF) for the J-index, ODF entropy and maximum ODF density is computed. The default value is 10 degree.
+    #[serde(default = "odf_resolution")]
+    pub odf_resolution: f64,
+
+    /// Optional value of the halfwidth in degree of the kernel used to compute the orientation distribution function
+    /// (ODF). The default value is 10 degree.
+    #[serde(default = "odf_halfwidth")]
+    pub odf_halfwidth: f64,
+
+    /// Optional value of the maximum number of uncorrelated grain pairs used to compute the M-index. If there are more
+    /// possible pairs, random pairs are used. The default value is 10000.
+    #[serde(default = "misorientation_pairs")]
+    pub misorientation_pairs: usize,
+
+    /// Optional value of the file, relative to the experiment dir, to which the distributions of uncorrelated
+    /// misorientation angles of every particle, time and mineral are written, together with the theoretical
+    /// distribution for randomly oriented grains. The particles do not store which grains are neighbours, so
+    /// correlated misorientation angles can not be computed. The default value is `misorientation.dat`.
+    #[serde(default = "misorientation_output_file")]
+    pub misorientation_output_file: String,
+
+    /// Optional value of the width in degree of the bins of the misorientation angle distributions. The width is
+    /// adjusted so that the bins exactly cover the range up to the maximum misorientation angle. The default value
+    /// is 5 degree.
+    #[serde(default = "misorientation_bin_width")]
+    pub misorientation_bin_width: f64,
+
+    /// Optional value whether to plot the misorientation angle distributions of every particle and time. The
+    /// figures are written to the figure output dir of the pole figure configuration.
+    /// Default is false.
+    #[serde(default = "default_false")]
+    pub misorientation_figures: bool,
+}
+
+impl Default for FabricConfiguration {
+    fn default() -> Self {
+        FabricConfiguration {
+            strength_output_file: "fabric_strength.dat".to_string(),
+            orientation_tensor_output_file: "orientation_tensor.dat".to_string(),
+            fabric_type_output_file: "fabric_type.dat".to_string(),
+            shear_direction: [1.0, 0.0, 0.0],
+            shear_plane_normal: [0.0, 0.0, 1.0],
+            odf_resolution: 10.0,
+            odf_halfwidth: 10.0,
+            misorientation_pairs: 10000,
+            misorientation_output_file: "misorientation.dat".to_string(),
+            misorientation_bin_width: 5.0,
+            misorientation_figures: false,
+        }
+    }
+}
+
+fn strength_output_file() -> String {
+    FabricConfiguration {
+        ..FabricConfiguration::default()
+    }
+    .strength_output_file
+}
+
+fn orientation_tensor_output_file() -> String {
+    FabricConfiguration {
+        ..FabricConfiguration::default()
+    }
+    .orientation_tensor_output_file
+}
+
+fn fabric_type_output_file() -> String {
+    FabricConfiguration {
+        ..FabricConfiguration::default()
+    }
+    .fabric_type_output_file
+}
+
+fn shear_direction() -> [f64; 3] {
+    FabricConfiguration {
+        ..FabricConfiguration::default()
+    }
+    .shear_direction
+}
+
+fn shear_plane_normal() -> [f64; 3] {
+    FabricConfiguration {
+        ..FabricConfiguration::default()
+    }
+    .shear_plane_normal
+}
+
+fn odf_resolution() -> f64 {
+    FabricConfiguration {
+        ..FabricConfiguration::default()
+    }
+    .odf_resolution
+}
+
+fn odf_halfwidth() -> f64 {
+    FabricConfiguration {
+        ..FabricConfiguration::default()
+    }
+    .odf_halfwidth
+}
+
+fn misorientation_pairs() -> usize {
+    FabricConfiguration {
+        ..FabricConfiguration::default()
+    }
+    .misorientation_pairs
+}
+
+fn misorientation_output_file() -> String {
+    FabricConfiguration {
+        ..FabricConfiguration::default()
+    }
+    .misorientation_output_file
+}
+
+fn misorientation_bin_width() -> f64 {
+    FabricConfiguration {
+        ..FabricConfiguration::default()
+    }
+    .misorientation_bin_width
+}
+
+fn default_false() -> bool {
+    false
+}
@@ -0,0 +1,54 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use crate::pole_figures::sample_direction::SampleDirection;
+
+use serde_derive::Deserialize;
+/// The configuration of the inverse pole figures. The inverse pole figures are made for the times, particles and
+/// minerals set in the pole figure configuration, and use the same output directory, figure size and color scale.
+#[derive(Deserialize, Clone)]
+pub struct InversePoleFiguresConfiguration {
+    /// Optional value of the prefix of the produced inverse pole figures. The program will add a postfix containing
+    /// information about individual variables and settings such as the timestep and sample directions.
+    /// The default value is `weighted_IPF`.
+    #[serde(default = "figure_output_prefix")]
+    pub figure_output_prefix: String,
+
+    /// A vector of the sample directions to plot. This will be plot on the horizontal axis of the plot, and the
+    /// maximum of the maximum count of the inverse pole figures will be used as the new maximum to scale the
+    /// colors. Available options are `X`, `Y`, `Z` and any direction given as `{ Vector = [1.0, 0.0, 1.0] }`.
+    pub sample_directions: Vec<SampleDirection>,
+}
+
+impl Default for InversePoleFiguresConfiguration {
+    fn default() -> Self {
+        InversePoleFiguresConfiguration {
+            figure_output_prefix: "weighted_IPF".to_string(),
+            sample_directions: vec![],
+        }
+    }
+}
+
+fn figure_output_prefix() -> String {
+    InversePoleFiguresConfiguration {
+        ..InversePoleFiguresConfiguration::default()
+    }
+    .figure_output_prefix
+}
@@ -0,0 +1,226 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use serde_derive::Deserialize;
+
+/// The layout of the pole figure figures. The sizes are in pixels before the `scale` is applied, and the sizes
+/// which are not set depend on `small_figure` of the pole figure configuration.
+#[derive(Deserialize, Clone)]
+pub struct LayoutConfiguration {
+    /// Optional value of the width and height of a pole figure in pixels.
+    /// The default value is 500 for a small figure and 800 otherwise.
+    pub panel_size: Option<u32>,
+
+    /// Optional value of the factor all the sizes in pixels, including the font sizes and line widths, are
+    /// multiplied with, to get a higher or lower resolution with the same layout.
+    /// The default value is 1.0.
+    #[serde(default = "scale")]
+    pub scale: f64,
+
+    /// Optional value of the font family of all the text.
+    /// The default value is `helvetica`.
+    #[serde(default = "font_family")]
+    pub font_family: String,
+
+    /// Optional value of the font size of the header.
+    /// The default value is 28 for a small figure and 45 otherwise.
+    pub header_font_size: Option<u32>,
+
+    /// Optional value of the font size of the text on the pole figures and of the color bars.
+    /// The default value is 30 for a small figure and 50 otherwise.
+    pub panel_font_size: Option<u32>,
+
+    /// Optional value of the width of the vertical color bars.
+    /// The default value is 150 for a small figure and 200 otherwise.
+    pub color_bar_width: Option<u32>,
+
+    /// Optional value of the height of a horizontal color bar.
+    /// The default value is 160 for a small figure and 250 otherwise.
+    pub color_bar_height: Option<u32>,
+
+    /// Optional value of the number of evenly spaced colors the color bars are drawn with.
+    /// The default value is 150.
+    #[serde(default = "color_bar_steps")]
+    pub color_bar_steps: u32,
+
+    /// Optional value of the margin above the first line of text of the header and of a pole figure, in percent of
+    /// the panel size.
+    /// The default value is 0.25.
+    #[serde(default = "top_margin")]
+    pub top_margin: f64,
+
+    /// Optional value of the margin left of the text of the header and of a pole figure, in percent of the panel
+    /// size.
+    /// The default value is 0.5.
+    #[serde(default = "left_margin")]
+    pub left_margin: f64,
+
+    /// Optional value of the distance between the lines of text, in percent of the panel size.
+    /// The default value is 5.5.
+    #[serde(default = "line_distance")]
+    pub line_distance: f64,
+
+    /// Optional value of the distance between the five columns of the symmetry decomposition in the elasticity
+    /// header, in percent of the panel size. By default the columns are spread evenly over the width of the figure.
+    pub header_column_distance: Option<f64>,
+
+    /// Optional value of the template of the first line of the header, which replaces the particle information. The
+    /// placeholders `{id}`, `{time}`, `{grains}`, `{x}`, `{y}`, `{z}`, `{odt}` and `{title}` are replaced by the
+    /// particle id, the time, the number of grains, the position, the olivine deformation type and the title of a
+    /// comparison figure. If it is set, the line is also written when there is no other header.
+    pub header_template: Option<String>,
+}
+
+impl LayoutConfiguration {
+    /// Return a size in pixels multiplied with the scale.
+    pub fn scaled(&self, size: u32) -> u32 {
+        (size as f64 * self.scale).round() as u32
+    }
+
+    /// Return the width and height of a pole figure in pixels.
+    pub fn panel_size(&self, small_figure: bool) -> u32 {
+        self.scaled(
+            self.panel_size
+                .unwrap_or(if small_figure { 500 } else { 800 }),
+        )
+    }
+
+    /// Return the font size of the header.
+    pub fn header_font_size(&self, small_figure: bool) -> u32 {
+        self.scaled(
+            self.header_font_size
+                .unwrap_or(if small_figure { 28 } else { 45 }),
+        )
+    }
+
+    /// Return the font size of the text on the pole figures and of the color bars.
+    pub fn panel_font_size(&self, small_figure: bool) -> u32 {
+        self.scaled(
+            self.panel_font_size
+                .unwrap_or(if small_figure { 30 } else { 50 }),
+        )
+    }
+
+    /// Return the width of the vertical color bars.
+    pub fn color_bar_width(&self, small_figure: bool) -> u32 {
+        self.scaled(
+            self.color_bar_width
+                .unwrap_or(if small_figure { 150 } else { 200 }),
+        )
+    }
+
+    /// Return the height of a horizontal color bar.
+    pub fn color_bar_height(&self, small_figure: bool) -> u32 {
+        self.scaled(
+            self.color_bar_height
+                .unwrap_or(if small_figure { 160 } else { 250 }),
+        )
+    }
+}
+
+impl Default for LayoutConfiguration {
+    fn default() -> Self {
+        LayoutConfiguration {
+            panel_size: None,
+            scale: 1.0,
+            font_family: "helvetica".to_string(),
+            header_font_size: None,
+            panel_font_size: None,
+            color_bar_width: None,
+            color_bar_height: None,
+            color_bar_steps: 150,
+            top_margin: 0.25,
+            left_margin: 0.5,
+            line_distance: 5.5,
+            header_column_distance: None,
+            header_template: None,
+        }
+    }
+}
+
+fn scale() -> f64 {
+    LayoutConfiguration {
+        ..LayoutConfiguration::default()
+    }
+    .scale
+}
+
+fn font_family() -> String {
+    LayoutConfiguration {
+        ..LayoutConfiguration::default()
+    }
+    .font_family
+}
+
+fn color_bar_steps() -> u32 {
+    LayoutConfiguration {
+        ..LayoutConfiguration::default()
+    }
+    .color_bar_steps
+}
+
+fn top_margin() -> f64 {
+    LayoutConfiguration {
+        ..LayoutConfiguration::default()
+    }
+    .top_margin
+}
+
+fn left_margin() -> f64 {
+    LayoutConfiguration {
+        ..LayoutConfiguration::default()
+    }
+    .left_margin
+}
+
+fn line_distance() -> f64 {
+    LayoutConfiguration {
+        ..LayoutConfiguration::default()
+    }
+    .line_distance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizes() {
+        let layout = LayoutConfiguration::default();
+        assert_eq!(layout.panel_size(true), 500);
+        assert_eq!(layout.panel_size(false), 800);
+        assert_eq!(layout.header_font_size(true), 28);
+        assert_eq!(layout.panel_font_size(false), 50);
+
+        let layout = LayoutConfiguration {
+            panel_size: Some(400),
+            scale: 1.5,
+            ..LayoutConfiguration::default()
+        };
+        assert_eq!(layout.panel_size(true), 600);
+        assert_eq!(layout.panel_size(false), 600);
+        assert_eq!(layout.color_bar_width(true), 225);
+        assert_eq!(layout.scaled(3), 5);
+
+        let layout: LayoutConfiguration = toml::from_str("scale = 2.0").unwrap();
+        assert_eq!(layout.color_bar_steps, 150);
+        assert_eq!(layout.header_column_distance, None);
+    }
+}
@@ -18,8 +18,15 @@
   <http://www.gnu.org/licenses/>.
 */
 
+pub mod animation_configuration;
+pub mod comparison_configuration;
 pub mod config;
+pub mod elasticity_configuration;
+pub mod fabric_configuration;
+pub mod inverse_pole_figure_configuration;
+pub mod layout_configuration;
 pub mod opt;
 pub mod particle_record;
 pub mod pole_figure_configuration;
 pub mod record;
+pub mod sks_splitting_configuration;
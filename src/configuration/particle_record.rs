@@ -20,7 +20,7 @@
 
 use serde_derive::Deserialize;
 /// A structure to hold the particle data, including the id, position, deformation type, and optionally elasticity information.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct ParticleRecord {
     pub id: usize,
     pub x: f64,
@@ -21,9 +21,79 @@
 use std::vec;
 
 use crate::color_gradients::*;
+use crate::configuration::layout_configuration::LayoutConfiguration;
 use crate::pole_figures::{crystal_axis::CrystalAxes, minerals::Mineral};
 
 use serde_derive::Deserialize;
+
+/// The file format of the pole figures.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum OutputFormat {
+    /// A bitmap image.
+    Png,
+    /// A vector image, with the density field embedded as a bitmap.
+    Svg,
+}
+
+impl OutputFormat {
+    /// Return the file extension of the format.
+    pub fn extension(&self) -> &str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Svg => "svg",
+        }
+    }
+}
+
+/// The range of the values covered by the color scale of the pole figures. Values outside of the range get the
+/// color of the closest end of the range.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum ColorScaleRange {
+    /// From zero to the maximum of every pole figure separately.
+    Subplot,
+    /// From zero to the maximum of all the pole figures of a mineral in a figure.
+    Row,
+    /// From zero to the maximum of all the pole figures in a figure.
+    Figure,
+    /// From zero to the maximum of all the pole figures of all the times and particles in a run.
+    Global,
+    /// A fixed range in multiples of uniform density, given as `{ Fixed = { min = 0.0, max = 3.0 } }`.
+    Fixed { min: f64, max: f64 },
+}
+
+/// The mapping of the values of the pole figures to the colors of the color scale.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum ColorScaling {
+    /// The position on the color scale is linear in the value.
+    Linear,
+    /// The position on the color scale is the fraction of the range to the power of the exponent, given as
+    /// `{ Power = { exponent = 0.5 } }`.
+    Power { exponent: f64 },
+    /// The position on the color scale is linear in the logarithm of the value. If the minimum of the range is not
+    /// positive, the range starts at a hundredth of the maximum.
+    Log,
+}
+
+impl ColorScaling {
+    /// Return the label of the scaling in the file name of the pole figures.
+    pub fn file_label(&self) -> String {
+        match self {
+            ColorScaling::Linear => "g1".to_string(),
+            ColorScaling::Power { exponent } => format!("g{}", exponent),
+            ColorScaling::Log => "log".to_string(),
+        }
+    }
+}
+
+/// The placement of the color bars of the pole figures.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum ColorBarOrientation {
+    /// A vertical color bar to the right of every row of pole figures.
+    Vertical,
+    /// A horizontal color bar for every row of pole figures below all the pole figures.
+    Horizontal,
+}
+
 /// The configuration of the pole figure analysis.
 #[derive(Deserialize, Clone)]
 pub struct PoleFiguresConfiguration {
@@ -67,21 +137,140 @@ pub struct PoleFiguresConfiguration {
     pub figure_output_prefix: String,
 
     /// Optional value of the color gradient. Available options are `Vik`, `Batlow`, `Simple`, `Imola`, `Hawaii` and `Roma`,
-    /// which are all, except for `Simple`, from [http://www.fabiocrameri.ch/colourmaps.php](http://www.fabiocrameri.ch/colourmaps.php).
+    /// which are all, except for `Simple`, from [http://www.fabiocrameri.ch/colourmaps.php](http://www.fabiocrameri.ch/colourmaps.php),
+    /// a color map file given as `{ File = { path = "lapaz.cpt" } }`, which is a GMT `.cpt` file or a table of RGB
+    /// values, or a list of colors given as `{ List = ["#2a0bd9", [1.0, 1.0, 0.75], "#a60021"] }`. The other maps
+    /// of the Scientific colour maps, like lapaz, oslo and tokyo, are not built in and have to be read from their
+    /// `.cpt` files.
     /// The default value is `Batlow`.
     #[serde(default = "color_scale")]
     pub color_scale: ColorGradient,
 
+    /// Optional value whether to reverse the color gradient, so that the low values get the last color.
+    /// Default is false.
+    #[serde(default = "default_false")]
+    pub reverse_color_scale: bool,
+
+    /// Optional value of the file format of the pole figures, `Png` or `Svg`. The SVG format is meant for
+    /// publications: the layout is the same, but the text and lines are vector graphics.
+    /// The default value is `Png`.
+    #[serde(default = "output_format")]
+    pub output_format: OutputFormat,
+
+    /// Optional value of the range of the color scale, `Subplot`, `Row`, `Figure`, `Global` or a fixed range given
+    /// as `{ Fixed = { min = 0.0, max = 3.0 } }`. The `Row` range is the same for all the pole figures of a mineral
+    /// in a figure, and the `Global` range is the same for all the figures of a run, so that they can be compared.
+    /// A `Global` range takes two passes: the first pass computes the pole figures of all the times and particles
+    /// and only keeps their maximum, and the second pass computes the pole figures again and draws them with the
+    /// range from zero to the maximum of the first pass. If values are outside of the range, this is marked with an
+    /// arrow on the color scale.
+    /// The default value is `Row`.
+    #[serde(default = "color_scale_range")]
+    pub color_scale_range: ColorScaleRange,
+
+    /// Optional value of the mapping of the values to the colors, `Linear`, `Log` or a power law given as
+    /// `{ Power = { exponent = 0.5 } }`. An exponent smaller than one shows more detail in the low values.
+    /// The default value is `Linear`.
+    #[serde(default = "color_scaling")]
+    pub color_scaling: ColorScaling,
+
+    /// Optional value of the title of the color bars. The values of the pole figures are in multiples of uniform
+    /// density (MUD).
+    /// The default value is `MUD`.
+    #[serde(default = "color_bar_title")]
+    pub color_bar_title: String,
+
+    /// Optional value of the placement of the color bars, `Vertical` to the right of every row of pole figures or
+    /// `Horizontal` below all the pole figures.
+    /// The default value is `Vertical`.
+    #[serde(default = "color_bar_orientation")]
+    pub color_bar_orientation: ColorBarOrientation,
+
+    /// Optional value whether to smooth the density field of the pole figures by bilinear interpolation between
+    /// the grid points. Without smoothing every grid cell has a single color.
+    /// Default is false.
+    #[serde(default = "default_false")]
+    pub smooth_density: bool,
+
+    /// Optional value of the levels at which to draw contour lines on the pole figures, in multiples of uniform
+    /// density (MUD), for example `[1.0, 2.0, 3.0, 4.0]`. No contour lines are drawn if this is empty.
+    /// The default value is empty.
+    #[serde(default = "contour_levels")]
+    pub contour_levels: Vec<f64>,
+
+    /// Optional value whether to label every contour line with its level in MUD.
+    /// Default is false.
+    #[serde(default = "default_false")]
+    pub contour_labels: bool,
+
+    /// Optional value whether to only draw the contour lines, without the colors of the density field and the color
+    /// scale, for black and white figures.
+    /// Default is false.
+    #[serde(default = "default_false")]
+    pub contours_only: bool,
+
+    /// Optional value whether to draw the projected axis of every grain as a point on the pole figures. This is
+    /// useful for a small number of grains, for which the density field is misleading. To only draw the points, use
+    /// `contours_only` without contour levels.
+    /// Default is false.
+    #[serde(default = "default_false")]
+    pub scatter: bool,
+
+    /// Optional value of the radius in pixels of the points of the grains in the scatter plot.
+    /// The default value is 4.
+    #[serde(default = "scatter_point_size")]
+    pub scatter_point_size: f64,
+
+    /// Optional value whether to scale the area of the points in the scatter plot with the volume fraction of the
+    /// grains, relative to the average volume fraction. The volume fractions are read from the
+    /// `mineral_0_volume_fraction` and `mineral_1_volume_fraction` columns of the grain data if present, otherwise
+    /// all grains have the same size.
+    /// Default is false.
+    #[serde(default = "default_false")]
+    pub scatter_size_by_volume_fraction: bool,
+
+    /// Optional value whether to color the points in the scatter plot by mineral, green for olivine and orange for
+    /// enstatite, instead of black.
+    /// Default is false.
+    #[serde(default = "default_false")]
+    pub scatter_color_by_mineral: bool,
+
     /// Optional value whether to inluce elasticity information in the header of the polefigure.
     /// Default is true.
     #[serde(default = "default_true")]
     pub elastisity_header: bool,
 
-    /// Optional value whether to create a small (500x500 per pole figure) or normal (800x800 per pole figure) figure size.
+    /// Optional value whether to include the fabric strength indices (J-index and M-index) of the minerals in the
+    /// header of the polefigure. The indices are computed with the settings of the fabric configuration, or with the
+    /// default settings if there is no fabric configuration.
+    /// Default is false.
+    #[serde(default = "default_false")]
+    pub fabric_strength_header: bool,
+
+    /// Optional value whether to draw the hexagonal symmetry axis of the Voigt averaged stiffness as a marker on
+    /// every pole figure. A filled circle marks a fast axis and an open circle a slow axis. This requires an
+    /// elasticity configuration.
+    /// Default is false.
+    #[serde(default = "default_false")]
+    pub hexagonal_axis_marker: bool,
+
+    /// Optional value whether to include the radial anisotropy parameters (xi, phi and eta) and the amplitudes of
+    /// the azimuthal anisotropy of the Voigt averaged stiffness in the header of the polefigure. This requires an
+    /// elasticity configuration.
     /// Default is false.
     #[serde(default = "default_false")]
+    pub seismic_anisotropy_header: bool,
+
+    /// Optional value whether to create a small (500x500 per pole figure) or normal (800x800 per pole figure) figure size.
+    /// The sizes which are set in the `layout` take precedence. Default is false.
+    #[serde(default = "default_false")]
     pub small_figure: bool,
 
+    /// Optional value of the layout of the figures: the sizes, the fonts, the margins and the header. See
+    /// `LayoutConfiguration`.
+    #[serde(default = "layout")]
+    pub layout: LayoutConfiguration,
+
     /// Optional value whether to omit mineral and axis information to the figure.
     /// Default value is false.
     #[serde(default = "default_false")]
@@ -96,7 +285,10 @@ pub struct PoleFiguresConfiguration {
 
     /// A vector of the crytal axis to plot. This will be plot on the horizontal axis of the plot, and the maximum
     /// of the maximum count of the pole figures will be used as the new maximum to scale the colors. Available options
-    /// are `AAxis`, `BAxis` and `CAxis`.
+    /// are `AAxis`, `BAxis` and `CAxis`, and Miller indices of a direction or the pole to a plane, given as
+    /// `{ Direction = [1, 0, 1] }` for [101] or `{ Plane = [0, 1, 0] }` for (010). To plot all the symmetrically
+    /// equivalent directions or planes together, use `DirectionFamily` or `PlaneFamily`, e.g.
+    /// `{ PlaneFamily = [1, 1, 0] }` for {110}.
     pub axes: Vec<CrystalAxes>,
 
     /// A vector containing the minerals to be plot. These will be added as a vertical axis to the plot. Available
@@ -113,8 +305,26 @@ impl Default for PoleFiguresConfiguration {
             figure_output_dir: "CPO_figures/".to_string(),
             figure_output_prefix: "weighted_LPO".to_string(),
             color_scale: ColorGradient::Batlow,
+            reverse_color_scale: false,
+            output_format: OutputFormat::Png,
+            color_scale_range: ColorScaleRange::Row,
+            color_scaling: ColorScaling::Linear,
+            color_bar_title: "MUD".to_string(),
+            color_bar_orientation: ColorBarOrientation::Vertical,
+            smooth_density: false,
+            contour_levels: vec![],
+            contour_labels: false,
+            contours_only: false,
+            scatter: false,
+            scatter_point_size: 4.0,
+            scatter_size_by_volume_fraction: false,
+            scatter_color_by_mineral: false,
             elastisity_header: true,
+            fabric_strength_header: false,
+            hexagonal_axis_marker: false,
+            seismic_anisotropy_header: false,
             small_figure: false,
+            layout: LayoutConfiguration::default(),
             no_description_text: false,
             times: vec![],
             particle_ids: vec![],
@@ -132,6 +342,55 @@ fn default_true() -> bool {
     true
 }
 
+fn output_format() -> OutputFormat {
+    PoleFiguresConfiguration {
+        ..PoleFiguresConfiguration::default()
+    }
+    .output_format
+}
+
+fn color_scale_range() -> ColorScaleRange {
+    PoleFiguresConfiguration {
+        ..PoleFiguresConfiguration::default()
+    }
+    .color_scale_range
+}
+
+fn color_scaling() -> ColorScaling {
+    PoleFiguresConfiguration {
+        ..PoleFiguresConfiguration::default()
+    }
+    .color_scaling
+}
+
+fn color_bar_title() -> String {
+    PoleFiguresConfiguration {
+        ..PoleFiguresConfiguration::default()
+    }
+    .color_bar_title
+}
+
+fn color_bar_orientation() -> ColorBarOrientation {
+    PoleFiguresConfiguration {
+        ..PoleFiguresConfiguration::default()
+    }
+    .color_bar_orientation
+}
+
+fn contour_levels() -> Vec<f64> {
+    PoleFiguresConfiguration {
+        ..PoleFiguresConfiguration::default()
+    }
+    .contour_levels
+}
+
+fn scatter_point_size() -> f64 {
+    PoleFiguresConfiguration {
+        ..PoleFiguresConfiguration::default()
+    }
+    .scatter_point_size
+}
+
 fn time_data_file() -> String {
     PoleFiguresConfiguration {
         ..PoleFiguresConfiguration::default()
@@ -167,6 +426,13 @@ fn figure_output_prefix() -> String {
     .figure_output_prefix
 }
 
+fn layout() -> LayoutConfiguration {
+    PoleFiguresConfiguration {
+        ..PoleFiguresConfiguration::default()
+    }
+    .layout
+}
+
 fn color_scale() -> ColorGradient {
     PoleFiguresConfiguration {
         ..PoleFiguresConfiguration::default()
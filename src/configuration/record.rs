@@ -30,4 +30,7 @@ pub struct Record {
     pub mineral_1_EA_phi: Option<f64>,
     pub mineral_1_EA_theta: Option<f64>,
     pub mineral_1_EA_z: Option<f64>,
+    /// The volume fraction of the grains, if the file contains it.
+    pub mineral_0_volume_fraction: Option<f64>,
+    pub mineral_1_volume_fraction: Option<f64>,
 }
@@ -0,0 +1,158 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+use serde_derive::Deserialize;
+
+/// The method used to compute the SKS splitting of a column of particles.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum SplittingMethod {
+    /// The elastic tensors of the particles are averaged, weighted by the thickness of their layers, and the
+    /// splitting is computed for a single layer with the total thickness.
+    SingleLayer,
+    /// Every particle is a layer with its own splitting operator, and the apparent splitting of the stack of
+    /// layers is computed for each back-azimuth.
+    MultiLayer,
+}
+
+/// The configuration of the SKS splitting prediction. The particles form a vertical column, and a layer is made
+/// around each of them from their positions. The elastic tensors and the vertical direction are computed with the
+/// settings of the elasticity configuration, or with the default settings if there is no elasticity configuration.
+/// The splitting is computed for the times set in the pole figure configuration.
+#[derive(Deserialize, Clone)]
+pub struct SksSplittingConfiguration {
+    /// Optional value of the file, relative to the experiment dir, to which the apparent fast polarization
+    /// direction and delay time as a function of back-azimuth are written for every time.
+    /// The default value is `sks_splitting.dat`.
+    #[serde(default = "output_file")]
+    pub output_file: String,
+
+    /// Optional value of the ids of the particles in the column. These particles also need to be in the particle
+    /// ids of the pole figure configuration. The default is all the particle ids of the pole figure configuration.
+    pub particle_ids: Option<Vec<usize>>,
+
+    /// Optional value of the method, `SingleLayer` or `MultiLayer`. The default value is `MultiLayer`.
+    #[serde(default = "method")]
+    pub method: SplittingMethod,
+
+    /// Optional value of the dominant period in seconds of the SKS wave, which sets the frequency of the splitting
+    /// operators of the layers. The default value is 8.
+    #[serde(default = "period", deserialize_with = "positive")]
+    pub period: f64,
+
+    /// Optional value of the step in degrees between the back-azimuths from 0 to 180 degree. The apparent
+    /// splitting of the column is periodic over 180 degree in back-azimuth. The default value is 10.
+    #[serde(default = "back_azimuth_step", deserialize_with = "positive")]
+    pub back_azimuth_step: f64,
+
+    /// Optional value of the thickness in meters of every layer. By default the layer boundaries are halfway
+    /// between the particles, which requires at least two particles.
+    #[serde(default, deserialize_with = "optional_positive")]
+    pub layer_thickness: Option<f64>,
+}
+
+impl Default for SksSplittingConfiguration {
+    fn default() -> Self {
+        SksSplittingConfiguration {
+            output_file: "sks_splitting.dat".to_string(),
+            particle_ids: None,
+            method: SplittingMethod::MultiLayer,
+            period: 8.,
+            back_azimuth_step: 10.,
+            layer_thickness: None,
+        }
+    }
+}
+
+fn output_file() -> String {
+    SksSplittingConfiguration {
+        ..SksSplittingConfiguration::default()
+    }
+    .output_file
+}
+
+fn method() -> SplittingMethod {
+    SksSplittingConfiguration {
+        ..SksSplittingConfiguration::default()
+    }
+    .method
+}
+
+fn period() -> f64 {
+    SksSplittingConfiguration {
+        ..SksSplittingConfiguration::default()
+    }
+    .period
+}
+
+fn back_azimuth_step() -> f64 {
+    SksSplittingConfiguration {
+        ..SksSplittingConfiguration::default()
+    }
+    .back_azimuth_step
+}
+
+/// Deserialize a value which has to be larger than zero.
+fn positive<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    check_positive(f64::deserialize(deserializer)?)
+}
+
+/// Deserialize an optional value which has to be larger than zero if it is given.
+fn optional_positive<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<f64>::deserialize(deserializer)?
+        .map(check_positive)
+        .transpose()
+}
+
+fn check_positive<E: Error>(value: f64) -> Result<f64, E> {
+    if value > 0. {
+        Ok(value)
+    } else {
+        Err(E::custom(format!(
+            "the value has to be larger than zero, but is {}",
+            value
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positive_values() {
+        let configuration: SksSplittingConfiguration =
+            toml::from_str("period = 10.0\nlayer_thickness = 5000.0").unwrap();
+        assert_eq!(configuration.period, 10.);
+        assert_eq!(configuration.back_azimuth_step, 10.);
+        assert_eq!(configuration.layer_thickness, Some(5000.));
+
+        assert!(toml::from_str::<SksSplittingConfiguration>("back_azimuth_step = 0.0").is_err());
+        assert!(toml::from_str::<SksSplittingConfiguration>("period = -8.0").is_err());
+        assert!(toml::from_str::<SksSplittingConfiguration>("layer_thickness = 0.0").is_err());
+    }
+}
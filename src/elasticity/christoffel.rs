@@ -0,0 +1,157 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use crate::linear_algebra::symmetric_eigen;
+use crate::pole_figures::lambert::Lambert;
+
+use ndarray::{Array1, Array2, Array3};
+
+/// Stores the seismic phase velocities in km/s for propagation directions on the grid points of a [`Lambert`]
+/// grid, computed with the Christoffel equation from an elastic tensor and a density.
+pub struct SeismicVelocities {
+    /// The P-wave velocity.
+    pub vp: Array2<f64>,
+    /// The velocity of the fast shear wave.
+    pub vs1: Array2<f64>,
+    /// The velocity of the slow shear wave.
+    pub vs2: Array2<f64>,
+    /// The shear wave anisotropy in percent: `200 (vs1 - vs2) / (vs1 + vs2)`.
+    pub shear_wave_anisotropy: Array2<f64>,
+    /// The unit polarization vector of the fast shear wave, in the last dimension of the array.
+    pub vs1_polarization: Array3<f64>,
+}
+
+impl SeismicVelocities {
+    /// Compute the phase velocities for all the directions of the Lambert grid from a stiffness matrix in GPa in
+    /// Voigt notation and a density in kg/m³.
+    pub fn on_lambert_grid(
+        stiffness: &Array2<f64>,
+        density: f64,
+        lambert: &Lambert,
+    ) -> SeismicVelocities {
+        let npts = lambert.sphere_points;
+        let mut vp = Array2::zeros((npts, npts));
+        let mut vs1 = Array2::zeros((npts, npts));
+        let mut vs2 = Array2::zeros((npts, npts));
+        let mut shear_wave_anisotropy = Array2::zeros((npts, npts));
+        let mut vs1_polarization = Array3::zeros((npts, npts, 3));
+        for i in 0..npts {
+            for j in 0..npts {
+                let direction = [lambert.x[[i, j]], lambert.y[[i, j]], lambert.z[[i, j]]];
+                let (velocities, polarizations) = phase_velocities(stiffness, density, &direction);
+                vp[[i, j]] = velocities[0];
+                vs1[[i, j]] = velocities[1];
+                vs2[[i, j]] = velocities[2];
+                shear_wave_anisotropy[[i, j]] =
+                    200. * (velocities[1] - velocities[2]) / (velocities[1] + velocities[2]);
+                for k in 0..3 {
+                    vs1_polarization[[i, j, k]] = polarizations[[k, 1]];
+                }
+            }
+        }
+        SeismicVelocities {
+            vp,
+            vs1,
+            vs2,
+            shear_wave_anisotropy,
+            vs1_polarization,
+        }
+    }
+}
+
+/// Solve the Christoffel equation for a propagation direction, given a stiffness matrix in GPa in Voigt notation
+/// and a density in kg/m³. Returns the velocities in km/s of the P-wave and of the fast and slow shear waves, and
+/// their unit polarization vectors in the columns of the second array in the same order.
+pub fn phase_velocities(
+    stiffness: &Array2<f64>,
+    density: f64,
+    direction: &[f64; 3],
+) -> (Array1<f64>, Array2<f64>) {
+    let length = direction.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let n = [
+        direction[0] / length,
+        direction[1] / length,
+        direction[2] / length,
+    ];
+    // the Voigt index of the component ij of the fourth order tensor.
+    let index = |i: usize, j: usize| if i == j { i } else { 6 - i - j };
+
+    // the Christoffel matrix M_ik = C_ijkl n_j n_l / rho, in km²/s² for C in GPa and rho in g/cm³.
+    let mut christoffel = Array2::zeros((3, 3));
+    for i in 0..3 {
+        for k in 0..3 {
+            for j in 0..3 {
+                for l in 0..3 {
+                    christoffel[[i, k]] += stiffness[[index(i, j), index(k, l)]] * n[j] * n[l];
+                }
+            }
+        }
+    }
+    christoffel /= density / 1000.;
+
+    let (eigenvalues, eigenvectors) = symmetric_eigen(&christoffel);
+    (eigenvalues.mapv(|x| x.max(0.).sqrt()), eigenvectors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pole_figures::lambert::create_lambert_equal_area_gridpoint;
+    use crate::pole_figures::minerals::Mineral;
+
+    #[test]
+    fn test_phase_velocities() {
+        // olivine along the crystal axes: the P-wave moduli are C11, C22 and C33.
+        let olivine = Mineral::Olivine.stiffness();
+        let density = Mineral::Olivine.density();
+        for (axis, direction) in [[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]]
+            .iter()
+            .enumerate()
+        {
+            let (velocities, polarizations) = phase_velocities(&olivine, density, direction);
+            let expected = (olivine[[axis, axis]] / (density / 1000.)).sqrt();
+            assert!((velocities[0] - expected).abs() < 1e-10);
+            assert!((polarizations[[axis, 0]].abs() - 1.).abs() < 1e-10);
+            assert!(velocities[1] >= velocities[2]);
+        }
+
+        // an isotropic medium has the same velocities in all directions, and no shear wave anisotropy.
+        let (lambda, mu, density) = (100., 70., 3300.);
+        let mut isotropic = Array2::zeros((6, 6));
+        for i in 0..3 {
+            for j in 0..3 {
+                isotropic[[i, j]] = lambda;
+            }
+            isotropic[[i, i]] += 2. * mu;
+            isotropic[[i + 3, i + 3]] = mu;
+        }
+        let lambert = create_lambert_equal_area_gridpoint(11, "upper".to_string()).unwrap();
+        let velocities = SeismicVelocities::on_lambert_grid(&isotropic, density, &lambert);
+        let vp = ((lambda + 2. * mu) / (density / 1000.)).sqrt();
+        let vs = (mu / (density / 1000.)).sqrt();
+        assert!(velocities.vp.iter().all(|v| (v - vp).abs() < 1e-8));
+        assert!(velocities.vs1.iter().all(|v| (v - vs).abs() < 1e-8));
+        assert!(velocities.vs2.iter().all(|v| (v - vs).abs() < 1e-8));
+        assert!(velocities
+            .shear_wave_anisotropy
+            .iter()
+            .all(|a| a.abs() < 1e-8));
+    }
+}
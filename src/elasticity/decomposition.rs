@@ -0,0 +1,353 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use crate::elasticity::stiffness::rotate_stiffness;
+use crate::linear_algebra::symmetric_eigen;
+
+use ndarray::{Array1, Array2};
+
+/// Stores the decomposition of an elastic tensor into the symmetry classes of Browaeys and Chevrot (2004). The
+/// values are the squares of the norms of the parts of the tensor belonging to each symmetry class, in GPa².
+/// The anisotropic parts are computed for the three choices of the symmetry axis (the x3 axis) of the
+/// symmetry cartesian coordinate system (SCCS), which are stored in the same order as the rows of `sccs`.
+#[derive(Clone, Debug)]
+pub struct SymmetryDecomposition {
+    /// The axes of the symmetry cartesian coordinate system in the rows.
+    pub sccs: Array2<f64>,
+    pub full_norm_square: f64,
+    pub triclinic_norm_square: [f64; 3],
+    pub monoclinic_norm_square: [f64; 3],
+    pub orthorhombic_norm_square: [f64; 3],
+    pub tetragonal_norm_square: [f64; 3],
+    pub hexagonal_norm_square: [f64; 3],
+    pub isotropic_norm_square: f64,
+    /// The axis of the SCCS with the largest hexagonal part.
+    pub hexagonal_axis: HexagonalSymmetryAxis,
+}
+
+/// The symmetry axis of the hexagonal part of an elastic tensor, which is the SCCS axis for which the tensor is
+/// closest to hexagonal symmetry.
+#[derive(Clone, Debug)]
+pub struct HexagonalSymmetryAxis {
+    /// The unit vector of the axis in the sample frame, with a positive Y component.
+    pub vector: Array1<f64>,
+    /// The trend in degree, measured clockwise from the Z axis in the X-Z plane of the pole figure.
+    pub trend: f64,
+    /// The plunge in degree, which is the angle with the X-Z plane of the pole figure.
+    pub plunge: f64,
+    /// Whether the P-wave velocity of the hexagonal tensor is larger along the axis (a fast axis) than
+    /// normal to it (a slow axis).
+    pub fast: bool,
+}
+
+impl HexagonalSymmetryAxis {
+    fn from_vector(vector: Array1<f64>, fast: bool) -> HexagonalSymmetryAxis {
+        let vector = if vector[1] < 0. { -vector } else { vector };
+        let trend = vector[0].atan2(vector[2]).to_degrees();
+        let plunge = vector[1].clamp(-1., 1.).asin().to_degrees();
+        HexagonalSymmetryAxis {
+            trend: if trend < 0. { trend + 360. } else { trend },
+            plunge,
+            vector,
+            fast,
+        }
+    }
+}
+
+impl SymmetryDecomposition {
+    /// Decompose a stiffness matrix in Voigt notation. The tensor is rotated into the symmetry cartesian
+    /// coordinate system, and for each permutation of its axes the 21 dimensional vector of the tensor is
+    /// successively projected onto the monoclinic, orthorhombic, tetragonal, hexagonal and isotropic symmetry
+    /// classes. The part belonging to a class is the difference between two successive projections.
+    pub fn from_stiffness(stiffness: &Array2<f64>) -> SymmetryDecomposition {
+        let sccs = symmetry_cartesian_coordinate_system(stiffness);
+        let full_norm_square = norm_square(&stiffness_to_vector(stiffness));
+
+        let mut triclinic_norm_square = [0.; 3];
+        let mut monoclinic_norm_square = [0.; 3];
+        let mut orthorhombic_norm_square = [0.; 3];
+        let mut tetragonal_norm_square = [0.; 3];
+        let mut hexagonal_norm_square = [0.; 3];
+        let mut isotropic_norm_square = 0.;
+        let mut hexagonal_axis = HexagonalSymmetryAxis::from_vector(sccs.row(2).to_owned(), true);
+        let mut hexagonal_permutation = 0;
+        for permutation in 0..3 {
+            // cyclic permutations of the SCCS axes, in the same order as ASPECT, so that the symmetry axis
+            // x3 is the third, first and second axis of the SCCS.
+            let mut frame = Array2::zeros((3, 3));
+            for i in 0..3 {
+                frame.row_mut(i).assign(&sccs.row((permutation + i) % 3));
+            }
+            // rotate_stiffness rotates from the frame of the rows to the frame of the columns.
+            let rotated = rotate_stiffness(stiffness, &frame.t().to_owned());
+            let vector = stiffness_to_vector(&rotated);
+
+            let monoclinic = project_monoclinic(&vector);
+            let orthorhombic = project_orthorhombic(&monoclinic);
+            let tetragonal = project_tetragonal(&orthorhombic);
+            let hexagonal = project_hexagonal(&tetragonal);
+            let isotropic = project_isotropic(&hexagonal);
+
+            triclinic_norm_square[permutation] = norm_square(&(&vector - &monoclinic));
+            monoclinic_norm_square[permutation] = norm_square(&(&monoclinic - &orthorhombic));
+            orthorhombic_norm_square[permutation] = norm_square(&(&orthorhombic - &tetragonal));
+            tetragonal_norm_square[permutation] = norm_square(&(&tetragonal - &hexagonal));
+            hexagonal_norm_square[permutation] = norm_square(&(&hexagonal - &isotropic));
+            isotropic_norm_square = norm_square(&isotropic);
+
+            // the hexagonal part is largest for the symmetry axis which makes the tensor closest to hexagonal.
+            if permutation == 0
+                || hexagonal_norm_square[permutation] > hexagonal_norm_square[hexagonal_permutation]
+            {
+                hexagonal_permutation = permutation;
+                // C33 and C11 of the hexagonal tensor are the P-wave moduli along and normal to the axis.
+                hexagonal_axis = HexagonalSymmetryAxis::from_vector(
+                    frame.row(2).to_owned(),
+                    hexagonal[2] > hexagonal[0],
+                );
+            }
+        }
+
+        SymmetryDecomposition {
+            sccs,
+            full_norm_square,
+            triclinic_norm_square,
+            monoclinic_norm_square,
+            orthorhombic_norm_square,
+            tetragonal_norm_square,
+            hexagonal_norm_square,
+            isotropic_norm_square,
+            hexagonal_axis,
+        }
+    }
+}
+
+/// Convert a stiffness matrix in Voigt notation to the 21 dimensional vector of Browaeys and Chevrot (2004),
+/// whose Euclidean norm is the norm of the fourth order tensor.
+pub fn stiffness_to_vector(c: &Array2<f64>) -> Array1<f64> {
+    let sqrt_2 = 2_f64.sqrt();
+    Array1::from(vec![
+        c[[0, 0]],
+        c[[1, 1]],
+        c[[2, 2]],
+        sqrt_2 * c[[1, 2]],
+        sqrt_2 * c[[0, 2]],
+        sqrt_2 * c[[0, 1]],
+        2. * c[[3, 3]],
+        2. * c[[4, 4]],
+        2. * c[[5, 5]],
+        2. * c[[0, 3]],
+        2. * c[[1, 4]],
+        2. * c[[2, 5]],
+        2. * c[[2, 3]],
+        2. * c[[0, 4]],
+        2. * c[[1, 5]],
+        2. * c[[1, 3]],
+        2. * c[[2, 4]],
+        2. * c[[0, 5]],
+        2. * sqrt_2 * c[[4, 5]],
+        2. * sqrt_2 * c[[3, 5]],
+        2. * sqrt_2 * c[[3, 4]],
+    ])
+}
+
+/// Compute the symmetry cartesian coordinate system (SCCS) of a stiffness matrix in Voigt notation. Each axis
+/// is the bisectrix of an eigenvector of the dilatational stiffness tensor `C_ijkk` and the closest eigenvector
+/// of the Voigt stiffness tensor `C_ikjk`, and the axes are made orthonormal. The axes are returned in the rows.
+pub fn symmetry_cartesian_coordinate_system(c: &Array2<f64>) -> Array2<f64> {
+    let mut dilatation = Array2::zeros((3, 3));
+    let mut voigt = Array2::zeros((3, 3));
+    // the Voigt indices of the components ij, and of the pairs (i, k) and (k, j).
+    let index = |i: usize, j: usize| if i == j { i } else { 6 - i - j };
+    for i in 0..3 {
+        for j in 0..3 {
+            for k in 0..3 {
+                dilatation[[i, j]] += c[[index(i, j), index(k, k)]];
+                voigt[[i, j]] += c[[index(i, k), index(j, k)]];
+            }
+        }
+    }
+    let (_, dilatation_eigenvectors) = symmetric_eigen(&dilatation);
+    let (_, voigt_eigenvectors) = symmetric_eigen(&voigt);
+
+    let mut sccs: Array2<f64> = Array2::zeros((3, 3));
+    for i in 0..3 {
+        let dilatation_eigenvector = dilatation_eigenvectors.column(i);
+        let (closest, cosine) = (0..3)
+            .map(|j| (j, dilatation_eigenvector.dot(&voigt_eigenvectors.column(j))))
+            .fold((0, 0.), |closest: (usize, f64), (j, cosine)| {
+                if cosine.abs() > closest.1.abs() {
+                    (j, cosine)
+                } else {
+                    closest
+                }
+            });
+        let sign = if cosine < 0. { -1. } else { 1. };
+        let bisectrix = &dilatation_eigenvector + &(&voigt_eigenvectors.column(closest) * sign);
+        sccs.row_mut(i).assign(&bisectrix);
+    }
+
+    // Gram-Schmidt orthonormalization, with the third axis completing a right handed system.
+    let first = &sccs.row(0) / sccs.row(0).dot(&sccs.row(0)).sqrt();
+    let second = &sccs.row(1) - &(&first * first.dot(&sccs.row(1)));
+    let second = &second / second.dot(&second).sqrt();
+    let third = Array1::from(vec![
+        first[1] * second[2] - first[2] * second[1],
+        first[2] * second[0] - first[0] * second[2],
+        first[0] * second[1] - first[1] * second[0],
+    ]);
+    sccs.row_mut(0).assign(&first);
+    sccs.row_mut(1).assign(&second);
+    sccs.row_mut(2).assign(&third);
+    sccs
+}
+
+/// Project a 21 dimensional elastic vector onto the isotropic symmetry class.
+pub fn project_isotropic(x: &Array1<f64>) -> Array1<f64> {
+    let sqrt_2 = 2_f64.sqrt();
+    let a = x[0] + x[1] + x[2];
+    let b = x[3] + x[4] + x[5];
+    let c = x[6] + x[7] + x[8];
+    let mut projection = Array1::zeros(21);
+    let normal = 3. / 15. * a + sqrt_2 / 15. * b + 2. / 15. * c;
+    let off_diagonal = sqrt_2 / 15. * a + 4. / 15. * b - sqrt_2 / 15. * c;
+    let shear = 2. / 15. * a - sqrt_2 / 15. * b + 1. / 5. * c;
+    for i in 0..3 {
+        projection[i] = normal;
+        projection[i + 3] = off_diagonal;
+        projection[i + 6] = shear;
+    }
+    projection
+}
+
+/// Project a 21 dimensional elastic vector onto the hexagonal symmetry class with the symmetry axis along x3.
+pub fn project_hexagonal(x: &Array1<f64>) -> Array1<f64> {
+    let sqrt_2 = 2_f64.sqrt();
+    let mut projection = Array1::zeros(21);
+    projection[0] = 3. / 8. * (x[0] + x[1]) + x[5] / (4. * sqrt_2) + x[8] / 4.;
+    projection[1] = projection[0];
+    projection[2] = x[2];
+    projection[3] = (x[3] + x[4]) / 2.;
+    projection[4] = projection[3];
+    projection[5] = (x[0] + x[1]) / (4. * sqrt_2) + 3. / 4. * x[5] - x[8] / (2. * sqrt_2);
+    projection[6] = (x[6] + x[7]) / 2.;
+    projection[7] = projection[6];
+    projection[8] = (x[0] + x[1]) / 4. - x[5] / (2. * sqrt_2) + x[8] / 2.;
+    projection
+}
+
+/// Project a 21 dimensional elastic vector onto the tetragonal symmetry class with the symmetry axis along x3.
+pub fn project_tetragonal(x: &Array1<f64>) -> Array1<f64> {
+    let mut projection = Array1::zeros(21);
+    projection[0] = (x[0] + x[1]) / 2.;
+    projection[1] = projection[0];
+    projection[2] = x[2];
+    projection[3] = (x[3] + x[4]) / 2.;
+    projection[4] = projection[3];
+    projection[5] = x[5];
+    projection[6] = (x[6] + x[7]) / 2.;
+    projection[7] = projection[6];
+    projection[8] = x[8];
+    projection
+}
+
+/// Project a 21 dimensional elastic vector onto the orthorhombic symmetry class with the symmetry planes normal
+/// to the coordinate axes.
+pub fn project_orthorhombic(x: &Array1<f64>) -> Array1<f64> {
+    let mut projection = Array1::zeros(21);
+    for i in 0..9 {
+        projection[i] = x[i];
+    }
+    projection
+}
+
+/// Project a 21 dimensional elastic vector onto the monoclinic symmetry class with the symmetry axis along x3.
+pub fn project_monoclinic(x: &Array1<f64>) -> Array1<f64> {
+    let mut projection = project_orthorhombic(x);
+    // the components C36, C26, C16 and C45
+    for i in &[11, 14, 17, 20] {
+        projection[*i] = x[*i];
+    }
+    projection
+}
+
+fn norm_square(vector: &Array1<f64>) -> f64 {
+    vector.dot(vector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euler_angles_to_rotation_matrix;
+    use crate::pole_figures::minerals::Mineral;
+    use ndarray::Array;
+
+    #[test]
+    fn test_symmetry_decomposition() {
+        // The olivine single crystal is orthorhombic, in any orientation.
+        let olivine = Mineral::Olivine.stiffness();
+        let rotation_matrix =
+            euler_angles_to_rotation_matrix(Array::from(vec![0.3, 1.1, -0.7])).unwrap();
+        let rotated = rotate_stiffness(&olivine, &rotation_matrix);
+        let decomposition = SymmetryDecomposition::from_stiffness(&rotated);
+
+        // the SCCS is the crystal frame
+        let alignment = decomposition.sccs.dot(&rotation_matrix.t());
+        for i in 0..3 {
+            assert!(
+                (alignment.row(i).iter().map(|x| x.abs()).fold(0., f64::max) - 1.).abs() < 1e-8
+            );
+        }
+
+        let full_norm_square = norm_square(&stiffness_to_vector(&olivine));
+        assert!((decomposition.full_norm_square - full_norm_square).abs() < 1e-8);
+        for permutation in 0..3 {
+            assert!(decomposition.triclinic_norm_square[permutation] < 1e-8);
+            assert!(decomposition.monoclinic_norm_square[permutation] < 1e-8);
+            let sum = decomposition.triclinic_norm_square[permutation]
+                + decomposition.monoclinic_norm_square[permutation]
+                + decomposition.orthorhombic_norm_square[permutation]
+                + decomposition.tetragonal_norm_square[permutation]
+                + decomposition.hexagonal_norm_square[permutation]
+                + decomposition.isotropic_norm_square;
+            assert!((sum - full_norm_square).abs() < 1e-6 * full_norm_square);
+        }
+
+        // the hexagonal axis of olivine is the a-axis, along which the P-wave velocity is largest.
+        let a_axis = rotation_matrix.row(0);
+        assert!((decomposition.hexagonal_axis.vector.dot(&a_axis).abs() - 1.).abs() < 1e-8);
+        assert!(decomposition.hexagonal_axis.fast);
+
+        // an isotropic tensor only has an isotropic part.
+        let (lambda, mu) = (100., 70.);
+        let mut isotropic = Array2::zeros((6, 6));
+        for i in 0..3 {
+            for j in 0..3 {
+                isotropic[[i, j]] = lambda;
+            }
+            isotropic[[i, i]] += 2. * mu;
+            isotropic[[i + 3, i + 3]] = mu;
+        }
+        let vector = stiffness_to_vector(&isotropic);
+        assert!((&project_isotropic(&vector) - &vector)
+            .iter()
+            .all(|x| x.abs() < 1e-10));
+    }
+}
@@ -0,0 +1,205 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use crate::elasticity::stiffness::rotate_stiffness;
+
+use ndarray::{arr2, Array2};
+
+/// Stores the radial and azimuthal anisotropy of an elastic tensor in a frame with a vertical x3 axis, following
+/// Montagner and Nataf (1986). The Love parameters `a`, `c`, `f`, `l` and `n` in GPa are the ones of the
+/// equivalent transversely isotropic medium with a vertical symmetry axis. The azimuthal variation of the
+/// parameters with the azimuth `psi` of horizontal propagation, measured from x1 towards x2, is
+/// `A(psi) = a + bc cos(2 psi) + bs sin(2 psi) + ec cos(4 psi) + es sin(4 psi)`,
+/// `L(psi) = l + gc cos(2 psi) + gs sin(2 psi)` and `F(psi) = f + hc cos(2 psi) + hs sin(2 psi)`.
+#[derive(Clone, Debug)]
+pub struct SeismicAnisotropy {
+    pub a: f64,
+    pub c: f64,
+    pub f: f64,
+    pub l: f64,
+    pub n: f64,
+    /// The S-wave radial anisotropy `Vsh²/Vsv² = N/L`.
+    pub xi: f64,
+    /// The P-wave radial anisotropy `Vpv²/Vph² = C/A`.
+    pub phi: f64,
+    /// The anisotropy parameter `F/(A - 2L)`.
+    pub eta: f64,
+    pub bc: f64,
+    pub bs: f64,
+    pub gc: f64,
+    pub gs: f64,
+    pub hc: f64,
+    pub hs: f64,
+    pub ec: f64,
+    pub es: f64,
+}
+
+impl SeismicAnisotropy {
+    /// Compute the anisotropy parameters of a stiffness matrix in Voigt notation in the sample frame. The x3 axis
+    /// is the given vertical direction, and the x1 axis is the projection of the sample X axis (or the Y axis if
+    /// the vertical direction is X) onto the horizontal plane.
+    pub fn from_stiffness(
+        stiffness: &Array2<f64>,
+        vertical_direction: &[f64; 3],
+    ) -> SeismicAnisotropy {
+        let c = rotate_stiffness(
+            stiffness,
+            &vertical_frame(vertical_direction).t().to_owned(),
+        );
+
+        let a = 3. / 8. * (c[[0, 0]] + c[[1, 1]]) + c[[0, 1]] / 4. + c[[5, 5]] / 2.;
+        let cc = c[[2, 2]];
+        let f = (c[[0, 2]] + c[[1, 2]]) / 2.;
+        let l = (c[[3, 3]] + c[[4, 4]]) / 2.;
+        let n = (c[[0, 0]] + c[[1, 1]]) / 8. - c[[0, 1]] / 4. + c[[5, 5]] / 2.;
+
+        SeismicAnisotropy {
+            a,
+            c: cc,
+            f,
+            l,
+            n,
+            xi: n / l,
+            phi: cc / a,
+            eta: f / (a - 2. * l),
+            bc: (c[[0, 0]] - c[[1, 1]]) / 2.,
+            bs: c[[0, 5]] + c[[1, 5]],
+            gc: (c[[4, 4]] - c[[3, 3]]) / 2.,
+            gs: c[[3, 4]],
+            hc: (c[[0, 2]] - c[[1, 2]]) / 2.,
+            hs: c[[2, 5]],
+            ec: (c[[0, 0]] + c[[1, 1]]) / 8. - c[[0, 1]] / 4. - c[[5, 5]] / 2.,
+            es: (c[[0, 5]] - c[[1, 5]]) / 2.,
+        }
+    }
+
+    /// Return the amplitude of the 2θ azimuthal variation of the P-wave modulus A relative to A in percent.
+    pub fn b_amplitude(&self) -> f64 {
+        self.bc.hypot(self.bs) / self.a * 100.
+    }
+
+    /// Return the amplitude of the 2θ azimuthal variation of the SV-wave modulus L relative to L in percent.
+    pub fn g_amplitude(&self) -> f64 {
+        self.gc.hypot(self.gs) / self.l * 100.
+    }
+
+    /// Return the azimuth in degree, from x1 towards x2, of the fastest horizontally propagating SV-wave.
+    pub fn g_fast_azimuth(&self) -> f64 {
+        0.5 * self.gs.atan2(self.gc).to_degrees()
+    }
+
+    /// Return the amplitude of the 4θ azimuthal variation of the P-wave modulus A relative to A in percent.
+    pub fn e_amplitude(&self) -> f64 {
+        self.ec.hypot(self.es) / self.a * 100.
+    }
+}
+
+/// Return the axes of the frame with a vertical x3 axis in the rows.
+pub(crate) fn vertical_frame(vertical_direction: &[f64; 3]) -> Array2<f64> {
+    let normalize = |v: [f64; 3]| {
+        let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+        [v[0] / length, v[1] / length, v[2] / length]
+    };
+    let e3 = normalize(*vertical_direction);
+    let reference = if e3[0].abs() > 0.9 {
+        [0., 1., 0.]
+    } else {
+        [1., 0., 0.]
+    };
+    let dot = reference[0] * e3[0] + reference[1] * e3[1] + reference[2] * e3[2];
+    let e1 = normalize([
+        reference[0] - dot * e3[0],
+        reference[1] - dot * e3[1],
+        reference[2] - dot * e3[2],
+    ]);
+    let e2 = [
+        e3[1] * e1[2] - e3[2] * e1[1],
+        e3[2] * e1[0] - e3[0] * e1[2],
+        e3[0] * e1[1] - e3[1] * e1[0],
+    ];
+    arr2(&[e1, e2, e3])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euler_angles_to_rotation_matrix;
+    use crate::pole_figures::minerals::Mineral;
+    use ndarray::Array;
+
+    #[test]
+    fn test_seismic_anisotropy() {
+        // an isotropic medium has no radial or azimuthal anisotropy.
+        let (lambda, mu) = (100., 70.);
+        let mut isotropic = Array2::zeros((6, 6));
+        for i in 0..3 {
+            for j in 0..3 {
+                isotropic[[i, j]] = lambda;
+            }
+            isotropic[[i, i]] += 2. * mu;
+            isotropic[[i + 3, i + 3]] = mu;
+        }
+        let anisotropy = SeismicAnisotropy::from_stiffness(&isotropic, &[0.3, -0.2, 1.]);
+        assert!((anisotropy.xi - 1.).abs() < 1e-10);
+        assert!((anisotropy.phi - 1.).abs() < 1e-10);
+        assert!((anisotropy.eta - 1.).abs() < 1e-10);
+        assert!(anisotropy.g_amplitude() < 1e-10 && anisotropy.e_amplitude() < 1e-10);
+
+        // olivine with the a-axis horizontal along X: the fastest SV-wave propagates along the a-axis.
+        let olivine = Mineral::Olivine.stiffness();
+        let anisotropy = SeismicAnisotropy::from_stiffness(&olivine, &[0., 0., 1.]);
+        assert!(anisotropy.g_fast_azimuth().abs() < 1e-10);
+        assert!(anisotropy.g_amplitude() > 0.);
+
+        // the azimuthal variation matches the moduli of horizontally propagating waves of a rotated crystal.
+        let rotation_matrix =
+            euler_angles_to_rotation_matrix(Array::from(vec![0.3, 1.1, -0.7])).unwrap();
+        let rotated = rotate_stiffness(&olivine, &rotation_matrix);
+        let anisotropy = SeismicAnisotropy::from_stiffness(&rotated, &[0., 0., 1.]);
+        let index = |i: usize, j: usize| if i == j { i } else { 6 - i - j };
+        for step in 0..12 {
+            let psi = step as f64 * std::f64::consts::PI / 12.;
+            let n = [psi.cos(), psi.sin(), 0.];
+            // the longitudinal modulus C_ijkl n_i n_j n_k n_l and the SV modulus C_i3k3 n_i n_k.
+            let mut longitudinal = 0.;
+            let mut shear_vertical = 0.;
+            for i in 0..3 {
+                for k in 0..3 {
+                    shear_vertical += rotated[[index(i, 2), index(k, 2)]] * n[i] * n[k];
+                    for j in 0..3 {
+                        for l in 0..3 {
+                            longitudinal +=
+                                rotated[[index(i, j), index(k, l)]] * n[i] * n[j] * n[k] * n[l];
+                        }
+                    }
+                }
+            }
+            let a_psi = anisotropy.a
+                + anisotropy.bc * (2. * psi).cos()
+                + anisotropy.bs * (2. * psi).sin()
+                + anisotropy.ec * (4. * psi).cos()
+                + anisotropy.es * (4. * psi).sin();
+            let l_psi =
+                anisotropy.l + anisotropy.gc * (2. * psi).cos() + anisotropy.gs * (2. * psi).sin();
+            assert!((a_psi - longitudinal).abs() < 1e-8);
+            assert!((l_psi - shear_vertical).abs() < 1e-8);
+        }
+    }
+}
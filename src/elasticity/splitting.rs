@@ -0,0 +1,229 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use crate::elasticity::christoffel::phase_velocities;
+use crate::elasticity::seismic_anisotropy::vertical_frame;
+
+use ndarray::{arr1, Array2};
+
+/// The splitting of a vertically propagating shear wave by a single anisotropic layer.
+#[derive(Clone, Debug)]
+pub struct SplittingLayer {
+    /// The azimuth in degree of the polarization of the fast shear wave, measured in the horizontal plane from the
+    /// projection of the X axis (or the Y axis if the vertical direction is X) towards the other horizontal axis.
+    pub fast_azimuth: f64,
+    /// The delay time in seconds between the fast and slow shear waves.
+    pub delay_time: f64,
+}
+
+impl SplittingLayer {
+    /// Compute the splitting of a layer from its stiffness matrix in GPa in Voigt notation, its density in kg/m³
+    /// and its thickness in m, for a shear wave propagating along the vertical direction.
+    pub fn from_stiffness(
+        stiffness: &Array2<f64>,
+        density: f64,
+        thickness: f64,
+        vertical_direction: &[f64; 3],
+    ) -> SplittingLayer {
+        let frame = vertical_frame(vertical_direction);
+        let (velocities, polarizations) = phase_velocities(
+            stiffness,
+            density,
+            &[frame[[2, 0]], frame[[2, 1]], frame[[2, 2]]],
+        );
+        let polarization = polarizations.column(1);
+        let fast_azimuth = polarization
+            .dot(&frame.row(1))
+            .atan2(polarization.dot(&frame.row(0)))
+            .to_degrees();
+        SplittingLayer {
+            fast_azimuth: fast_azimuth.rem_euclid(180.),
+            delay_time: thickness / 1000. * (1. / velocities[2] - 1. / velocities[1]),
+        }
+    }
+}
+
+/// The apparent splitting parameters measured for a shear wave arriving with a given back-azimuth, in degree.
+#[derive(Clone, Debug)]
+pub struct ApparentSplitting {
+    pub back_azimuth: f64,
+    pub fast_azimuth: f64,
+    pub delay_time: f64,
+}
+
+/// Compute the apparent splitting of a stack of layers, ordered from the bottom to the top, for a shear wave with
+/// an initial polarization along the back-azimuth and a dominant period in seconds.
+///
+/// The splitting operator of each layer at the dominant frequency is a unit quaternion, so the operator of the
+/// stack is their product (Silver and Savage, 1994). The apparent parameters are those of the single layer which,
+/// when its splitting is removed, leaves no energy on the transverse component. These depend on the back-azimuth
+/// unless there is only one layer.
+pub fn apparent_splitting(
+    layers: &[SplittingLayer],
+    back_azimuth: f64,
+    period: f64,
+) -> ApparentSplitting {
+    let angular_frequency = 2. * std::f64::consts::PI / period;
+    let q = layers.iter().fold([1., 0., 0., 0.], |q, layer| {
+        let half_delay = angular_frequency * layer.delay_time / 2.;
+        let angle = 2. * layer.fast_azimuth.to_radians();
+        let layer_operator = [
+            half_delay.cos(),
+            half_delay.sin() * angle.cos(),
+            half_delay.sin() * angle.sin(),
+            0.,
+        ];
+        quaternion_product(&layer_operator, &q)
+    });
+
+    // The operator of the stack is split into an apparent layer followed by a layer with its fast axis along
+    // the initial polarization, which does not split the wave. The components of the operator are taken along
+    // and normal to the direction of twice the back-azimuth.
+    let beta = 2. * back_azimuth.to_radians();
+    let parallel = q[1] * beta.cos() + q[2] * beta.sin();
+    let perpendicular = -q[1] * beta.sin() + q[2] * beta.cos();
+    // without a transverse component (a null measurement) the splitting is taken along the back-azimuth.
+    let null_layer = if q[3].hypot(perpendicular) < 1e-12 {
+        0.
+    } else {
+        (-q[3]).atan2(perpendicular)
+    };
+    let (cos_null, sin_null) = (null_layer.cos(), null_layer.sin());
+    let mut cos_delay = q[0] * cos_null + parallel * sin_null;
+    let mut sin_delay_parallel = parallel * cos_null - q[0] * sin_null;
+    let mut sin_delay_perpendicular = perpendicular * cos_null - q[3] * sin_null;
+    // an operator and its negative give the same splitting, so the half delay is chosen between 0 and 90 degrees.
+    if cos_delay < 0. {
+        cos_delay = -cos_delay;
+        sin_delay_parallel = -sin_delay_parallel;
+        sin_delay_perpendicular = -sin_delay_perpendicular;
+    }
+    let half_delay = sin_delay_parallel
+        .hypot(sin_delay_perpendicular)
+        .atan2(cos_delay);
+    let angle = sin_delay_perpendicular.atan2(sin_delay_parallel);
+
+    ApparentSplitting {
+        back_azimuth,
+        fast_azimuth: (back_azimuth + angle.to_degrees() / 2.).rem_euclid(180.),
+        delay_time: 2. * half_delay / angular_frequency,
+    }
+}
+
+/// Return the thickness of the layers around points at the given heights along the vertical. The layer boundaries
+/// are halfway between neighbouring points, and the outer layers extend as far beyond their point as towards their
+/// neighbour. Returns `None` if there are less than two points.
+pub fn layer_thicknesses(heights: &[f64]) -> Option<Vec<f64>> {
+    if heights.len() < 2 {
+        return None;
+    }
+    let n = heights.len();
+    let gap = |i: usize| (heights[i + 1] - heights[i]).abs();
+    Some(
+        (0..n)
+            .map(|i| match i {
+                0 => gap(0),
+                i if i == n - 1 => gap(n - 2),
+                i => (gap(i - 1) + gap(i)) / 2.,
+            })
+            .collect(),
+    )
+}
+
+/// Return the height of a position along the vertical direction.
+pub fn height(position: &[f64; 3], vertical_direction: &[f64; 3]) -> f64 {
+    let frame = vertical_frame(vertical_direction);
+    frame.row(2).dot(&arr1(position))
+}
+
+/// The product of two quaternions `(w, x, y, z)`.
+fn quaternion_product(a: &[f64; 4], b: &[f64; 4]) -> [f64; 4] {
+    [
+        a[0] * b[0] - a[1] * b[1] - a[2] * b[2] - a[3] * b[3],
+        a[0] * b[1] + a[1] * b[0] + a[2] * b[3] - a[3] * b[2],
+        a[0] * b[2] - a[1] * b[3] + a[2] * b[0] + a[3] * b[1],
+        a[0] * b[3] + a[1] * b[2] - a[2] * b[1] + a[3] * b[0],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pole_figures::minerals::Mineral;
+
+    #[test]
+    fn test_splitting() {
+        // olivine with the a-axis along X and the c-axis vertical: the fast shear wave is polarized along a.
+        let olivine = Mineral::Olivine.stiffness();
+        let layer = SplittingLayer::from_stiffness(
+            &olivine,
+            Mineral::Olivine.density(),
+            100e3,
+            &[0., 0., 1.],
+        );
+        let c55 = olivine[[4, 4]];
+        let c44 = olivine[[3, 3]];
+        let density = Mineral::Olivine.density() / 1000.;
+        let expected = 100. * (1. / (c44 / density).sqrt() - 1. / (c55 / density).sqrt());
+        assert!(layer.fast_azimuth.abs() < 1e-8 || (layer.fast_azimuth - 180.).abs() < 1e-8);
+        assert!((layer.delay_time - expected).abs() < 1e-10);
+
+        // a single layer is measured the same for all back-azimuths.
+        let layer = SplittingLayer {
+            fast_azimuth: 30.,
+            delay_time: 1.2,
+        };
+        for back_azimuth in &[0., 20., 75., 130.] {
+            let apparent = apparent_splitting(std::slice::from_ref(&layer), *back_azimuth, 8.);
+            assert!((apparent.fast_azimuth - 30.).abs() < 1e-8);
+            assert!((apparent.delay_time - 1.2).abs() < 1e-8);
+        }
+
+        // two identical layers are a single layer of double thickness, and two layers with perpendicular fast
+        // axes and the same delay time cancel.
+        let apparent = apparent_splitting(&[layer.clone(), layer.clone()], 50., 8.);
+        assert!((apparent.fast_azimuth - 30.).abs() < 1e-8);
+        assert!((apparent.delay_time - 2.4).abs() < 1e-8);
+        let perpendicular = SplittingLayer {
+            fast_azimuth: 120.,
+            delay_time: 1.2,
+        };
+        let apparent = apparent_splitting(&[layer.clone(), perpendicular], 50., 8.);
+        assert!(apparent.delay_time.abs() < 1e-8);
+
+        // two different layers give apparent parameters which vary with a period of 90 degrees in back-azimuth.
+        let lower = SplittingLayer {
+            fast_azimuth: 80.,
+            delay_time: 0.8,
+        };
+        let first = apparent_splitting(&[lower.clone(), layer.clone()], 10., 8.);
+        let second = apparent_splitting(&[lower.clone(), layer.clone()], 100., 8.);
+        let other = apparent_splitting(&[lower, layer], 55., 8.);
+        assert!((first.fast_azimuth - second.fast_azimuth).abs() < 1e-8);
+        assert!((first.delay_time - second.delay_time).abs() < 1e-8);
+        assert!((first.delay_time - other.delay_time).abs() > 1e-3);
+
+        assert_eq!(layer_thicknesses(&[0.]), None);
+        assert_eq!(
+            layer_thicknesses(&[0., 10., 30.]).unwrap(),
+            vec![10., 15., 20.]
+        );
+    }
+}
@@ -0,0 +1,224 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use crate::linear_algebra::invert;
+
+use ndarray::Array2;
+
+/// Stores the Voigt, Reuss and Hill averages of the stiffness of an aggregate, as 6x6 matrices in Voigt notation
+/// in the sample frame, in GPa.
+#[derive(Clone, Debug)]
+pub struct AveragedStiffness {
+    /// The Voigt average, which assumes a uniform strain and is the arithmetic mean of the stiffness.
+    pub voigt: Array2<f64>,
+    /// The Reuss average, which assumes a uniform stress and is the inverse of the arithmetic mean of the
+    /// compliance.
+    pub reuss: Array2<f64>,
+    /// The Hill average, which is the arithmetic mean of the Voigt and Reuss averages.
+    pub hill: Array2<f64>,
+}
+
+/// Return the 6x6 Bond matrix which transforms a stiffness matrix in Voigt notation with the rotation `g`, where
+/// `g[[i, j]]` is the cosine between axis i of the new frame and axis j of the old frame. The rotated stiffness is
+/// `M C M^T` (Auld, 1973).
+pub fn bond_matrix(g: &Array2<f64>) -> Array2<f64> {
+    // the pairs of indices belonging to the Voigt indices 4, 5 and 6.
+    let pairs = [(1, 2), (2, 0), (0, 1)];
+    let mut m = Array2::zeros((6, 6));
+    for i in 0..3 {
+        for j in 0..3 {
+            m[[i, j]] = g[[i, j]] * g[[i, j]];
+        }
+        for (jj, (j, k)) in pairs.iter().enumerate() {
+            m[[i, jj + 3]] = 2. * g[[i, *j]] * g[[i, *k]];
+        }
+    }
+    for (ii, (i, l)) in pairs.iter().enumerate() {
+        for j in 0..3 {
+            m[[ii + 3, j]] = g[[*i, j]] * g[[*l, j]];
+        }
+        for (jj, (j, k)) in pairs.iter().enumerate() {
+            m[[ii + 3, jj + 3]] = g[[*i, *j]] * g[[*l, *k]] + g[[*i, *k]] * g[[*l, *j]];
+        }
+    }
+    m
+}
+
+/// Rotate a single crystal stiffness matrix in Voigt notation from the crystal frame to the sample frame. The
+/// rows of the rotation matrix are the crystal axes in the sample frame, as computed by
+/// `euler_angles_to_rotation_matrix`.
+pub fn rotate_stiffness(stiffness: &Array2<f64>, rotation_matrix: &Array2<f64>) -> Array2<f64> {
+    let m = bond_matrix(&rotation_matrix.t().to_owned());
+    m.dot(stiffness).dot(&m.t())
+}
+
+/// A mineral in an aggregate, used to compute the average stiffness.
+pub struct MineralPhase<'a> {
+    /// The single crystal stiffness in Voigt notation in the crystal frame.
+    pub stiffness: &'a Array2<f64>,
+    /// The rotation matrices of the grains, which all have the same volume.
+    pub rotation_matrices: &'a [Array2<f64>],
+    /// The volume fraction of the mineral in the aggregate.
+    pub volume_fraction: f64,
+}
+
+/// Compute the Voigt, Reuss and Hill averages of the stiffness of an aggregate of several minerals. The volume
+/// fractions are normalized, and minerals without grains are ignored.
+pub fn average_stiffness(
+    phases: &[MineralPhase],
+) -> Result<AveragedStiffness, Box<dyn std::error::Error>> {
+    let total_fraction: f64 = phases
+        .iter()
+        .filter(|phase| !phase.rotation_matrices.is_empty())
+        .map(|phase| phase.volume_fraction)
+        .sum();
+    if total_fraction <= 0. {
+        return Err("No grains or volume fraction to average the stiffness".into());
+    }
+
+    let mut voigt = Array2::zeros((6, 6));
+    let mut compliance = Array2::zeros((6, 6));
+    for phase in phases {
+        if phase.rotation_matrices.is_empty() {
+            continue;
+        }
+        let weight = phase.volume_fraction / total_fraction / phase.rotation_matrices.len() as f64;
+        for rotation_matrix in phase.rotation_matrices {
+            let rotated = rotate_stiffness(phase.stiffness, rotation_matrix);
+            compliance = compliance + invert(&rotated).ok_or("Singular stiffness matrix")? * weight;
+            voigt = voigt + rotated * weight;
+        }
+    }
+    let reuss = invert(&compliance).ok_or("Singular compliance matrix")?;
+    let hill = (&voigt + &reuss) * 0.5;
+
+    Ok(AveragedStiffness { voigt, reuss, hill })
+}
+
+/// Return the 21 independent components of a stiffness matrix in Voigt notation, in the order C11, C12, ...,
+/// C16, C22, ..., C66.
+pub fn independent_components(stiffness: &Array2<f64>) -> Vec<f64> {
+    (0..6)
+        .flat_map(|i| (i..6).map(move |j| (i, j)))
+        .map(|(i, j)| stiffness[[i, j]])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euler_angles_to_rotation_matrix;
+    use crate::pole_figures::minerals::Mineral;
+    use ndarray::{Array, Array4};
+
+    /// Rotate the full fourth order stiffness tensor, to check the Bond matrix against.
+    fn rotate_full_tensor(stiffness: &Array2<f64>, g: &Array2<f64>) -> Array2<f64> {
+        let voigt = |i: usize, j: usize| if i == j { i } else { 6 - i - j };
+        let c = Array4::from_shape_fn((3, 3, 3, 3), |(i, j, k, l)| {
+            stiffness[[voigt(i, j), voigt(k, l)]]
+        });
+        let mut rotated = Array4::<f64>::zeros((3, 3, 3, 3));
+        for i in 0..3 {
+            for j in 0..3 {
+                for k in 0..3 {
+                    for l in 0..3 {
+                        let mut sum = 0.;
+                        for p in 0..3 {
+                            for q in 0..3 {
+                                for r in 0..3 {
+                                    for s in 0..3 {
+                                        sum += g[[i, p]]
+                                            * g[[j, q]]
+                                            * g[[k, r]]
+                                            * g[[l, s]]
+                                            * c[[p, q, r, s]];
+                                    }
+                                }
+                            }
+                        }
+                        rotated[[i, j, k, l]] = sum;
+                    }
+                }
+            }
+        }
+        let index = [(0, 0), (1, 1), (2, 2), (1, 2), (0, 2), (0, 1)];
+        Array2::from_shape_fn((6, 6), |(a, b)| {
+            rotated[[index[a].0, index[a].1, index[b].0, index[b].1]]
+        })
+    }
+
+    #[test]
+    fn test_rotate_stiffness() {
+        let stiffness = Mineral::Olivine.stiffness();
+        let rotation_matrix =
+            euler_angles_to_rotation_matrix(Array::from(vec![0.3, 1.1, -0.7])).unwrap();
+        let rotated = rotate_stiffness(&stiffness, &rotation_matrix);
+        let expected = rotate_full_tensor(&stiffness, &rotation_matrix.t().to_owned());
+        assert!((&rotated - &expected).iter().all(|x| x.abs() < 1e-10));
+    }
+
+    #[test]
+    fn test_average_stiffness() {
+        let olivine = Mineral::Olivine.stiffness();
+        let enstatite = Mineral::Enstatite.stiffness();
+
+        // a single orientation: all averages are equal to the single crystal.
+        let identity = vec![Array2::eye(3)];
+        let average = average_stiffness(&[MineralPhase {
+            stiffness: &olivine,
+            rotation_matrices: &identity,
+            volume_fraction: 1.,
+        }])
+        .unwrap();
+        for averaged in &[&average.voigt, &average.reuss, &average.hill] {
+            assert!((*averaged - &olivine).iter().all(|x| x.abs() < 1e-10));
+        }
+
+        // the Voigt average is an upper bound and the Reuss average a lower bound of the stiffness.
+        let rotations: Vec<Array2<f64>> = (0..10)
+            .map(|i| {
+                euler_angles_to_rotation_matrix(Array::from(vec![
+                    0.7 * i as f64,
+                    0.3 * i as f64,
+                    1.3 * i as f64,
+                ]))
+                .unwrap()
+            })
+            .collect();
+        let average = average_stiffness(&[
+            MineralPhase {
+                stiffness: &olivine,
+                rotation_matrices: &rotations,
+                volume_fraction: 0.7,
+            },
+            MineralPhase {
+                stiffness: &enstatite,
+                rotation_matrices: &rotations,
+                volume_fraction: 0.3,
+            },
+        ])
+        .unwrap();
+        for i in 0..6 {
+            assert!(average.voigt[[i, i]] >= average.hill[[i, i]]);
+            assert!(average.hill[[i, i]] >= average.reuss[[i, i]]);
+        }
+        assert_eq!(independent_components(&average.hill).len(), 21);
+    }
+}
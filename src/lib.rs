@@ -42,6 +42,19 @@
 //! # Whether the Data was compressed with ZLIB.
 //! compressed = true
 //!
+//! # Optional number of threads to use. All experiments, times and particles are
+//! # processed in parallel. The default is the number of logical cores.
+//! number_of_threads = 8
+//!
+//! # Optional global limit in megabytes on the estimated memory of all the tasks which
+//! # run at the same time. The default is no limit.
+//! max_memory_mb = 4000
+
+//! # Optional size in megabytes of the temporary array each task uses to compute
+//! # the densities of the pole figures. The default is max_memory_mb divided by the
+//! # number of threads, or no limit.
+//! density_chunk_memory_mb = 500
+//!
 //! [pole_figures]
 //!   # Wheter to include elasticity information in the header of the polefigure plots.
 //!   elastisity_header = false
@@ -59,6 +72,29 @@
 //!   # A vector containing the minerals to be plotted. These will be added as a vertical
 //!   # axis  to the plot. Available options are `Olivine` and `Enstatite`.
 //!   minerals = ["Olivine","Enstatite"]
+//!
+//!   # Optional color map. The built-in maps are `Vik`, `Batlow`, `Simple`, `Imola`,
+//!   # `Hawaii` and `Roma`. Other maps are read from a GMT `.cpt` file or a table of RGB
+//!   # values, or given as a list of colors. The other Crameri Scientific colour maps,
+//!   # like lapaz, oslo and tokyo, are not built in, but can be read from the `.cpt`
+//!   # files of that distribution with `{ File = { path = "lapaz.cpt" } }`.
+//!   color_scale = "Batlow"
+//!   reverse_color_scale = false
+//!
+//! [inverse_pole_figures]
+//!   # Optional inverse pole figures for the times, particles and minerals of the pole
+//!   # figures. For each sample direction in this vector the direction in the crystal
+//!   # frame is plotted in the fundamental sector of the mineral. Available options are
+//!   # `X`, `Y`, `Z` and any direction given as `{ Vector = [1.0, 0.0, 1.0] }`.
+//!   sample_directions = ["X","Z"]
+//!
+//! [fabric]
+//!   # Optional fabric analysis for the times, particles and minerals of the pole
+//!   # figures. The misorientation angle distributions and the M-index only use
+//!   # uncorrelated misorientation angles between random grain pairs. Correlated
+//!   # (neighbour-pair) misorientation angles are not supported, because the particles
+//!   # do not store which grains are neighbours.
+//!   misorientation_figures = true
 //! ```
 //!
 //! The configuration file without comments:
@@ -76,20 +112,61 @@
 //! ```
 pub mod color_gradients;
 pub mod configuration;
+pub mod elasticity;
+pub mod linear_algebra;
 pub mod pole_figures;
+pub mod task_pool;
+pub mod texture;
 
+use crate::color_gradients::set_color_gradient;
 use crate::configuration::{
-    config::Config, opt::Opt, particle_record::ParticleRecord, record::Record,
+    animation_configuration::{AnimationConfiguration, AnimationFormat},
+    comparison_configuration::{ComparisonConfiguration, ComparisonDimension},
+    config::Config,
+    elasticity_configuration::ElasticityConfiguration,
+    fabric_configuration::FabricConfiguration,
+    inverse_pole_figure_configuration::InversePoleFiguresConfiguration,
+    opt::Opt,
+    particle_record::ParticleRecord,
+    pole_figure_configuration::{ColorScaleRange, PoleFiguresConfiguration},
+    record::Record,
+    sks_splitting_configuration::{SksSplittingConfiguration, SplittingMethod},
+};
+use crate::elasticity::christoffel::SeismicVelocities;
+use crate::elasticity::decomposition::{HexagonalSymmetryAxis, SymmetryDecomposition};
+use crate::elasticity::seismic_anisotropy::SeismicAnisotropy;
+use crate::elasticity::splitting::{
+    apparent_splitting, height, layer_thicknesses, ApparentSplitting, SplittingLayer,
 };
+use crate::elasticity::stiffness::{
+    average_stiffness, independent_components, AveragedStiffness, MineralPhase,
+};
+use crate::pole_figures::make_inverse_pole_figures::make_inverse_pole_figures;
+use crate::pole_figures::make_misorientation_figures::make_misorientation_figures;
 use crate::pole_figures::make_pole_figures::*;
+use crate::pole_figures::make_velocity_figures::make_velocity_figures;
 use crate::pole_figures::{
-    crystal_axis::CrystalAxes, lambert::*, minerals::Mineral, pole_figure::PoleFigure,
+    crystal_axis::CrystalAxes, inverse_pole_figure::InversePoleFigure, lambert::*,
+    minerals::Mineral, pole_figure::PoleFigure, sample_direction::SampleDirection,
+};
+use crate::task_pool::TaskPool;
+use crate::texture::fabric_strength::FabricStrength;
+use crate::texture::fabric_type::{OlivineFabric, OlivineFabricType};
+use crate::texture::grains::ParticleGrains;
+use crate::texture::inverse_pole_figure::sample_direction_in_crystal_frame;
+use crate::texture::misorientation::{
+    uncorrelated_misorientation_angles, MisorientationDistribution,
 };
+use crate::texture::odf::Odf;
+use crate::texture::orientation_tensor::OrientationTensor;
 
 use ndarray::{Array, Array2, Axis};
-use rayon::prelude::*;
+use palette::gradient::Gradient;
+use palette::rgb::LinSrgb;
 use structopt::StructOpt;
 
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::fs;
 use std::fs::File;
 use std::io::BufRead;
@@ -158,14 +235,13 @@ pub fn load_configuration_file(config_file: PathBuf) -> Result<Config, toml::de:
     let mut file = match File::open(&config_file) {
         // The `description` method of `io::Error` returns a string that
         // describes the error
-        Err(why) => panic!("couldn't open {}: {}", config_file_display, why.to_string()),
+        Err(why) => panic!("couldn't open {}: {}", config_file_display, why),
         Ok(file) => file,
     };
     // Read the file contents into a string, returns `io::Result<usize>`
     let mut config_file_string = String::new();
-    match file.read_to_string(&mut config_file_string) {
-        Err(why) => panic!("couldn't read {}: {}", config_file_display, why.to_string()),
-        Ok(_) => (),
+    if let Err(why) = file.read_to_string(&mut config_file_string) {
+        panic!("couldn't read {}: {}", config_file_display, why)
     }
 
     toml::from_str(&config_file_string)
@@ -206,489 +282,2243 @@ pub fn load_configuration_file(config_file: PathBuf) -> Result<Config, toml::de:
 pub fn process_configuration(config: Config) -> Result<(), Box<dyn std::error::Error>> {
     let before = Instant::now();
 
-    let base_dir = config.base_dir.clone();
+    // Use the requested number of threads. A value of zero lets rayon choose, which
+    // is the number of logical cores.
+    let task_pool = TaskPool {
+        thread_pool: rayon::ThreadPoolBuilder::new()
+            .num_threads(config.number_of_threads.unwrap_or(0))
+            .build()?,
+        max_memory: config
+            .max_memory_mb
+            .map(|max_memory_mb| max_memory_mb * 1024 * 1024),
+    };
+
+    let pole_figure_configuration = match config.pole_figures.as_ref() {
+        Some(pole_figure_configuration) => pole_figure_configuration,
+        None => return Ok(()),
+    };
 
-    // start the experiments
-    let experiment_dirs = config.experiment_dirs.clone();
-    experiment_dirs.par_iter().for_each(|experiment_dir| {
+    // The fabric analysis is also needed when the fabric strength is only shown in the header.
+    let fabric_configuration = match &config.fabric {
+        Some(fabric_configuration) => Some(fabric_configuration.clone()),
+        None if pole_figure_configuration.fabric_strength_header => {
+            Some(FabricConfiguration::default())
+        }
+        None => None,
+    };
+
+    // The elastic tensors are also needed for the SKS splitting.
+    let elasticity_configuration = match &config.elasticity {
+        Some(elasticity_configuration) => Some(elasticity_configuration.clone()),
+        None if config.sks_splitting.is_some() => Some(ElasticityConfiguration::default()),
+        None => None,
+    };
+
+    // The grid on which the pole figures are computed only depends on the resolution and the hemisphere,
+    // so it is created once and shared between all experiments, times and particles.
+    let sphere_points = 301; //76;//151;
+    println!("create lambert equal area gridpoint");
+    let lambert = create_lambert_equal_area_gridpoint(sphere_points, "upper".to_string())?;
+
+    // A color map file is only read once, and an invalid color map is reported before any work is done.
+    let color_gradient = set_color_gradient(
+        &pole_figure_configuration.color_scale,
+        pole_figure_configuration.reverse_color_scale,
+    )?;
+
+    // The temporary array needed to compute the counts on the grid scales with both the number of grains
+    // and grid points. If a chunk size is set, every task computes the counts in chunks of grains for
+    // which this array fits in that size. With a memory limit, the arrays of all the threads together fit in it.
+    let density_chunk_memory = match config.density_chunk_memory_mb {
+        Some(density_chunk_memory_mb) => Some(density_chunk_memory_mb * 1024 * 1024),
+        None => task_pool
+            .max_memory
+            .map(|max_memory| max_memory / task_pool.thread_pool.current_num_threads()),
+    };
+
+    // collect all the experiment, time and particle combinations which need to be processed.
+    let mut tasks: Vec<ParticleTask> = Vec::new();
+    for experiment_dir in &config.experiment_dirs {
         println!("Processing experiment {}", experiment_dir);
 
-        let lpo_dir = base_dir.clone() + &experiment_dir;
+        let lpo_dir = config.base_dir.clone() + experiment_dir;
 
         // get a vector with the time for all the timesteps
-        let statistics_file =
-            lpo_dir.to_owned() + &config.pole_figures.as_ref().unwrap().time_data_file;
-
+        let statistics_file = lpo_dir.to_owned() + &pole_figure_configuration.time_data_file;
         println!("time data file:{}", statistics_file);
-        let file = File::open(statistics_file).unwrap();
-        let reader = BufReader::new(file);
-
-        let mut data: String = "".to_string();
-        for line in reader.lines() {
-            let line = line.unwrap();
-            let line = line.trim();
-            let mut line = line.replace("  ", " ");
-            while let Some(_) = line.find("  ") {
-                line = line.replace("  ", " ");
+        let timestep_to_time = read_time_data_file(Path::new(&statistics_file))?;
+
+        fs::create_dir_all(lpo_dir.to_owned() + &pole_figure_configuration.figure_output_dir)?;
+
+        for output_time in &pole_figure_configuration.times {
+            let time_step = closest_time_step(&timestep_to_time, *output_time);
+            let time = timestep_to_time[time_step];
+
+            println!(
+                "Processing time {} (requested time: {}), located in timestep : {}",
+                time, output_time, time_step,
+            );
+
+            for particle_id in &pole_figure_configuration.particle_ids {
+                tasks.push(ParticleTask {
+                    lpo_dir: lpo_dir.clone(),
+                    time_step: time_step as u64,
+                    time,
+                    particle_id: *particle_id,
+                });
             }
+        }
+    }
 
-            if line.find('#') != Some(0) {
-                if line.find("particle_LPO") != None {
-                    data = data + &line + "\n";
-                }
-            }
+    println!("Processing {} tasks.", tasks.len());
+    let results: Vec<Option<ParticleTaskResult>> = task_pool.run(&tasks, |task| {
+        let result = process_particle_task(
+            task,
+            &config,
+            fabric_configuration.as_ref(),
+            elasticity_configuration.as_ref(),
+            &lambert,
+            &color_gradient,
+            density_chunk_memory,
+        )?;
+        println!(
+            "  After make_polefigures: Elapsed time: {:.2?}",
+            before.elapsed()
+        );
+        let memory = result.as_ref().map_or(0, |result| {
+            particle_task_memory(
+                result.n_grains,
+                &config,
+                fabric_configuration.as_ref(),
+                &lambert,
+                density_chunk_memory,
+            )
+        });
+        Ok((result, memory))
+    })?;
+
+    // The global maximum is only known after all the tasks are done, so the pole figures are drawn now.
+    if pole_figure_configuration.color_scale_range == ColorScaleRange::Global {
+        let global_max_count = global_max_count(&results);
+        let style = PoleFigureStyle::new(pole_figure_configuration, &color_gradient);
+        let deferred_tasks: Vec<(&ParticleTask, &ParticleTaskResult, &DeferredPoleFigures)> = tasks
+            .iter()
+            .zip(results.iter())
+            .filter_map(|(task, result)| {
+                let result = result.as_ref()?;
+                Some((task, result, result.deferred_pole_figures.as_ref()?))
+            })
+            .collect();
+        task_pool.run(&deferred_tasks, |(task, result, deferred)| {
+            let pole_figure_grid = global_color_scale_pole_figure_grid(
+                task,
+                pole_figure_configuration,
+                config.compressed,
+                &lambert,
+                density_chunk_memory,
+                global_max_count,
+            )?;
+            make_pole_figures(
+                &style,
+                &ParticlePoleFigures {
+                    particle_id: task.particle_id as u64,
+                    time: task.time,
+                    n_grains: result.n_grains,
+                    particle_record: &deferred.particle_record,
+                    pole_figure_grid: &pole_figure_grid,
+                    labels: None,
+                    fabric_strength: &result.fabric_strength,
+                    hexagonal_axis: result.hexagonal_axis.as_ref(),
+                    seismic_anisotropy: result.seismic_anisotropy.as_ref(),
+                },
+                &lambert,
+                PoleFigureOutput::File {
+                    path: &deferred.output_file,
+                    format: &pole_figure_configuration.output_format,
+                },
+            )
+            .map_err(|error| error.to_string())?;
+            let memory = pole_figure_memory(
+                result.n_grains,
+                pole_figure_grid.iter().flatten().count(),
+                &lambert,
+                density_chunk_memory,
+            );
+            Ok(((), memory))
+        })?;
+    }
+
+    if let Some(animation_configuration) = &config.animation {
+        for experiment_dir in &config.experiment_dirs {
+            let lpo_dir = config.base_dir.clone() + experiment_dir;
+            let statistics_file = lpo_dir.to_owned() + &pole_figure_configuration.time_data_file;
+            let timestep_to_time = read_time_data_file(Path::new(&statistics_file))?;
+            make_particle_animation(
+                &config,
+                animation_configuration,
+                &lpo_dir,
+                &timestep_to_time,
+                &lambert,
+                &color_gradient,
+                &task_pool,
+                density_chunk_memory,
+            )?;
+        }
+    }
+
+    if let Some(comparison_configuration) = &config.comparison {
+        make_comparison_figures(
+            &config,
+            comparison_configuration,
+            &lambert,
+            &color_gradient,
+            &task_pool,
+            density_chunk_memory,
+        )?;
+    }
+
+    if let Some(fabric_configuration) = &fabric_configuration {
+        for experiment_dir in &config.experiment_dirs {
+            let lpo_dir = config.base_dir.clone() + experiment_dir;
+            let experiment_results: Vec<(&ParticleTask, &ParticleTaskResult)> = tasks
+                .iter()
+                .zip(results.iter())
+                .filter(|(task, _)| task.lpo_dir == lpo_dir)
+                .filter_map(|(task, result)| result.as_ref().map(|result| (task, result)))
+                .collect();
+
+            write_fabric_strength_file(
+                Path::new(&(lpo_dir.to_owned() + &fabric_configuration.strength_output_file)),
+                &experiment_results,
+            )?;
+            write_orientation_tensor_file(
+                Path::new(
+                    &(lpo_dir.to_owned() + &fabric_configuration.orientation_tensor_output_file),
+                ),
+                &experiment_results,
+            )?;
+            write_misorientation_file(
+                Path::new(&(lpo_dir.to_owned() + &fabric_configuration.misorientation_output_file)),
+                &experiment_results,
+            )?;
+            write_fabric_type_file(
+                Path::new(&(lpo_dir.to_owned() + &fabric_configuration.fabric_type_output_file)),
+                &experiment_results,
+            )?;
+        }
+    }
+
+    if let Some(elasticity_configuration) = &config.elasticity {
+        for experiment_dir in &config.experiment_dirs {
+            let lpo_dir = config.base_dir.clone() + experiment_dir;
+            let experiment_results: Vec<(&ParticleTask, &ParticleTaskResult)> = tasks
+                .iter()
+                .zip(results.iter())
+                .filter(|(task, _)| task.lpo_dir == lpo_dir)
+                .filter_map(|(task, result)| result.as_ref().map(|result| (task, result)))
+                .collect();
+
+            write_elastic_tensor_file(
+                Path::new(&(lpo_dir.to_owned() + &elasticity_configuration.output_file)),
+                &experiment_results,
+            )?;
+            write_hexagonal_axis_file(
+                Path::new(
+                    &(lpo_dir.to_owned() + &elasticity_configuration.hexagonal_axis_output_file),
+                ),
+                &experiment_results,
+            )?;
+            write_seismic_anisotropy_file(
+                Path::new(
+                    &(lpo_dir.to_owned()
+                        + &elasticity_configuration.seismic_anisotropy_output_file),
+                ),
+                &experiment_results,
+            )?;
+        }
+    }
+
+    if let (Some(sks_splitting_configuration), Some(elasticity_configuration)) =
+        (&config.sks_splitting, &elasticity_configuration)
+    {
+        for experiment_dir in &config.experiment_dirs {
+            let lpo_dir = config.base_dir.clone() + experiment_dir;
+            let experiment_results: Vec<(&ParticleTask, &ParticleTaskResult)> = tasks
+                .iter()
+                .zip(results.iter())
+                .filter(|(task, _)| task.lpo_dir == lpo_dir)
+                .filter_map(|(task, result)| result.as_ref().map(|result| (task, result)))
+                .filter(
+                    |(task, _)| match &sks_splitting_configuration.particle_ids {
+                        Some(particle_ids) => particle_ids.contains(&task.particle_id),
+                        None => true,
+                    },
+                )
+                .collect();
+
+            write_sks_splitting_file(
+                Path::new(&(lpo_dir.to_owned() + &sks_splitting_configuration.output_file)),
+                &experiment_results,
+                sks_splitting_configuration,
+                elasticity_configuration,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A single unit of work: one particle of one experiment at one time.
+struct ParticleTask {
+    lpo_dir: String,
+    time_step: u64,
+    time: f64,
+    particle_id: usize,
+}
+
+/// The results of a single task which are written to tables after all tasks are done.
+struct ParticleTaskResult {
+    n_grains: usize,
+    fabric_strength: Vec<FabricStrength>,
+    orientation_tensors: Vec<(Mineral, CrystalAxes, OrientationTensor)>,
+    misorientation_distributions: Vec<(Mineral, MisorientationDistribution)>,
+    averaged_stiffness: Option<AveragedStiffness>,
+    position: [f64; 3],
+    hexagonal_axis: Option<HexagonalSymmetryAxis>,
+    seismic_anisotropy: Option<SeismicAnisotropy>,
+    olivine_fabric: Option<OlivineFabric>,
+    olivine_deformation_type: Option<f64>,
+    deferred_pole_figures: Option<DeferredPoleFigures>,
+}
+
+/// The pole figures of a task which are drawn after all the tasks are done, because their color scale depends on
+/// the pole figures of all the tasks. Only the maximum count of the pole figures is kept, so that the pole figures
+/// of all the tasks do not have to be in memory at the same time. They are computed again when they are drawn.
+struct DeferredPoleFigures {
+    output_file: PathBuf,
+    max_count: f64,
+    particle_record: ParticleRecord,
+}
+
+/// Return the maximum count of the pole figures of all the tasks which are drawn with a global color scale.
+fn global_max_count(results: &[Option<ParticleTaskResult>]) -> f64 {
+    results
+        .iter()
+        .flatten()
+        .filter_map(|result| result.deferred_pole_figures.as_ref())
+        .map(|deferred| deferred.max_count)
+        .fold(0.0, f64::max)
+}
+
+/// Compute the pole figures of a task again, with a color scale from zero to the maximum count of all the tasks.
+fn global_color_scale_pole_figure_grid(
+    task: &ParticleTask,
+    pole_figure_configuration: &PoleFiguresConfiguration,
+    compressed: bool,
+    lambert: &Lambert,
+    density_chunk_memory: Option<usize>,
+    global_max_count: f64,
+) -> Result<Vec<Vec<PoleFigure>>, String> {
+    let (grains, _) = read_particle_data(
+        &task.lpo_dir,
+        pole_figure_configuration,
+        task.time_step,
+        task.particle_id,
+        compressed,
+    )?
+    .ok_or_else(|| {
+        format!(
+            "particle id {} not found for timestep {}.",
+            task.particle_id, task.time_step
+        )
+    })?;
+    let mut pole_figure_grid = compute_pole_figure_grid(
+        &grains,
+        pole_figure_configuration,
+        lambert,
+        density_chunk_memory,
+    )
+    .map_err(|error| error.to_string())?;
+    set_color_scale_range(
+        &mut pole_figure_grid,
+        &ColorScaleRange::Global,
+        Some(global_max_count),
+    );
+    Ok(pole_figure_grid)
+}
+
+/// Read the file relating the numbers of the individual data files with the time they represent.
+fn read_time_data_file(statistics_file: &Path) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    let file = File::open(statistics_file)?;
+    let reader = BufReader::new(file);
+
+    let mut data: String = "".to_string();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        let mut line = line.replace("  ", " ");
+        while line.contains("  ") {
+            line = line.replace("  ", " ");
+        }
+
+        if !line.starts_with('#') && line.contains("particle_LPO") {
+            data = data + &line + "\n";
+        }
+    }
+
+    let mut timestep_to_time: Vec<f64> = vec![];
+    let mut rdr = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .delimiter(b' ')
+        .comment(Some(b'#'))
+        .has_headers(false)
+        .from_reader(data.as_bytes());
+    for result in rdr.records() {
+        // The iterator yields Result<StringRecord, Error>, so we check the
+        // error here..
+        let record = result?;
+        match record.get(1) {
+            Some(time) => timestep_to_time.push(time.parse::<f64>()?),
+            None => return Err("Time not found".into()),
+        }
+    }
+
+    Ok(timestep_to_time)
+}
+
+/// Find the timestep of which the time is closest to the requested output time.
+/// This assumes the times are sorted and always start at zero.
+fn closest_time_step(timestep_to_time: &[f64], output_time: f64) -> usize {
+    let after_timestep = match timestep_to_time.iter().position(|x| *x > output_time) {
+        Some(timestep) => timestep,
+        None => timestep_to_time.len() - 1,
+    };
+
+    let before_timestep = after_timestep.saturating_sub(1);
+
+    // check wheter before_timestep or after_timestep is closer to output_time,
+    // then use that one.
+    let before_timestep_diff = (output_time - timestep_to_time[before_timestep]).abs();
+    let after_timestep_diff = (output_time - timestep_to_time[after_timestep]).abs();
+
+    if before_timestep_diff < after_timestep_diff {
+        before_timestep
+    } else {
+        after_timestep
+    }
+}
+
+/// Replace the norms of the symmetry classes read from the ASPECT particle data by the ones of a decomposition
+/// computed by this program.
+fn set_symmetry_decomposition(
+    particle_record: &mut ParticleRecord,
+    decomposition: &SymmetryDecomposition,
+) {
+    let [tric_p1, tric_p2, tric_p3] = decomposition.triclinic_norm_square;
+    let [mono_p1, mono_p2, mono_p3] = decomposition.monoclinic_norm_square;
+    let [ortho_p1, ortho_p2, ortho_p3] = decomposition.orthorhombic_norm_square;
+    let [tet_p1, tet_p2, tet_p3] = decomposition.tetragonal_norm_square;
+    let [hex_p1, hex_p2, hex_p3] = decomposition.hexagonal_norm_square;
+
+    particle_record.full_norm_square = Some(decomposition.full_norm_square);
+    particle_record.triclinic_norm_square_p1 = Some(tric_p1);
+    particle_record.triclinic_norm_square_p2 = Some(tric_p2);
+    particle_record.triclinic_norm_square_p3 = Some(tric_p3);
+    particle_record.monoclinic_norm_square_p1 = Some(mono_p1);
+    particle_record.monoclinic_norm_square_p2 = Some(mono_p2);
+    particle_record.monoclinic_norm_square_p3 = Some(mono_p3);
+    particle_record.orthohombic_norm_square_p1 = Some(ortho_p1);
+    particle_record.orthohombic_norm_square_p2 = Some(ortho_p2);
+    particle_record.orthohombic_norm_square_p3 = Some(ortho_p3);
+    particle_record.tetragonal_norm_square_p1 = Some(tet_p1);
+    particle_record.tetragonal_norm_square_p2 = Some(tet_p2);
+    particle_record.tetragonal_norm_square_p3 = Some(tet_p3);
+    particle_record.hexagonal_norm_square_p1 = Some(hex_p1);
+    particle_record.hexagonal_norm_square_p2 = Some(hex_p2);
+    particle_record.hexagonal_norm_square_p3 = Some(hex_p3);
+    particle_record.isotropic_norm_square = Some(decomposition.isotropic_norm_square);
+}
+
+/// Find and read the grain orientations and particle information of a single particle at a single time.
+/// For each timestep all files of the different ranks are searched until the particle is found. Returns
+/// `None` if the particle is not found, and an error if one of the files can not be read or parsed.
+fn read_particle_data(
+    lpo_dir: &str,
+    pole_figure_configuration: &PoleFiguresConfiguration,
+    time_step: u64,
+    particle_id: usize,
+    compressed: bool,
+) -> Result<Option<(ParticleGrains, ParticleRecord)>, String> {
+    let mut rank_id = 0;
+    loop {
+        let angles_file = format!(
+            "{}{}-{:05}.{:04}.dat",
+            lpo_dir, pole_figure_configuration.grain_data_file_prefix, time_step, rank_id
+        );
+        let angles_file = Path::new(&angles_file);
+        let particle_file = format!(
+            "{}{}-{:05}.{:04}.dat",
+            lpo_dir, pole_figure_configuration.particle_data_file_prefix, time_step, rank_id
+        );
+        let particle_info_file = Path::new(&particle_file);
+
+        println!("  trying file name: {}", angles_file.display());
+
+        // check wheter file exists, if not it means that is reached the max rank, so stop.
+        let metadata = match fs::metadata(angles_file) {
+            Ok(metadata) => metadata,
+            Err(_) => return Ok(None),
+        };
+
+        // check wheter file is empty, if not continue to next rank
+        if metadata.len() == 0 {
+            rank_id += 1;
+            continue;
         }
 
-        let mut timestep_to_time: Vec<f64> = vec![];
+        let read_error =
+            |error: &dyn std::fmt::Display| format!("{}: {}", angles_file.display(), error);
+        let file = File::open(angles_file).map_err(|error| read_error(&error))?;
+
+        let mut buf_reader = BufReader::with_capacity(metadata.len() as usize, file);
+
+        let mut decoded_data = Vec::new();
+
+        let decoded_reader = if compressed {
+            let mut decoder =
+                libflate::zlib::Decoder::new(buf_reader).map_err(|error| read_error(&error))?;
+            decoder
+                .read_to_end(&mut decoded_data)
+                .map_err(|error| read_error(&error))?;
+            String::from_utf8_lossy(&decoded_data)
+        } else {
+            let data = buf_reader.fill_buf().map_err(|error| read_error(&error))?;
+            String::from_utf8_lossy(data)
+        };
+
         let mut rdr = csv::ReaderBuilder::new()
-            .trim(csv::Trim::All)
+            .has_headers(true)
             .delimiter(b' ')
-            .comment(Some(b'#'))
-            .has_headers(false)
-            .from_reader(data.as_bytes());
-        for result in rdr.records() {
-            // The iterator yields Result<StringRecord, Error>, so we check the
-            // error here..
-            let record = result.unwrap().clone();
-            let time = record.get(1).clone();
-            match time {
-                Some(time) => timestep_to_time.push(time.to_string().parse::<f64>().unwrap()),
-                None => assert!(false, "Time not found"),
+            .from_reader(decoded_reader.as_bytes());
+
+        let mut grains = ParticleGrains {
+            olivine: Vec::new(),
+            enstatite: Vec::new(),
+            olivine_volume_fractions: Vec::new(),
+            enstatite_volume_fractions: Vec::new(),
+        };
+        for result in rdr.deserialize() {
+            let record: Record = result.map_err(|error| read_error(&error))?;
+            if record.id == particle_id {
+                let deg_to_rad = std::f64::consts::PI / 180.;
+                let euler_angle = |angle: Option<f64>, name: &str| {
+                    angle.map(|angle| angle * deg_to_rad).ok_or_else(|| {
+                        format!(
+                            "{}: missing {} of particle id {}",
+                            angles_file.display(),
+                            name,
+                            particle_id
+                        )
+                    })
+                };
+
+                // olivine
+                let euler_angles = Array::from(vec![
+                    euler_angle(record.mineral_0_EA_phi, "mineral_0_EA_phi")?,
+                    euler_angle(record.mineral_0_EA_theta, "mineral_0_EA_theta")?,
+                    euler_angle(record.mineral_0_EA_z, "mineral_0_EA_z")?,
+                ]);
+                grains.olivine.push(
+                    euler_angles_to_rotation_matrix(euler_angles)
+                        .map_err(|error| read_error(&error))?,
+                );
+                grains
+                    .olivine_volume_fractions
+                    .push(record.mineral_0_volume_fraction.unwrap_or(1.0));
+
+                // enstatite
+                let euler_angles = Array::from(vec![
+                    euler_angle(record.mineral_1_EA_phi, "mineral_1_EA_phi")?,
+                    euler_angle(record.mineral_1_EA_theta, "mineral_1_EA_theta")?,
+                    euler_angle(record.mineral_1_EA_z, "mineral_1_EA_z")?,
+                ]);
+                grains.enstatite.push(
+                    euler_angles_to_rotation_matrix(euler_angles)
+                        .map_err(|error| read_error(&error))?,
+                );
+                grains
+                    .enstatite_volume_fractions
+                    .push(record.mineral_1_volume_fraction.unwrap_or(1.0));
             }
         }
 
-        if config.pole_figures.is_some() {
-            let pole_figure_configuration = config.pole_figures.as_ref().unwrap();
-            let elastisity_header = pole_figure_configuration.elastisity_header;
+        // check if the particle id was found in this file, otherwise continue
+        if grains.n_grains() == 0 {
+            rank_id += 1;
+            continue;
+        }
 
-            for output_time in &pole_figure_configuration.times {
-                // find closest value in timestep_to_time
-                // assume it always starts a zero
-                let after_time = timestep_to_time.iter().position(|x| x > &output_time);
+        println!(
+            "  found particle id {} in:{}",
+            particle_id,
+            angles_file.display()
+        );
 
-                let after_timestep = match after_time {
-                    Some(timestep) => timestep,
-                    None => timestep_to_time.len() - 1,
-                };
+        // retrieve anisotropy info
+        let mut particle_record = ParticleRecord {
+            id: 0,
+            x: 0.0,
+            y: 0.0,
+            z: Some(0.0),
+            olivine_deformation_type: None,
+            full_norm_square: None,
+            triclinic_norm_square_p1: None,
+            triclinic_norm_square_p2: None,
+            triclinic_norm_square_p3: None,
+            monoclinic_norm_square_p1: None,
+            monoclinic_norm_square_p2: None,
+            monoclinic_norm_square_p3: None,
+            orthohombic_norm_square_p1: None,
+            orthohombic_norm_square_p2: None,
+            orthohombic_norm_square_p3: None,
+            tetragonal_norm_square_p1: None,
+            tetragonal_norm_square_p2: None,
+            tetragonal_norm_square_p3: None,
+            hexagonal_norm_square_p1: None,
+            hexagonal_norm_square_p2: None,
+            hexagonal_norm_square_p3: None,
+            isotropic_norm_square: None,
+        };
+
+        let particle_read_error =
+            |error: &dyn std::fmt::Display| format!("{}: {}", particle_info_file.display(), error);
+        let buf_reader = BufReader::new(
+            File::open(particle_info_file).map_err(|error| particle_read_error(&error))?,
+        );
 
-                // todo: oneline
-                let mut before_timestep = after_timestep;
-                if after_timestep > 0 {
-                    before_timestep = after_timestep - 1
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(true)
+            .delimiter(b' ')
+            .from_reader(buf_reader);
+
+        for result in rdr.deserialize() {
+            // We must tell Serde what type we want to deserialize into.
+            let record: ParticleRecord = result.map_err(|error| particle_read_error(&error))?;
+            if record.id == particle_id {
+                particle_record = record;
+            }
+        }
+        // end retrieve anisotropy info
+        println!("end retrieve antisotropy info");
+
+        return Ok(Some((grains, particle_record)));
+    }
+}
+
+/// Create the file name of the pole figure of a particle at a timestep.
+fn pole_figure_output_file(
+    lpo_dir: &str,
+    pole_figure_configuration: &PoleFiguresConfiguration,
+    sphere_points: usize,
+    time_step: u64,
+    particle_id: usize,
+) -> String {
+    let file_prefix_figures = pole_figure_configuration.figure_output_dir.to_owned()
+        + &pole_figure_configuration.figure_output_prefix;
+
+    let mut config_mineral_string = String::new();
+    for mineral in pole_figure_configuration.minerals.clone() {
+        config_mineral_string = format!(
+            "{}{}",
+            config_mineral_string,
+            match mineral {
+                Mineral::Olivine => {
+                    "oli_"
                 }
+                Mineral::Enstatite => {
+                    "ens_"
+                }
+            }
+        )
+    }
+    let mut config_axis_string = String::new();
+    for axis in &pole_figure_configuration.axes {
+        config_axis_string = format!("{}{}-", config_axis_string, axis.file_label())
+    }
+    config_axis_string = format!("{}Axis_", config_axis_string);
+
+    format!(
+        "{}{}_{}{}{}{}_{}_sp{}_t{:05}.{:05}.{}",
+        lpo_dir,
+        file_prefix_figures,
+        if pole_figure_configuration.elastisity_header {
+            "elastic_"
+        } else {
+            "no-elastic_"
+        },
+        config_mineral_string,
+        config_axis_string,
+        pole_figure_configuration
+            .color_scale
+            .file_label(pole_figure_configuration.reverse_color_scale),
+        pole_figure_configuration.color_scaling.file_label(),
+        sphere_points,
+        time_step,
+        particle_id,
+        pole_figure_configuration.output_format.extension()
+    )
+}
 
-                // check wheter before_timestep or after_timestep is closer to output_time,
-                // then use that one.
-                let before_timestep_diff = (output_time - timestep_to_time[before_timestep]).abs();
-                let after_timestep_diff = (output_time - timestep_to_time[after_timestep]).abs();
+/// Read the data of a single particle at a single time, compute the pole figures
+/// and write them to file. Returns `None` if the particle could not be found. The error is returned as a string,
+/// so that it can be passed from the thread of the task.
+fn process_particle_task(
+    task: &ParticleTask,
+    config: &Config,
+    fabric_configuration: Option<&FabricConfiguration>,
+    elasticity_configuration: Option<&ElasticityConfiguration>,
+    lambert: &Lambert,
+    color_gradient: &Gradient<LinSrgb>,
+    density_chunk_memory: Option<usize>,
+) -> Result<Option<ParticleTaskResult>, String> {
+    let pole_figure_configuration = match config.pole_figures.as_ref() {
+        Some(pole_figure_configuration) => pole_figure_configuration,
+        None => return Ok(None),
+    };
+    let inverse_pole_figure_configuration = config.inverse_pole_figures.as_ref();
+    let time_step = task.time_step;
+    let time = task.time;
+    let particle_id = task.particle_id;
+
+    println!("processing particle_id {}", particle_id);
+    let (grains, mut particle_record) = match read_particle_data(
+        &task.lpo_dir,
+        pole_figure_configuration,
+        time_step,
+        particle_id,
+        config.compressed,
+    )? {
+        Some(particle_data) => particle_data,
+        None => {
+            println!(
+                "particle id {} not found for timestep {}.",
+                particle_id, time_step
+            );
+            return Ok(None);
+        }
+    };
 
-                let time_step = if before_timestep_diff < after_timestep_diff {
-                    before_timestep as u64
-                } else {
-                    after_timestep as u64
-                };
+    let output_file = pole_figure_output_file(
+        &task.lpo_dir,
+        pole_figure_configuration,
+        lambert.sphere_points,
+        time_step,
+        particle_id,
+    );
+    let output_file = Path::new(&output_file);
+
+    let n_grains = grains.n_grains();
+
+    let averaged_stiffness = elasticity_configuration
+        .map(|elasticity_configuration| {
+            let minerals = [Mineral::Olivine, Mineral::Enstatite];
+            let stiffness: Vec<Array2<f64>> = minerals
+                .iter()
+                .map(|mineral| elasticity_configuration.stiffness(mineral))
+                .collect();
+            let phases: Vec<MineralPhase> = minerals
+                .iter()
+                .zip(stiffness.iter())
+                .map(|(mineral, stiffness)| MineralPhase {
+                    stiffness,
+                    rotation_matrices: grains.rotation_matrices(mineral),
+                    volume_fraction: elasticity_configuration.volume_fraction(mineral),
+                })
+                .collect();
+            average_stiffness(&phases)
+        })
+        .transpose()
+        .map_err(|error| error.to_string())?;
+
+    let symmetry_decomposition = averaged_stiffness
+        .as_ref()
+        .map(|averaged_stiffness| SymmetryDecomposition::from_stiffness(&averaged_stiffness.voigt));
+    if let (Some(elasticity_configuration), Some(symmetry_decomposition)) =
+        (elasticity_configuration, &symmetry_decomposition)
+    {
+        if elasticity_configuration.compute_header {
+            set_symmetry_decomposition(&mut particle_record, symmetry_decomposition);
+        }
+    }
+    let hexagonal_axis = symmetry_decomposition.map(|decomposition| decomposition.hexagonal_axis);
+    let seismic_anisotropy = elasticity_configuration
+        .zip(averaged_stiffness.as_ref())
+        .map(|(elasticity_configuration, averaged_stiffness)| {
+            SeismicAnisotropy::from_stiffness(
+                &averaged_stiffness.voigt,
+                &elasticity_configuration.vertical_direction,
+            )
+        });
+
+    let mut misorientation_distributions: Vec<(Mineral, MisorientationDistribution)> = vec![];
+    let fabric_strength: Vec<FabricStrength> = match fabric_configuration {
+        Some(fabric_configuration) => pole_figure_configuration
+            .minerals
+            .iter()
+            .map(|mineral| {
+                let rotation_matrices = grains.rotation_matrices(mineral);
+                let odf = Odf::from_rotation_matrices(
+                    rotation_matrices,
+                    mineral.symmetry(),
+                    fabric_configuration.odf_resolution,
+                    fabric_configuration.odf_halfwidth,
+                )
+                .map_err(|error| error.to_string())?;
+                let misorientation_angles = uncorrelated_misorientation_angles(
+                    rotation_matrices,
+                    mineral.symmetry(),
+                    fabric_configuration.misorientation_pairs,
+                );
+                misorientation_distributions.push((
+                    mineral.clone(),
+                    MisorientationDistribution::from_angles(
+                        &misorientation_angles,
+                        mineral.symmetry(),
+                        fabric_configuration.misorientation_bin_width,
+                    ),
+                ));
+                Ok(FabricStrength {
+                    mineral: mineral.clone(),
+                    n_grains: rotation_matrices.len(),
+                    j_index: odf.texture_index(),
+                    odf_entropy: odf.entropy(),
+                    odf_max_density: odf.max_density(),
+                    // the M-index is defined with bins of one degree.
+                    m_index: MisorientationDistribution::from_angles(
+                        &misorientation_angles,
+                        mineral.symmetry(),
+                        1.,
+                    )
+                    .m_index(),
+                })
+            })
+            .collect::<Result<Vec<FabricStrength>, String>>()?,
+        None => vec![],
+    };
+
+    if let Some(fabric_configuration) = fabric_configuration {
+        if fabric_configuration.misorientation_figures {
+            let config_mineral_string = mineral_file_string(&pole_figure_configuration.minerals);
+            let output_file = format!(
+                "{}{}misorientation_{}t{:05}.{:05}.png",
+                task.lpo_dir,
+                pole_figure_configuration.figure_output_dir,
+                config_mineral_string,
+                time_step,
+                particle_id
+            );
+            make_misorientation_figures(
+                pole_figure_configuration.small_figure,
+                particle_id as u64,
+                time,
+                &misorientation_distributions,
+                Path::new(&output_file),
+            )
+            .map_err(|error| error.to_string())?;
+        }
+    }
+
+    // the orientation tensors are computed for all the crystal axes, not only the ones in the pole figures.
+    let orientation_tensors: Vec<(Mineral, CrystalAxes, OrientationTensor)> =
+        match fabric_configuration {
+            Some(_) => pole_figure_configuration
+                .minerals
+                .iter()
+                .flat_map(|mineral| {
+                    [CrystalAxes::AAxis, CrystalAxes::BAxis, CrystalAxes::CAxis]
+                        .iter()
+                        .map(|axis| {
+                            (
+                                mineral.clone(),
+                                axis.clone(),
+                                OrientationTensor::from_axis_vectors(
+                                    &grains.axis_vectors(mineral, axis),
+                                ),
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect(),
+            None => vec![],
+        };
+
+    let olivine_fabric = fabric_configuration.map(|fabric_configuration| {
+        let tensor = |axis| {
+            OrientationTensor::from_axis_vectors(&grains.axis_vectors(&Mineral::Olivine, &axis))
+        };
+        OlivineFabric::from_orientation_tensors(
+            &tensor(CrystalAxes::AAxis),
+            &tensor(CrystalAxes::BAxis),
+            &tensor(CrystalAxes::CAxis),
+            &fabric_configuration.shear_direction,
+            &fabric_configuration.shear_plane_normal,
+        )
+    });
+
+    let mut pole_figure_grid = compute_pole_figure_grid(
+        &grains,
+        pole_figure_configuration,
+        lambert,
+        density_chunk_memory,
+    )
+    .map_err(|error| error.to_string())?;
+
+    // With a global color scale the pole figures are drawn after all the tasks are done.
+    let deferred_pole_figures =
+        if pole_figure_configuration.color_scale_range == ColorScaleRange::Global {
+            Some(DeferredPoleFigures {
+                output_file: output_file.to_path_buf(),
+                max_count: pole_figure_grid
+                    .iter()
+                    .flatten()
+                    .map(|pole_figure| pole_figure.max_count)
+                    .fold(0.0, f64::max),
+                particle_record: particle_record.clone(),
+            })
+        } else {
+            set_color_scale_range(
+                &mut pole_figure_grid,
+                &pole_figure_configuration.color_scale_range,
+                None,
+            );
+            make_pole_figures(
+                &PoleFigureStyle::new(pole_figure_configuration, color_gradient),
+                &ParticlePoleFigures {
+                    particle_id: particle_id as u64,
+                    time,
+                    n_grains,
+                    particle_record: &particle_record,
+                    pole_figure_grid: &pole_figure_grid,
+                    labels: None,
+                    fabric_strength: &fabric_strength,
+                    hexagonal_axis: hexagonal_axis.as_ref(),
+                    seismic_anisotropy: seismic_anisotropy.as_ref(),
+                },
+                lambert,
+                PoleFigureOutput::File {
+                    path: output_file,
+                    format: &pole_figure_configuration.output_format,
+                },
+            )
+            .map_err(|error| error.to_string())?;
+            None
+        };
+
+    if let (Some(elasticity_configuration), Some(averaged_stiffness)) =
+        (elasticity_configuration, &averaged_stiffness)
+    {
+        if elasticity_configuration.velocity_figures {
+            let velocities = SeismicVelocities::on_lambert_grid(
+                &averaged_stiffness.voigt,
+                elasticity_configuration.density(),
+                lambert,
+            );
+            let output_file = format!(
+                "{}{}seismic_velocities_t{:05}.{:05}.png",
+                task.lpo_dir, pole_figure_configuration.figure_output_dir, time_step, particle_id
+            );
+            make_velocity_figures(
+                pole_figure_configuration.small_figure,
+                pole_figure_configuration.no_description_text,
+                particle_id as u64,
+                &velocities,
+                lambert,
+                Path::new(&output_file),
+                time,
+                color_gradient,
+            )
+            .map_err(|error| error.to_string())?;
+        }
+    }
+
+    if let Some(inverse_pole_figure_configuration) = inverse_pole_figure_configuration {
+        let inverse_pole_figure_grid: Vec<Vec<InversePoleFigure>> = compute_inverse_pole_figures(
+            &grains,
+            &pole_figure_configuration.minerals,
+            &inverse_pole_figure_configuration.sample_directions,
+            lambert,
+            density_chunk_memory,
+        )
+        .map_err(|error| error.to_string())?;
+        let output_file = inverse_pole_figure_output_file(
+            &task.lpo_dir,
+            pole_figure_configuration,
+            inverse_pole_figure_configuration,
+            lambert.sphere_points,
+            time_step,
+            particle_id,
+        );
+        make_inverse_pole_figures(
+            pole_figure_configuration.small_figure,
+            pole_figure_configuration.no_description_text,
+            n_grains,
+            particle_id as u64,
+            &inverse_pole_figure_grid,
+            lambert,
+            Path::new(&output_file),
+            time,
+            &pole_figure_configuration.color_scaling,
+            color_gradient,
+        )
+        .map_err(|error| error.to_string())?;
+    }
+
+    println!("go to next id");
+    Ok(Some(ParticleTaskResult {
+        n_grains,
+        fabric_strength,
+        orientation_tensors,
+        misorientation_distributions,
+        averaged_stiffness,
+        position: [
+            particle_record.x,
+            particle_record.y,
+            particle_record.z.unwrap_or(0.),
+        ],
+        hexagonal_axis,
+        seismic_anisotropy,
+        olivine_fabric,
+        olivine_deformation_type: particle_record.olivine_deformation_type,
+        deferred_pole_figures,
+    }))
+}
+
+/// Estimate the memory in bytes used to compute `n_pole_figures` (inverse) pole figures of a particle with
+/// `n_grains` grains per mineral: the grains of both minerals, the temporary array of the densities and the
+/// counts and grain poles of the pole figures.
+fn pole_figure_memory(
+    n_grains: usize,
+    n_pole_figures: usize,
+    lambert: &Lambert,
+    density_chunk_memory: Option<usize>,
+) -> usize {
+    let f64_size = std::mem::size_of::<f64>();
+    let grid_points = lambert.sphere_points * lambert.sphere_points;
+    // the rotation matrix and the volume fraction of every grain.
+    let grains = 2 * n_grains * (9 + 1) * f64_size;
+    // the densities are computed for at least one grain at a time.
+    let densities = (n_grains * grid_points * f64_size)
+        .min(density_chunk_memory.unwrap_or(usize::MAX))
+        .max(grid_points * f64_size);
+    let pole_figures = n_pole_figures * (grid_points + 3 * n_grains) * f64_size;
+    grains + densities + pole_figures
+}
+
+/// Estimate the memory in bytes used by a task for a particle with `n_grains` grains per mineral: the pole figures,
+/// the inverse pole figures and, when the fabric is analyzed, the ODF and the misorientation angles of one mineral
+/// at a time.
+fn particle_task_memory(
+    n_grains: usize,
+    config: &Config,
+    fabric_configuration: Option<&FabricConfiguration>,
+    lambert: &Lambert,
+    density_chunk_memory: Option<usize>,
+) -> usize {
+    let n_pole_figures = config.pole_figures.as_ref().map_or(0, |pole_figures| {
+        let n_inverse_pole_figures = config
+            .inverse_pole_figures
+            .as_ref()
+            .map_or(0, |inverse_pole_figures| {
+                inverse_pole_figures.sample_directions.len()
+            });
+        pole_figures.minerals.len() * (pole_figures.axes.len() + n_inverse_pole_figures)
+    });
+    let fabric = fabric_configuration.map_or(0, |fabric_configuration| {
+        [Mineral::Olivine, Mineral::Enstatite]
+            .iter()
+            .map(|mineral| {
+                Odf::memory(
+                    n_grains,
+                    mineral.symmetry(),
+                    fabric_configuration.odf_resolution,
+                ) + fabric_configuration.misorientation_pairs * std::mem::size_of::<f64>()
+            })
+            .max()
+            .unwrap_or(0)
+    });
+    pole_figure_memory(n_grains, n_pole_figures, lambert, density_chunk_memory) + fabric
+}
+
+/// Compute the pole figures of the grains of a particle for the axes and minerals of the pole figure
+/// configuration, in multiples of uniform density. The color scale of every pole figure is set to its own range.
+fn compute_pole_figure_grid(
+    grains: &ParticleGrains,
+    pole_figure_configuration: &PoleFiguresConfiguration,
+    lambert: &Lambert,
+    density_chunk_memory: Option<usize>,
+) -> Result<Vec<Vec<PoleFigure>>, Box<dyn std::error::Error>> {
+    pole_figure_configuration
+        .axes
+        .iter()
+        .map(|axis| {
+            pole_figure_configuration
+                .minerals
+                .iter()
+                .map(|mineral| {
+                    compute_pole_figure(
+                        grains,
+                        mineral,
+                        axis,
+                        pole_figure_configuration,
+                        lambert,
+                        density_chunk_memory,
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Compute the pole figure of an axis of a mineral of the grains of a particle, in multiples of uniform density.
+/// The color scale is set to the range of the pole figure.
+fn compute_pole_figure(
+    grains: &ParticleGrains,
+    mineral: &Mineral,
+    axis: &CrystalAxes,
+    pole_figure_configuration: &PoleFiguresConfiguration,
+    lambert: &Lambert,
+    density_chunk_memory: Option<usize>,
+) -> Result<PoleFigure, Box<dyn std::error::Error>> {
+    let particle_arrays = grains.axis_vectors(mineral, axis);
+    let counts = multiples_of_uniform_density(
+        gaussian_orientation_counts(&particle_arrays, lambert, density_chunk_memory)?,
+        lambert,
+    );
+
+    let mut max_count_value = 0.0;
+    let mut min_count_value = f64::INFINITY;
+
+    for i in 0..counts.shape()[0] - 1 {
+        for j in 0..counts.shape()[1] - 1 {
+            if counts[[i, j]] > max_count_value {
+                max_count_value = counts[[i, j]];
+            }
+            if counts[[i, j]] < min_count_value {
+                min_count_value = counts[[i, j]];
+            }
+        }
+    }
+
+    let (grain_poles, grain_volume_fractions) = if pole_figure_configuration.scatter {
+        (
+            particle_arrays
+                .outer_iter()
+                .map(|axis| lambert.project_axis(&[axis[0], axis[1], axis[2]]))
+                .collect(),
+            grains.axis_volume_fractions(mineral, axis),
+        )
+    } else {
+        (vec![], vec![])
+    };
+
+    Ok(PoleFigure {
+        crystal_axis: axis.clone(),
+        mineral: mineral.clone(),
+        counts,
+        max_count: max_count_value,
+        min_count: min_count_value,
+        color_scale_min: 0.0,
+        color_scale_max: max_count_value,
+        grain_poles,
+        grain_volume_fractions,
+    })
+}
 
-                let time = timestep_to_time[time_step as usize];
+/// Set the range of the color scale of the pole figures of a figure. For a `Global` range, `global_max_count` is
+/// the maximum count of the pole figures of all the figures.
+fn set_color_scale_range(
+    pole_figure_grid: &mut [Vec<PoleFigure>],
+    color_scale_range: &ColorScaleRange,
+    global_max_count: Option<f64>,
+) {
+    let figure_max_count = pole_figure_grid
+        .iter()
+        .flatten()
+        .map(|pole_figure| pole_figure.max_count)
+        .fold(0.0, f64::max);
+    let n_minerals = pole_figure_grid.first().map_or(0, |column| column.len());
+    for figure_vertical_axis in 0..n_minerals {
+        let row_max_count = pole_figure_grid
+            .iter()
+            .map(|column| column[figure_vertical_axis].max_count)
+            .fold(0.0, f64::max);
+        for column in pole_figure_grid.iter_mut() {
+            let pole_figure = &mut column[figure_vertical_axis];
+            let (min, max) = match color_scale_range {
+                ColorScaleRange::Subplot => (0.0, pole_figure.max_count),
+                ColorScaleRange::Row => (0.0, row_max_count),
+                ColorScaleRange::Figure => (0.0, figure_max_count),
+                ColorScaleRange::Global => (0.0, global_max_count.unwrap_or(figure_max_count)),
+                ColorScaleRange::Fixed { min, max } => (*min, *max),
+            };
+            pole_figure.color_scale_min = min;
+            pole_figure.color_scale_max = max;
+        }
+    }
+}
 
+/// Draw the pole figures of a particle at every selected time step of an experiment as the frames of an animation.
+/// All the frames are drawn with the same color scale. Unless the range of the color scale is fixed, the pole
+/// figures of every frame are computed twice: first to find the maximum count of all the frames, and again when the
+/// frame is drawn, so that the pole figures of all the frames do not have to be in memory at the same time.
+#[allow(clippy::too_many_arguments)]
+fn make_particle_animation(
+    config: &Config,
+    animation_configuration: &AnimationConfiguration,
+    lpo_dir: &str,
+    timestep_to_time: &[f64],
+    lambert: &Lambert,
+    color_gradient: &Gradient<LinSrgb>,
+    task_pool: &TaskPool,
+    density_chunk_memory: Option<usize>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let pole_figure_configuration = match config.pole_figures.as_ref() {
+        Some(pole_figure_configuration) => pole_figure_configuration,
+        None => return Ok(()),
+    };
+    let particle_id = animation_configuration.particle_id;
+
+    let first_time_step = animation_configuration
+        .start_time
+        .map_or(0, |time| closest_time_step(timestep_to_time, time));
+    let last_time_step = animation_configuration
+        .end_time
+        .map_or(timestep_to_time.len().saturating_sub(1), |time| {
+            closest_time_step(timestep_to_time, time)
+        });
+    let time_steps: Vec<usize> = (first_time_step..=last_time_step)
+        .step_by(animation_configuration.time_step_interval.max(1))
+        .collect();
+
+    println!(
+        "Animating particle id {} over {} time steps.",
+        particle_id,
+        time_steps.len()
+    );
+
+    // All the frames use the fixed range of the color scale, or otherwise the maximum of all the frames.
+    let color_scale_range = match pole_figure_configuration.color_scale_range {
+        ColorScaleRange::Fixed { min, max } => ColorScaleRange::Fixed { min, max },
+        _ => ColorScaleRange::Global,
+    };
+    let read_frame =
+        |time_step: usize| -> Result<Option<(ParticleGrains, ParticleRecord)>, String> {
+            let particle_data = read_particle_data(
+                lpo_dir,
+                pole_figure_configuration,
+                time_step as u64,
+                particle_id,
+                config.compressed,
+            )?;
+            if particle_data.is_none() {
                 println!(
-                    "Processing time {} (requested time: {}), located in timestep : {}",
-                    time, output_time, time_step,
+                    "particle id {} not found for timestep {}.",
+                    particle_id, time_step
                 );
+            }
+            Ok(particle_data)
+        };
+    let frame_pole_figure_grid = |grains: &ParticleGrains| {
+        compute_pole_figure_grid(
+            grains,
+            pole_figure_configuration,
+            lambert,
+            density_chunk_memory,
+        )
+        .map_err(|error| error.to_string())
+    };
+    let frame_memory = |grains: &ParticleGrains| {
+        pole_figure_memory(
+            grains.n_grains(),
+            pole_figure_configuration.minerals.len() * pole_figure_configuration.axes.len(),
+            lambert,
+            density_chunk_memory,
+        )
+    };
+
+    // The first pass only keeps the time steps at which the particle is found and their maximum count.
+    let frame_max_counts: Vec<(usize, f64)> = task_pool
+        .run(&time_steps, |time_step| {
+            let (grains, _) = match read_frame(*time_step)? {
+                Some(particle_data) => particle_data,
+                None => return Ok((None, 0)),
+            };
+            let max_count = match color_scale_range {
+                ColorScaleRange::Fixed { .. } => 0.0,
+                _ => frame_pole_figure_grid(&grains)?
+                    .iter()
+                    .flatten()
+                    .map(|pole_figure| pole_figure.max_count)
+                    .fold(0.0, f64::max),
+            };
+            Ok((Some((*time_step, max_count)), frame_memory(&grains)))
+        })?
+        .into_iter()
+        .flatten()
+        .collect();
+    if frame_max_counts.is_empty() {
+        println!(
+            "No frames found for the animation of particle id {}.",
+            particle_id
+        );
+        return Ok(());
+    }
+    let global_max_count = frame_max_counts
+        .iter()
+        .map(|(_, max_count)| *max_count)
+        .fold(0.0, f64::max);
+
+    // The header of a frame only shows the particle id and the time, so that all the frames have the same size.
+    let frame_configuration = PoleFiguresConfiguration {
+        elastisity_header: false,
+        fabric_strength_header: false,
+        seismic_anisotropy_header: false,
+        hexagonal_axis_marker: false,
+        color_scale_range: color_scale_range.clone(),
+        ..pole_figure_configuration.clone()
+    };
+    let frame_style = PoleFigureStyle::new(&frame_configuration, color_gradient);
+
+    // The second pass computes the pole figures of a frame again and draws it right away. It returns the memory
+    // used for the frame.
+    let draw_frame = |time_step: usize, output: PoleFigureOutput| -> Result<usize, String> {
+        let (grains, particle_record) = read_frame(time_step)?.ok_or_else(|| {
+            format!(
+                "particle id {} not found for timestep {}.",
+                particle_id, time_step
+            )
+        })?;
+        let mut pole_figure_grid = frame_pole_figure_grid(&grains)?;
+        set_color_scale_range(
+            &mut pole_figure_grid,
+            &color_scale_range,
+            Some(global_max_count),
+        );
+        make_pole_figures(
+            &frame_style,
+            &ParticlePoleFigures {
+                particle_id: particle_id as u64,
+                time: timestep_to_time[time_step],
+                n_grains: grains.n_grains(),
+                particle_record: &particle_record,
+                pole_figure_grid: &pole_figure_grid,
+                labels: None,
+                fabric_strength: &[],
+                hexagonal_axis: None,
+                seismic_anisotropy: None,
+            },
+            lambert,
+            output,
+        )
+        .map_err(|error| error.to_string())?;
+        Ok(frame_memory(&grains))
+    };
+
+    let file_prefix = format!(
+        "{}{}{}_animation_{:05}",
+        lpo_dir,
+        pole_figure_configuration.figure_output_dir,
+        pole_figure_configuration.figure_output_prefix,
+        particle_id
+    );
+    match animation_configuration.format {
+        AnimationFormat::Gif => {
+            let output_file = format!("{}.gif", file_prefix);
+            let mut animation = PoleFigureAnimation::new(
+                Path::new(&output_file),
+                animation_configuration.frame_delay,
+            );
+            // the frames of a GIF are written in order, so they are drawn one after another
+            for (time_step, _) in &frame_max_counts {
+                draw_frame(*time_step, PoleFigureOutput::AnimationFrame(&mut animation))?;
+            }
+            println!("Written animation {}", output_file);
+        }
+        AnimationFormat::PngFrames => {
+            let frame_files: Vec<String> = (0..frame_max_counts.len())
+                .map(|frame| format!("{}_frame{:05}.png", file_prefix, frame))
+                .collect();
+            let frames: Vec<(usize, &String)> = frame_max_counts
+                .iter()
+                .map(|(time_step, _)| *time_step)
+                .zip(frame_files.iter())
+                .collect();
+            task_pool.run(&frames, |(time_step, frame_file)| {
+                let memory = draw_frame(
+                    *time_step,
+                    PoleFigureOutput::PngFrame(Path::new(frame_file)),
+                )?;
+                Ok(((), memory))
+            })?;
+            let frame_list_file = format!("{}_frames.txt", file_prefix);
+            fs::write(&frame_list_file, frame_files.join("\n") + "\n")?;
+            println!("Written frame list {}", frame_list_file);
+        }
+    }
+
+    Ok(())
+}
 
-                fs::create_dir_all(
-                    lpo_dir.to_owned() + &pole_figure_configuration.figure_output_dir,
+/// Make the figures which compare the pole figures along the rows and columns dimensions of the comparison
+/// configuration, one for every combination of the values of the other dimensions. The figures are drawn in
+/// parallel, and every figure only computes its own pole figures, so that the pole figures of all the particles,
+/// times and experiments do not have to be in memory at the same time.
+fn make_comparison_figures(
+    config: &Config,
+    comparison_configuration: &ComparisonConfiguration,
+    lambert: &Lambert,
+    color_gradient: &Gradient<LinSrgb>,
+    task_pool: &TaskPool,
+    density_chunk_memory: Option<usize>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let pole_figure_configuration = match config.pole_figures.as_ref() {
+        Some(pole_figure_configuration) => pole_figure_configuration,
+        None => return Ok(()),
+    };
+    let rows = comparison_configuration.rows;
+    let columns = comparison_configuration.columns;
+    if rows == columns {
+        return Err(format!(
+            "The rows and the columns of the comparison figures are both {:?}.",
+            rows
+        )
+        .into());
+    }
+
+    // The time step closest to every output time, for every experiment.
+    let mut experiment_time_steps: Vec<Vec<(usize, f64)>> = Vec::new();
+    for experiment_dir in &config.experiment_dirs {
+        let lpo_dir = config.base_dir.clone() + experiment_dir;
+        let statistics_file = lpo_dir.to_owned() + &pole_figure_configuration.time_data_file;
+        let timestep_to_time = read_time_data_file(Path::new(&statistics_file))?;
+        experiment_time_steps.push(
+            pole_figure_configuration
+                .times
+                .iter()
+                .map(|output_time| {
+                    let time_step = closest_time_step(&timestep_to_time, *output_time);
+                    (time_step, timestep_to_time[time_step])
+                })
+                .collect(),
+        );
+    }
+
+    // The number of values of every dimension, in the order of the `ComparisonDimension` variants.
+    let dimension_sizes = [
+        pole_figure_configuration.particle_ids.len(),
+        pole_figure_configuration.times.len(),
+        config.experiment_dirs.len(),
+        pole_figure_configuration.minerals.len(),
+        pole_figure_configuration.axes.len(),
+    ];
+    if dimension_sizes.contains(&0) {
+        return Ok(());
+    }
+    // The label of the value of a dimension of a pole figure, which is identified by the index of the value of
+    // every dimension.
+    let label = |dimension: ComparisonDimension, indices: &[usize; 5]| -> String {
+        let index = indices[dimension as usize];
+        match dimension {
+            ComparisonDimension::Particle => {
+                format!("id={}", pole_figure_configuration.particle_ids[index])
+            }
+            ComparisonDimension::Time => format!(
+                "t={:.3e}",
+                experiment_time_steps[indices[ComparisonDimension::Experiment as usize]][index].1
+            ),
+            ComparisonDimension::Experiment => config.experiment_dirs[index]
+                .trim_end_matches('/')
+                .to_string(),
+            ComparisonDimension::Mineral => pole_figure_configuration.minerals[index]
+                .to_string()
+                .to_lowercase(),
+            ComparisonDimension::Axis => pole_figure_configuration.axes[index].label(),
+        }
+    };
+    let file_label = |dimension: ComparisonDimension, indices: &[usize; 5]| -> String {
+        let index = indices[dimension as usize];
+        match dimension {
+            ComparisonDimension::Particle => {
+                format!("{:05}", pole_figure_configuration.particle_ids[index])
+            }
+            ComparisonDimension::Time => format!(
+                "t{:05}",
+                experiment_time_steps[indices[ComparisonDimension::Experiment as usize]][index].0
+            ),
+            ComparisonDimension::Experiment => config.experiment_dirs[index]
+                .trim_end_matches('/')
+                .replace('/', "-"),
+            ComparisonDimension::Mineral => mineral_file_string(std::slice::from_ref(
+                &pole_figure_configuration.minerals[index],
+            ))
+            .trim_end_matches('_')
+            .to_string(),
+            ComparisonDimension::Axis => {
+                format!(
+                    "{}-Axis",
+                    pole_figure_configuration.axes[index].file_label()
                 )
-                .unwrap();
+            }
+        }
+    };
 
-                let file_prefix_figures = pole_figure_configuration.figure_output_dir.to_owned()
-                    + &pole_figure_configuration.figure_output_prefix;
-                let mut rank_id = 0;
+    // All the pole figures of a comparison figure share the color scale, and the header only shows what they
+    // have in common.
+    let color_scale_range = match pole_figure_configuration.color_scale_range {
+        ColorScaleRange::Fixed { min, max } => ColorScaleRange::Fixed { min, max },
+        _ => ColorScaleRange::Figure,
+    };
+    let comparison_pole_figure_configuration = PoleFiguresConfiguration {
+        elastisity_header: false,
+        fabric_strength_header: false,
+        seismic_anisotropy_header: false,
+        hexagonal_axis_marker: false,
+        color_scale_range: color_scale_range.clone(),
+        ..pole_figure_configuration.clone()
+    };
 
-                let gam = 1.0; //0.5; // exponent for power-law normalization of color-scale
-                               //let f = 1.05; // factor to make plot limits slightly bigger than the circle
+    let dimensions = [
+        ComparisonDimension::Particle,
+        ComparisonDimension::Time,
+        ComparisonDimension::Experiment,
+        ComparisonDimension::Mineral,
+        ComparisonDimension::Axis,
+    ];
+    let fixed_dimensions: Vec<ComparisonDimension> = dimensions
+        .iter()
+        .copied()
+        .filter(|dimension| *dimension != rows && *dimension != columns)
+        .collect();
+    let number_of_figures: usize = fixed_dimensions
+        .iter()
+        .map(|dimension| dimension_sizes[*dimension as usize])
+        .product();
+
+    // The output file and the labels of every figure, which are identified by the indices of the values of the
+    // fixed dimensions.
+    let mut figures: Vec<([usize; 5], String, PoleFigureLabels)> = Vec::new();
+    for figure in 0..number_of_figures {
+        let mut indices = [0; 5];
+        let mut remainder = figure;
+        for dimension in &fixed_dimensions {
+            indices[*dimension as usize] = remainder % dimension_sizes[*dimension as usize];
+            remainder /= dimension_sizes[*dimension as usize];
+        }
 
-                let sphere_points = 301; //76;//151;
+        let mut pole_figure_labels: Vec<Vec<[String; 2]>> = Vec::new();
+        for column in 0..dimension_sizes[columns as usize] {
+            let mut label_column = Vec::new();
+            for row in 0..dimension_sizes[rows as usize] {
+                indices[columns as usize] = column;
+                indices[rows as usize] = row;
+                label_column.push([label(rows, &indices), label(columns, &indices)]);
+            }
+            pole_figure_labels.push(label_column);
+        }
 
-                println!(
-                    "particle ids size {}",
-                    pole_figure_configuration.particle_ids.len()
-                );
-                for particle_id in &pole_figure_configuration.particle_ids {
-                    println!("processing particle_id {}", particle_id);
-                    let mut particle_olivine_a_axis_vectors = Vec::new();
-                    let mut particle_olivine_b_axis_vectors = Vec::new();
-                    let mut particle_olivine_c_axis_vectors = Vec::new();
-                    let mut particle_enstatite_a_axis_vectors = Vec::new();
-                    let mut particle_enstatite_b_axis_vectors = Vec::new();
-                    let mut particle_enstatite_c_axis_vectors = Vec::new();
-
-                    let mut file_found: bool = false;
-                    while !file_found {
-                        let angles_file = format!(
-                            "{}{}-{:05}.{:04}.dat",
-                            lpo_dir,
-                            pole_figure_configuration.grain_data_file_prefix,
-                            time_step,
-                            rank_id
-                        );
-                        let angles_file = Path::new(&angles_file);
-
-                        let mut config_mineral_string = String::new();
-                        for mineral in pole_figure_configuration.minerals.clone() {
-                            config_mineral_string = format!(
-                                "{}{}",
-                                config_mineral_string.clone(),
-                                match mineral {
-                                    Mineral::Olivine => {
-                                        "oli_"
-                                    }
-                                    Mineral::Enstatite => {
-                                        "ens_"
-                                    }
-                                }
-                            )
-                        }
-                        let mut config_axis_string = String::new();
-                        for axis in pole_figure_configuration.axes.clone() {
-                            config_axis_string = format!(
-                                "{}{}",
-                                config_axis_string.clone(),
-                                match axis {
-                                    CrystalAxes::AAxis => {
-                                        "A-"
-                                    }
-                                    CrystalAxes::BAxis => {
-                                        "B-"
-                                    }
-                                    CrystalAxes::CAxis => {
-                                        "C-"
-                                    }
+        // With experiments in the rows or columns the figure is written in the base directory.
+        let output_dir = if fixed_dimensions.contains(&ComparisonDimension::Experiment) {
+            config.base_dir.clone()
+                + &config.experiment_dirs[indices[ComparisonDimension::Experiment as usize]]
+        } else {
+            config.base_dir.clone()
+        } + &pole_figure_configuration.figure_output_dir;
+        fs::create_dir_all(&output_dir)?;
+        let output_file = format!(
+            "{}{}_comparison_{:?}-{:?}_{}.{}",
+            output_dir,
+            pole_figure_configuration.figure_output_prefix,
+            rows,
+            columns,
+            fixed_dimensions
+                .iter()
+                .map(|dimension| file_label(*dimension, &indices))
+                .collect::<Vec<String>>()
+                .join("_"),
+            pole_figure_configuration.output_format.extension()
+        );
+        let title = fixed_dimensions
+            .iter()
+            .map(|dimension| label(*dimension, &indices))
+            .collect::<Vec<String>>()
+            .join(", ");
+        figures.push((
+            indices,
+            output_file,
+            PoleFigureLabels {
+                title,
+                pole_figures: pole_figure_labels,
+            },
+        ));
+    }
+    println!("Making {} comparison figures.", figures.len());
+
+    let style = PoleFigureStyle::new(&comparison_pole_figure_configuration, color_gradient);
+    task_pool.run(&figures, |(figure_indices, output_file, labels)| {
+                // The particles of the figure are only read once, even if they are in several pole figures.
+                let mut particles: HashMap<(usize, usize, usize), (ParticleGrains, ParticleRecord)> =
+                    HashMap::new();
+                let mut pole_figure_grid: Vec<Vec<PoleFigure>> = Vec::new();
+                for column in 0..dimension_sizes[columns as usize] {
+                    let mut pole_figure_column = Vec::new();
+                    for row in 0..dimension_sizes[rows as usize] {
+                        let mut indices = *figure_indices;
+                        indices[columns as usize] = column;
+                        indices[rows as usize] = row;
+                        let experiment = indices[ComparisonDimension::Experiment as usize];
+                        let time = indices[ComparisonDimension::Time as usize];
+                        let particle = indices[ComparisonDimension::Particle as usize];
+                        let (grains, _) = match particles.entry((experiment, time, particle)) {
+                            Entry::Occupied(entry) => entry.into_mut(),
+                            Entry::Vacant(entry) => match read_particle_data(
+                                &(config.base_dir.clone() + &config.experiment_dirs[experiment]),
+                                pole_figure_configuration,
+                                experiment_time_steps[experiment][time].0 as u64,
+                                pole_figure_configuration.particle_ids[particle],
+                                config.compressed,
+                            )? {
+                                Some(particle_data) => entry.insert(particle_data),
+                                None => {
+                                    println!(
+                                        "Skipping comparison figure {}, because {} at {} of {} is not found.",
+                                        output_file,
+                                        label(ComparisonDimension::Particle, &indices),
+                                        label(ComparisonDimension::Time, &indices),
+                                        label(ComparisonDimension::Experiment, &indices),
+                                    );
+                                    return Ok(((), 0));
                                 }
-                            )
-                        }
-                        config_axis_string = format!("{}Axis_", config_axis_string.clone());
-
-                        let output_file = format!(
-                            "{}{}_{}{}{}{}_g{}_sp{}_t{:05}.{:05}.png",
-                            lpo_dir,
-                            file_prefix_figures,
-                            if elastisity_header {
-                                "elastic_"
-                            } else {
-                                "no-elastic_"
                             },
-                            config_mineral_string,
-                            config_axis_string,
-                            pole_figure_configuration.color_scale.to_string(),
-                            gam,
-                            sphere_points,
-                            time_step,
-                            particle_id
-                        );
-                        let output_file = Path::new(&output_file);
-                        let particle_file = format!(
-                            "{}{}-{:05}.{:04}.dat",
-                            lpo_dir,
-                            pole_figure_configuration.particle_data_file_prefix,
-                            time_step,
-                            rank_id
-                        );
-                        let particle_info_file = Path::new(&particle_file);
-
-                        println!("  trying file name: {}", angles_file.display());
-
-                        // check wheter file exists, if not it means that is reached the max rank, so stop.
-                        if !(fs::metadata(angles_file).is_ok()) {
-                            println!(
-                                "particle id {} not found for timestep {}.",
-                                particle_id, time_step
-                            );
-                            break;
-                        }
-
-                        // check wheter file is empty, if not continue to next rank
-                        if fs::metadata(angles_file).unwrap().len() == 0 {
-                            rank_id = rank_id + 1;
-                            continue;
-                        }
-
-                        println!(
-                            "  found particle id {} in:{}",
-                            particle_id,
-                            angles_file.display()
+                        };
+                        pole_figure_column.push(
+                            compute_pole_figure(
+                                grains,
+                                &pole_figure_configuration.minerals
+                                    [indices[ComparisonDimension::Mineral as usize]],
+                                &pole_figure_configuration.axes
+                                    [indices[ComparisonDimension::Axis as usize]],
+                                pole_figure_configuration,
+                                lambert,
+                                density_chunk_memory,
+                            )
+                            .map_err(|error| error.to_string())?,
                         );
-                        let file = File::open(angles_file).unwrap();
-                        let metadata = file.metadata().unwrap();
+                    }
+                    pole_figure_grid.push(pole_figure_column);
+                }
+                set_color_scale_range(&mut pole_figure_grid, &color_scale_range, None);
+
+                // the header of a comparison figure only shows the labels, so any of its particles can be passed
+                let (_, particle_record) = particles
+                    .values()
+                    .next()
+                    .ok_or("a comparison figure without pole figures")?;
+                make_pole_figures(
+                    &style,
+                    &ParticlePoleFigures {
+                        particle_id: 0,
+                        time: 0.0,
+                        n_grains: 0,
+                        particle_record,
+                        pole_figure_grid: &pole_figure_grid,
+                        labels: Some(labels),
+                        fabric_strength: &[],
+                        hexagonal_axis: None,
+                        seismic_anisotropy: None,
+                    },
+                    lambert,
+                    PoleFigureOutput::File {
+                        path: Path::new(output_file),
+                        format: &pole_figure_configuration.output_format,
+                    },
+                )
+                .map_err(|error| error.to_string())?;
+                let memory = particles
+                    .values()
+                    .map(|(grains, _)| pole_figure_memory(grains.n_grains(), 0, lambert, density_chunk_memory))
+                    .sum::<usize>()
+                    + pole_figure_memory(0, pole_figure_grid.iter().flatten().count(), lambert, density_chunk_memory);
+                Ok(((), memory))
+    })?;
+
+    Ok(())
+}
 
-                        let mut buf_reader =
-                            BufReader::with_capacity(metadata.len() as usize, file);
+/// Return the short names of the minerals used in the file names of the figures.
+fn mineral_file_string(minerals: &[Mineral]) -> String {
+    minerals
+        .iter()
+        .map(|mineral| match mineral {
+            Mineral::Olivine => "oli_",
+            Mineral::Enstatite => "ens_",
+        })
+        .collect()
+}
 
-                        let mut decoded_data = Vec::new();
+/// Compute the counts of the inverse pole figures of all the sample directions and minerals. The directions in the
+/// crystal frame are projected with the [001] axis along the projection axis of the Lambert grid, the [100] axis
+/// along the horizontal axis and the [010] axis along the vertical axis. All the symmetric equivalents are included,
+/// so the counts are symmetric and the fundamental sector can be drawn on its own.
+fn compute_inverse_pole_figures(
+    grains: &ParticleGrains,
+    minerals: &[Mineral],
+    sample_directions: &[SampleDirection],
+    lambert: &Lambert,
+    density_chunk_memory: Option<usize>,
+) -> Result<Vec<Vec<InversePoleFigure>>, Box<dyn std::error::Error>> {
+    let mut inverse_pole_figure_grid: Vec<Vec<InversePoleFigure>> = sample_directions
+        .iter()
+        .map(|sample_direction| {
+            minerals
+                .iter()
+                .map(|mineral| {
+                    let crystal_directions = sample_direction_in_crystal_frame(
+                        grains.rotation_matrices(mineral),
+                        &sample_direction.vector(),
+                        mineral.symmetry(),
+                    );
+                    let mut projected_directions = crystal_directions.clone();
+                    projected_directions
+                        .column_mut(1)
+                        .assign(&crystal_directions.column(2));
+                    projected_directions
+                        .column_mut(2)
+                        .assign(&crystal_directions.column(1));
+                    let counts = gaussian_orientation_counts(
+                        &projected_directions,
+                        lambert,
+                        density_chunk_memory,
+                    )?;
+                    let max_count = counts.iter().cloned().fold(0.0, f64::max);
+                    Ok(InversePoleFigure {
+                        mineral: mineral.clone(),
+                        sample_direction: sample_direction.clone(),
+                        counts,
+                        max_count,
+                    })
+                })
+                .collect()
+        })
+        .collect::<Result<_, Box<dyn std::error::Error>>>()?;
+
+    // use the same color scale for all the sample directions of a mineral
+    for figure_vertical_axis in 0..minerals.len() {
+        let max_count_value = inverse_pole_figure_grid
+            .iter()
+            .map(|column| column[figure_vertical_axis].max_count)
+            .fold(0.0, f64::max);
+        for column in inverse_pole_figure_grid.iter_mut() {
+            column[figure_vertical_axis].max_count = max_count_value;
+        }
+    }
 
-                        let compressed = config.compressed;
+    Ok(inverse_pole_figure_grid)
+}
 
-                        let decoded_reader = if compressed {
-                            let mut decoder = libflate::zlib::Decoder::new(buf_reader).unwrap();
-                            decoder.read_to_end(&mut decoded_data).unwrap();
-                            String::from_utf8_lossy(&decoded_data)
-                        } else {
-                            let data = buf_reader.fill_buf().unwrap();
-                            String::from_utf8_lossy(&data)
-                        };
+/// Return the file name of the inverse pole figures of a particle at a timestep.
+fn inverse_pole_figure_output_file(
+    lpo_dir: &str,
+    pole_figure_configuration: &PoleFiguresConfiguration,
+    inverse_pole_figure_configuration: &InversePoleFiguresConfiguration,
+    sphere_points: usize,
+    time_step: u64,
+    particle_id: usize,
+) -> String {
+    let config_mineral_string = mineral_file_string(&pole_figure_configuration.minerals);
+    let config_direction_string = inverse_pole_figure_configuration
+        .sample_directions
+        .iter()
+        .map(|sample_direction| sample_direction.file_label())
+        .collect::<Vec<String>>()
+        .join("-");
+
+    format!(
+        "{}{}{}_{}{}_{}_{}_sp{}_t{:05}.{:05}.png",
+        lpo_dir,
+        pole_figure_configuration.figure_output_dir,
+        inverse_pole_figure_configuration.figure_output_prefix,
+        config_mineral_string,
+        config_direction_string,
+        pole_figure_configuration
+            .color_scale
+            .file_label(pole_figure_configuration.reverse_color_scale),
+        pole_figure_configuration.color_scaling.file_label(),
+        sphere_points,
+        time_step,
+        particle_id
+    )
+}
 
-                        let mut rdr = csv::ReaderBuilder::new()
-                            .has_headers(true)
-                            .delimiter(b' ')
-                            .from_reader(decoded_reader.as_bytes());
-
-                        let mut integer = 0;
-                        for result in rdr.deserialize() {
-                            let record: Record = result.unwrap();
-                            if record.id == *particle_id {
-                                let deg_to_rad = std::f64::consts::PI / 180.;
-
-                                // olivine
-                                let euler_angles = Array::from(vec![
-                                    record.mineral_0_EA_phi.unwrap() * deg_to_rad,
-                                    record.mineral_0_EA_theta.unwrap() * deg_to_rad,
-                                    record.mineral_0_EA_z.unwrap() * deg_to_rad,
-                                ]);
-                                let rotation_matrix =
-                                    euler_angles_to_rotation_matrix(euler_angles).unwrap();
-
-                                particle_olivine_a_axis_vectors
-                                    .push(rotation_matrix.row(0).to_owned());
-                                particle_olivine_b_axis_vectors
-                                    .push(rotation_matrix.row(1).to_owned());
-                                particle_olivine_c_axis_vectors
-                                    .push(rotation_matrix.row(2).to_owned());
-
-                                // enstatite
-                                let euler_angles = Array::from(vec![
-                                    record.mineral_1_EA_phi.unwrap() * deg_to_rad,
-                                    record.mineral_1_EA_theta.unwrap() * deg_to_rad,
-                                    record.mineral_1_EA_z.unwrap() * deg_to_rad,
-                                ]);
-                                let rotation_matrix =
-                                    euler_angles_to_rotation_matrix(euler_angles).unwrap();
-
-                                particle_enstatite_a_axis_vectors
-                                    .push(rotation_matrix.row(0).to_owned());
-                                particle_enstatite_b_axis_vectors
-                                    .push(rotation_matrix.row(1).to_owned());
-                                particle_enstatite_c_axis_vectors
-                                    .push(rotation_matrix.row(2).to_owned());
-                            }
-                            integer = integer + 1;
-                        }
-
-                        // check if the particle id was found in this file, otherwise continue
-                        if particle_olivine_a_axis_vectors.len() == 0 {
-                            rank_id = rank_id + 1;
-                            continue;
-                        }
-                        file_found = true;
-
-                        // retrieve anisotropy info
-                        let mut particle_record = ParticleRecord {
-                            id: 0,
-                            x: 0.0,
-                            y: 0.0,
-                            z: Some(0.0),
-                            olivine_deformation_type: None,
-                            full_norm_square: None,
-                            triclinic_norm_square_p1: None,
-                            triclinic_norm_square_p2: None,
-                            triclinic_norm_square_p3: None,
-                            monoclinic_norm_square_p1: None,
-                            monoclinic_norm_square_p2: None,
-                            monoclinic_norm_square_p3: None,
-                            orthohombic_norm_square_p1: None,
-                            orthohombic_norm_square_p2: None,
-                            orthohombic_norm_square_p3: None,
-                            tetragonal_norm_square_p1: None,
-                            tetragonal_norm_square_p2: None,
-                            tetragonal_norm_square_p3: None,
-                            hexagonal_norm_square_p1: None,
-                            hexagonal_norm_square_p2: None,
-                            hexagonal_norm_square_p3: None,
-                            isotropic_norm_square: None,
-                        };
+/// Write the fabric strength indices of all the particles, times and minerals of an experiment to a space
+/// separated table.
+fn write_fabric_strength_file(
+    output_file: &Path,
+    results: &[(&ParticleTask, &ParticleTaskResult)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b' ')
+        .from_path(output_file)?;
+    writer.write_record([
+        "id",
+        "timestep",
+        "time",
+        "mineral",
+        "n_grains",
+        "j_index",
+        "m_index",
+        "odf_entropy",
+        "odf_max_density",
+    ])?;
+    for (task, result) in results {
+        for fabric_strength in &result.fabric_strength {
+            writer.write_record(&[
+                task.particle_id.to_string(),
+                task.time_step.to_string(),
+                task.time.to_string(),
+                fabric_strength.mineral.to_string(),
+                fabric_strength.n_grains.to_string(),
+                fabric_strength.j_index.to_string(),
+                fabric_strength.m_index.to_string(),
+                fabric_strength.odf_entropy.to_string(),
+                fabric_strength.odf_max_density.to_string(),
+            ])?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
 
-                        let particle_info_file = File::open(particle_info_file).unwrap();
-                        let buf_reader = BufReader::new(particle_info_file);
-
-                        let mut rdr = csv::ReaderBuilder::new()
-                            .has_headers(true)
-                            .delimiter(b' ')
-                            .from_reader(buf_reader);
-
-                        for result in rdr.deserialize() {
-                            // We must tell Serde what type we want to deserialize into.
-                            let record: ParticleRecord = result.unwrap();
-                            if record.id == *particle_id {
-                                particle_record = record;
-                            }
-                        }
-                        // end retrieve anisotropy info
-                        println!("end retrieve antisotropy info");
-                        println!("create lambert equal area gridpoint");
-
-                        let lambert =
-                            create_lambert_equal_area_gridpoint(sphere_points, "upper".to_string())
-                                .unwrap();
-
-                        println!("create sphere_point_grid");
-                        let mut sphere_point_grid =
-                            Array2::zeros((3, sphere_points * sphere_points));
-
-                        for i in 0..sphere_points {
-                            for j in 0..sphere_points {
-                                sphere_point_grid[[0, i * sphere_points + j]] = lambert.x[[i, j]];
-                                sphere_point_grid[[1, i * sphere_points + j]] = lambert.y[[i, j]];
-                                sphere_point_grid[[2, i * sphere_points + j]] = lambert.z[[i, j]];
-                            }
-                        }
-
-                        let n_grains = particle_olivine_a_axis_vectors.len();
-
-                        let mut pole_figure_grid: Vec<Vec<PoleFigure>> = vec![
-                                vec![
-                                    PoleFigure {
-                                        crystal_axis: CrystalAxes::AAxis,
-                                        mineral: Mineral::Olivine,
-                                        counts: Array2::zeros((n_grains, 3)),
-                                        max_count: 0.0,
-                                    };
-                                    pole_figure_configuration.minerals.len()
-                                ];
-                                pole_figure_configuration.axes.len()
-                            ];
-
-                        let mut figure_horizontal_axis = 0;
-                        for axis in pole_figure_configuration.axes.clone() {
-                            let mut figure_vertical_axis = 0;
-                            for mineral in pole_figure_configuration.minerals.clone() {
-                                let mut particle_arrays = Array2::zeros((n_grains, 3));
-                                for i in 0..n_grains {
-                                    for j in 0..3 {
-                                        particle_arrays[[i, j]] = match axis {
-                                            CrystalAxes::AAxis => match mineral {
-                                                Mineral::Olivine => {
-                                                    particle_olivine_a_axis_vectors[i][j]
-                                                }
-                                                Mineral::Enstatite => {
-                                                    particle_enstatite_a_axis_vectors[i][j]
-                                                }
-                                            },
-                                            CrystalAxes::BAxis => match mineral {
-                                                Mineral::Olivine => {
-                                                    particle_olivine_b_axis_vectors[i][j]
-                                                }
-                                                Mineral::Enstatite => {
-                                                    particle_enstatite_b_axis_vectors[i][j]
-                                                }
-                                            },
-                                            CrystalAxes::CAxis => match mineral {
-                                                Mineral::Olivine => {
-                                                    particle_olivine_c_axis_vectors[i][j]
-                                                }
-                                                Mineral::Enstatite => {
-                                                    particle_enstatite_c_axis_vectors[i][j]
-                                                }
-                                            },
-                                        };
-                                    }
-                                }
-                                let counts = gaussian_orientation_counts(
-                                    &particle_arrays,
-                                    &sphere_point_grid,
-                                    sphere_points,
-                                )
-                                .unwrap();
-
-                                let mut max_count_value = 0.0;
-
-                                for i in 0..counts.shape()[0] - 1 {
-                                    for j in 0..counts.shape()[1] - 1 {
-                                        if counts[[i, j]] > max_count_value {
-                                            max_count_value = counts[[i, j]];
-                                        }
-                                    }
-                                }
+/// Write the orientation tensor eigenvalues and eigenvectors and the Vollmer fabric shape of all the particles,
+/// times, minerals and crystal axes of an experiment to a space separated table. The eigenvalues and eigenvectors
+/// are sorted from large to small.
+fn write_orientation_tensor_file(
+    output_file: &Path,
+    results: &[(&ParticleTask, &ParticleTaskResult)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b' ')
+        .from_path(output_file)?;
+    writer.write_record([
+        "id", "timestep", "time", "mineral", "axis", "e1", "e2", "e3", "v1_x", "v1_y", "v1_z",
+        "v2_x", "v2_y", "v2_z", "v3_x", "v3_y", "v3_z", "P", "G", "R", "B",
+    ])?;
+    for (task, result) in results {
+        for (mineral, axis, orientation_tensor) in &result.orientation_tensors {
+            let mut record = vec![
+                task.particle_id.to_string(),
+                task.time_step.to_string(),
+                task.time.to_string(),
+                mineral.to_string(),
+                axis.to_string(),
+            ];
+            record.extend(orientation_tensor.eigenvalues.iter().map(|x| x.to_string()));
+            for i in 0..3 {
+                record.extend(
+                    orientation_tensor
+                        .eigenvectors
+                        .column(i)
+                        .iter()
+                        .map(|x| x.to_string()),
+                );
+            }
+            record.extend(
+                [
+                    orientation_tensor.point(),
+                    orientation_tensor.girdle(),
+                    orientation_tensor.random(),
+                    orientation_tensor.cylindricity(),
+                ]
+                .iter()
+                .map(|x| x.to_string()),
+            );
+            writer.write_record(&record)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
 
-                                pole_figure_grid[figure_horizontal_axis][figure_vertical_axis] =
-                                    PoleFigure {
-                                        crystal_axis: axis.clone(),
-                                        mineral: mineral.clone(),
-                                        counts: counts,
-                                        max_count: max_count_value,
-                                    };
-                                figure_vertical_axis += 1;
-                            }
-                            figure_horizontal_axis += 1;
-                        }
-
-                        // set all horizontal max values to the max of the horizontal max max values
-                        let mut figure_vertical_axis = 0;
-                        for _mineral in pole_figure_configuration.minerals.clone() {
-                            let mut max_count_value = 0.0;
-
-                            // loop to find the max value
-                            let mut figure_horizontal_axis = 0;
-                            for _axis in pole_figure_configuration.axes.clone() {
-                                if pole_figure_grid[figure_horizontal_axis][figure_vertical_axis]
-                                    .max_count
-                                    > max_count_value
-                                {
-                                    max_count_value = pole_figure_grid[figure_horizontal_axis]
-                                        [figure_vertical_axis]
-                                        .max_count;
-                                }
-                                figure_horizontal_axis += 1;
-                            }
-
-                            // loop to write max value
-                            figure_horizontal_axis = 0;
-                            for _axis in pole_figure_configuration.axes.clone() {
-                                pole_figure_grid[figure_horizontal_axis][figure_vertical_axis]
-                                    .max_count = max_count_value;
-                                figure_horizontal_axis += 1;
-                            }
-                            figure_vertical_axis += 1;
-                        }
-
-                        make_pole_figures(
-                            pole_figure_configuration.small_figure,
-                            pole_figure_configuration.no_description_text,
-                            elastisity_header,
-                            n_grains,
-                            0,
-                            &pole_figure_grid,
-                            &lambert,
-                            output_file,
-                            &particle_record,
-                            time,
-                            gam,
-                            &pole_figure_configuration.color_scale,
-                        )
-                        .unwrap();
+/// Write the distributions of uncorrelated misorientation angles of all the particles, times and minerals of an
+/// experiment to a space separated table, with one row per bin. The observed and random columns contain the
+/// fraction of the grain pairs in each bin.
+fn write_misorientation_file(
+    output_file: &Path,
+    results: &[(&ParticleTask, &ParticleTaskResult)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b' ')
+        .from_path(output_file)?;
+    writer.write_record([
+        "id",
+        "timestep",
+        "time",
+        "mineral",
+        "n_pairs",
+        "angle_min",
+        "angle_max",
+        "observed",
+        "random",
+    ])?;
+    for (task, result) in results {
+        for (mineral, distribution) in &result.misorientation_distributions {
+            for (bin, (observed, random)) in distribution
+                .observed
+                .iter()
+                .zip(distribution.random.iter())
+                .enumerate()
+            {
+                writer.write_record(&[
+                    task.particle_id.to_string(),
+                    task.time_step.to_string(),
+                    task.time.to_string(),
+                    mineral.to_string(),
+                    distribution.n_pairs.to_string(),
+                    (bin as f64 * distribution.bin_width).to_string(),
+                    ((bin + 1) as f64 * distribution.bin_width).to_string(),
+                    observed.to_string(),
+                    random.to_string(),
+                ])?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
 
-                        println!(
-                            "  After make_polefigures: Elapsed time: {:.2?}",
-                            before.elapsed()
-                        );
-                    }
-                    println!("go to next id");
-                }
+/// Write the independent components of the Voigt, Reuss and Hill averaged stiffness in GPa of all the particles
+/// and times of an experiment to a space separated table, with one row per average.
+fn write_elastic_tensor_file(
+    output_file: &Path,
+    results: &[(&ParticleTask, &ParticleTaskResult)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b' ')
+        .from_path(output_file)?;
+    let mut header = vec![
+        "id".to_string(),
+        "timestep".to_string(),
+        "time".to_string(),
+        "average".to_string(),
+    ];
+    header.extend((1..=6).flat_map(|i| (i..=6).map(move |j| format!("C{}{}", i, j))));
+    writer.write_record(&header)?;
+    for (task, result) in results {
+        if let Some(averaged_stiffness) = &result.averaged_stiffness {
+            for (average, stiffness) in &[
+                ("Voigt", &averaged_stiffness.voigt),
+                ("Reuss", &averaged_stiffness.reuss),
+                ("Hill", &averaged_stiffness.hill),
+            ] {
+                let mut record = vec![
+                    task.particle_id.to_string(),
+                    task.time_step.to_string(),
+                    task.time.to_string(),
+                    average.to_string(),
+                ];
+                record.extend(
+                    independent_components(stiffness)
+                        .iter()
+                        .map(|x| x.to_string()),
+                );
+                writer.write_record(&record)?;
             }
         }
-    });
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the hexagonal symmetry axis of the Voigt averaged stiffness of all the particles and times of an experiment
+/// to a space separated table. The axis is given as a unit vector and as trend and plunge, and `fast` tells whether
+/// the P-wave velocity is largest along the axis.
+fn write_hexagonal_axis_file(
+    output_file: &Path,
+    results: &[(&ParticleTask, &ParticleTaskResult)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b' ')
+        .from_path(output_file)?;
+    writer.write_record([
+        "id", "timestep", "time", "x", "y", "z", "trend", "plunge", "fast",
+    ])?;
+    for (task, result) in results {
+        if let Some(hexagonal_axis) = &result.hexagonal_axis {
+            writer.write_record(&[
+                task.particle_id.to_string(),
+                task.time_step.to_string(),
+                task.time.to_string(),
+                hexagonal_axis.vector[0].to_string(),
+                hexagonal_axis.vector[1].to_string(),
+                hexagonal_axis.vector[2].to_string(),
+                hexagonal_axis.trend.to_string(),
+                hexagonal_axis.plunge.to_string(),
+                hexagonal_axis.fast.to_string(),
+            ])?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the radial and azimuthal anisotropy parameters of the Voigt averaged stiffness of all the particles and
+/// times of an experiment to a space separated table. The Love parameters and the coefficients of the azimuthal
+/// variation are in GPa, the amplitudes in percent and the azimuth in degree.
+fn write_seismic_anisotropy_file(
+    output_file: &Path,
+    results: &[(&ParticleTask, &ParticleTaskResult)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b' ')
+        .from_path(output_file)?;
+    writer.write_record([
+        "id",
+        "timestep",
+        "time",
+        "xi",
+        "phi",
+        "eta",
+        "A",
+        "C",
+        "F",
+        "L",
+        "N",
+        "Bc",
+        "Bs",
+        "Gc",
+        "Gs",
+        "Hc",
+        "Hs",
+        "Ec",
+        "Es",
+        "B_amplitude",
+        "G_amplitude",
+        "G_fast_azimuth",
+        "E_amplitude",
+    ])?;
+    for (task, result) in results {
+        if let Some(anisotropy) = &result.seismic_anisotropy {
+            let mut record = vec![
+                task.particle_id.to_string(),
+                task.time_step.to_string(),
+                task.time.to_string(),
+            ];
+            record.extend(
+                [
+                    anisotropy.xi,
+                    anisotropy.phi,
+                    anisotropy.eta,
+                    anisotropy.a,
+                    anisotropy.c,
+                    anisotropy.f,
+                    anisotropy.l,
+                    anisotropy.n,
+                    anisotropy.bc,
+                    anisotropy.bs,
+                    anisotropy.gc,
+                    anisotropy.gs,
+                    anisotropy.hc,
+                    anisotropy.hs,
+                    anisotropy.ec,
+                    anisotropy.es,
+                    anisotropy.b_amplitude(),
+                    anisotropy.g_amplitude(),
+                    anisotropy.g_fast_azimuth(),
+                    anisotropy.e_amplitude(),
+                ]
+                .iter()
+                .map(|x| x.to_string()),
+            );
+            writer.write_record(&record)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Compute the apparent SKS splitting of a column of particles at one time for all the back-azimuths. Returns
+/// `None` if the thickness of the layers can not be determined.
+fn column_splitting(
+    column: &[(&ParticleTask, &ParticleTaskResult)],
+    sks_splitting_configuration: &SksSplittingConfiguration,
+    elasticity_configuration: &ElasticityConfiguration,
+) -> Option<Vec<ApparentSplitting>> {
+    let vertical_direction = &elasticity_configuration.vertical_direction;
+    let mut column: Vec<(f64, &AveragedStiffness)> = column
+        .iter()
+        .filter_map(|(_, result)| {
+            result
+                .averaged_stiffness
+                .as_ref()
+                .map(|stiffness| (height(&result.position, vertical_direction), stiffness))
+        })
+        .collect();
+    // the layers are ordered from the bottom to the top.
+    column.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let thicknesses = match sks_splitting_configuration.layer_thickness {
+        Some(layer_thickness) => vec![layer_thickness; column.len()],
+        None => {
+            let heights: Vec<f64> = column.iter().map(|(height, _)| *height).collect();
+            layer_thicknesses(&heights)?
+        }
+    };
+
+    let density = elasticity_configuration.density();
+    let layers: Vec<SplittingLayer> = match sks_splitting_configuration.method {
+        SplittingMethod::SingleLayer => {
+            let total_thickness: f64 = thicknesses.iter().sum();
+            let mut stiffness = Array2::zeros((6, 6));
+            for ((_, averaged_stiffness), thickness) in column.iter().zip(thicknesses.iter()) {
+                stiffness = stiffness + &averaged_stiffness.voigt * (*thickness / total_thickness);
+            }
+            vec![SplittingLayer::from_stiffness(
+                &stiffness,
+                density,
+                total_thickness,
+                vertical_direction,
+            )]
+        }
+        SplittingMethod::MultiLayer => column
+            .iter()
+            .zip(thicknesses.iter())
+            .map(|((_, averaged_stiffness), thickness)| {
+                SplittingLayer::from_stiffness(
+                    &averaged_stiffness.voigt,
+                    density,
+                    *thickness,
+                    vertical_direction,
+                )
+            })
+            .collect(),
+    };
+
+    let n_back_azimuths = (180. / sks_splitting_configuration.back_azimuth_step).ceil() as usize;
+    Some(
+        (0..n_back_azimuths)
+            .map(|i| {
+                apparent_splitting(
+                    &layers,
+                    i as f64 * sks_splitting_configuration.back_azimuth_step,
+                    sks_splitting_configuration.period,
+                )
+            })
+            .collect(),
+    )
+}
+
+/// Write the apparent SKS splitting of the column of particles of an experiment as a function of back-azimuth for
+/// all times to a space separated table. The azimuths are in degree and the delay time in seconds.
+fn write_sks_splitting_file(
+    output_file: &Path,
+    results: &[(&ParticleTask, &ParticleTaskResult)],
+    sks_splitting_configuration: &SksSplittingConfiguration,
+    elasticity_configuration: &ElasticityConfiguration,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b' ')
+        .from_path(output_file)?;
+    writer.write_record([
+        "timestep",
+        "time",
+        "n_particles",
+        "back_azimuth",
+        "fast_azimuth",
+        "delay_time",
+    ])?;
+
+    let mut time_steps: Vec<u64> = results.iter().map(|(task, _)| task.time_step).collect();
+    time_steps.sort_unstable();
+    time_steps.dedup();
+    for time_step in time_steps {
+        // a particle which is given more than once is only one layer of the column.
+        let mut column: Vec<(&ParticleTask, &ParticleTaskResult)> = results
+            .iter()
+            .filter(|(task, _)| task.time_step == time_step)
+            .cloned()
+            .collect();
+        column.sort_by_key(|(task, _)| task.particle_id);
+        column.dedup_by_key(|(task, _)| task.particle_id);
+        let splitting = match column_splitting(
+            &column,
+            sks_splitting_configuration,
+            elasticity_configuration,
+        ) {
+            Some(splitting) => splitting,
+            None => {
+                println!(
+                    "Could not compute the SKS splitting for timestep {}: the column needs at least two \
+                     particles or a layer thickness.",
+                    time_step
+                );
+                continue;
+            }
+        };
+        for apparent in splitting {
+            writer.write_record(&[
+                time_step.to_string(),
+                column[0].0.time.to_string(),
+                column.len().to_string(),
+                apparent.back_azimuth.to_string(),
+                apparent.fast_azimuth.to_string(),
+                apparent.delay_time.to_string(),
+            ])?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the olivine BA-index and fabric type of all the particles and times of an experiment to a space
+/// separated table. The fabric type expected from the olivine deformation type in the simulation is added
+/// for comparison, and is `None` if the particle has no deformation type or it is not an olivine fabric type.
+fn write_fabric_type_file(
+    output_file: &Path,
+    results: &[(&ParticleTask, &ParticleTaskResult)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b' ')
+        .from_path(output_file)?;
+    writer.write_record([
+        "id",
+        "timestep",
+        "time",
+        "ba_index",
+        "fabric_type",
+        "deformation_type",
+        "matches",
+    ])?;
+    for (task, result) in results {
+        if let Some(olivine_fabric) = &result.olivine_fabric {
+            let simulated_type = result
+                .olivine_deformation_type
+                .and_then(OlivineFabricType::from_deformation_type);
+            writer.write_record(&[
+                task.particle_id.to_string(),
+                task.time_step.to_string(),
+                task.time.to_string(),
+                olivine_fabric.ba_index.to_string(),
+                olivine_fabric.fabric_type.to_string(),
+                simulated_type.map_or("None".to_string(), |x| x.to_string()),
+                (simulated_type == Some(olivine_fabric.fabric_type)).to_string(),
+            ])?;
+        }
+    }
+    writer.flush()?;
     Ok(())
 }
 
 /// Utility function to compute a rotation matrix from Z-X-Z Euler angles.
-fn euler_angles_to_rotation_matrix(
+pub fn euler_angles_to_rotation_matrix(
     euler_angles: Array<f64, ndarray::Dim<[usize; 1]>>, //phi1: f64,
                                                         //theta: f64,
                                                         //phi2: f64
@@ -719,31 +2549,48 @@ fn euler_angles_to_rotation_matrix(
 /// using contouring circles and continuous weighting functions.
 /// For the k value we use a combination between option 2 and 3, where option
 /// 2 is used as long as k is larger as 100, otherwise it is set to 100.
-fn gaussian_orientation_counts(
+///
+/// The `particles` array contains one (unit) axis per row, and the counts are computed on
+/// the sphere point grid of the provided `lambert` structure, which can be reused for any
+/// number of particles.
+///
+/// The temporary array of the angles between all the particles and all the grid points can
+/// become large. If `max_memory` (in bytes) is provided, the particles are processed in chunks
+/// so that this array does not use more memory than that, with a minimum of one particle per chunk.
+pub fn gaussian_orientation_counts(
     particles: &Array2<f64>,
-    sphere_point_grid: &Array2<f64>,
-    sphere_points: usize,
+    lambert: &Lambert,
+    max_memory: Option<usize>,
 ) -> Result<Array2<f64>, Box<dyn std::error::Error>> {
     let npts = particles.shape()[0];
+    let sphere_points = lambert.sphere_points;
 
     // Choose k, which defines width of spherical gaussian  (table 3)
     let k = (2. * (1. + npts as f64 / 9.)).min(100.);
 
     // Given k, calculate standard deviation (eq 13b)
-    let std_dev = ((npts as f64 * (k as f64 / 2. - 1.) / (k * k)) as f64).sqrt();
+    let std_dev = (npts as f64 * (k / 2. - 1.) / (k * k)).sqrt();
+
+    let memory_per_particle = sphere_points * sphere_points * std::mem::size_of::<f64>();
+    let chunk_size = match max_memory {
+        Some(max_memory) => (max_memory / memory_per_particle).max(1),
+        None => npts.max(1),
+    };
 
-    // Calculate dot product
-    let mut cosalpha = particles.dot(sphere_point_grid);
+    let mut counts = Array::zeros(sphere_points * sphere_points);
+    for particle_chunk in particles.axis_chunks_iter(Axis(0), chunk_size) {
+        // Calculate dot product
+        let mut cosalpha = particle_chunk.dot(&lambert.sphere_point_grid);
 
-    // Calculate the counts from the spherical gaussian
-    //let counts = Array::zeros(cosalpha.shape());
-    cosalpha.par_mapv_inplace(f64::abs);
+        // Calculate the counts from the spherical gaussian
+        cosalpha.par_mapv_inplace(f64::abs);
 
-    cosalpha = (k as f64) * (cosalpha - 1.);
+        cosalpha = k * (cosalpha - 1.);
 
-    cosalpha.par_mapv_inplace(f64::exp);
+        cosalpha.par_mapv_inplace(f64::exp);
 
-    let counts = cosalpha.sum_axis(Axis(0));
+        counts += &cosalpha.sum_axis(Axis(0));
+    }
     let counts = counts.into_shape((sphere_points, sphere_points))?;
 
     // normalize so each MUD is 3 sigma from that expected for a uniform
@@ -753,6 +2600,24 @@ fn gaussian_orientation_counts(
     Ok(counts)
 }
 
+/// Normalize the counts on the equal area grid of `lambert` to multiples of uniform density (MUD), by dividing
+/// them by their average over the grid points within the pole figure. All the grid points represent the same area,
+/// so the average is the density of a uniform distribution.
+pub fn multiples_of_uniform_density(counts: Array2<f64>, lambert: &Lambert) -> Array2<f64> {
+    let (sum, n_points) = counts
+        .iter()
+        .zip(lambert.x_plane.iter().zip(lambert.z_plane.iter()))
+        .filter(|(_, (x, z))| *x * *x + *z * *z <= lambert.r_plane * lambert.r_plane)
+        .fold((0.0, 0), |(sum, n_points), (count, _)| {
+            (sum + count, n_points + 1)
+        });
+    if sum > 0.0 {
+        counts / (sum / n_points as f64)
+    } else {
+        counts
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -798,6 +2663,188 @@ mod tests {
         euler_angles
     }
 
+    #[test]
+    fn test_closest_time_step() {
+        let timestep_to_time = vec![0.0, 0.5, 1.0, 1.5];
+        assert_eq!(closest_time_step(&timestep_to_time, 0.0), 0);
+        assert_eq!(closest_time_step(&timestep_to_time, 0.6), 1);
+        assert_eq!(closest_time_step(&timestep_to_time, 0.8), 2);
+        assert_eq!(closest_time_step(&timestep_to_time, 1.0), 2);
+        assert_eq!(closest_time_step(&timestep_to_time, 10.0), 3);
+    }
+
+    #[test]
+    fn test_multiples_of_uniform_density() {
+        let lambert = create_lambert_equal_area_gridpoint(51, "upper".to_string()).unwrap();
+        let counts = multiples_of_uniform_density(Array2::from_elem((51, 51), 3.0), &lambert);
+        assert!(counts.iter().all(|count| (count - 1.0).abs() < 1e-12));
+
+        // the density of axes evenly spread over the sphere (a Fibonacci lattice) is close to uniform
+        let n_particles = 2000;
+        let golden_angle = std::f64::consts::PI * (3.0 - 5.0_f64.sqrt());
+        let mut particles = Array2::zeros((n_particles, 3));
+        for (i, mut particle) in particles.outer_iter_mut().enumerate() {
+            let z = 1.0 - 2.0 * (i as f64 + 0.5) / n_particles as f64;
+            let radius = (1.0 - z * z).sqrt();
+            let angle = i as f64 * golden_angle;
+            particle.assign(&ndarray::arr1(&[
+                radius * angle.cos(),
+                radius * angle.sin(),
+                z,
+            ]));
+        }
+        let counts = multiples_of_uniform_density(
+            gaussian_orientation_counts(&particles, &lambert, None).unwrap(),
+            &lambert,
+        );
+        assert!((counts[[25, 25]] - 1.0).abs() < 0.3, "{}", counts[[25, 25]]);
+    }
+
+    #[test]
+    fn test_set_color_scale_range() {
+        let pole_figure = |max_count: f64| PoleFigure {
+            crystal_axis: CrystalAxes::AAxis,
+            mineral: Mineral::Olivine,
+            counts: Array2::zeros((2, 2)),
+            max_count,
+            min_count: 0.1,
+            color_scale_min: 0.0,
+            color_scale_max: 0.0,
+            grain_poles: vec![],
+            grain_volume_fractions: vec![],
+        };
+        // two axes (columns) of two minerals (rows)
+        let grid = vec![
+            vec![pole_figure(1.0), pole_figure(4.0)],
+            vec![pole_figure(2.0), pole_figure(3.0)],
+        ];
+        let ranges = |color_scale_range: ColorScaleRange, global_max_count: Option<f64>| {
+            let mut grid = grid.clone();
+            set_color_scale_range(&mut grid, &color_scale_range, global_max_count);
+            grid.iter()
+                .map(|column| {
+                    column
+                        .iter()
+                        .map(|pole_figure| {
+                            (pole_figure.color_scale_min, pole_figure.color_scale_max)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            ranges(ColorScaleRange::Subplot, None),
+            vec![vec![(0., 1.), (0., 4.)], vec![(0., 2.), (0., 3.)]]
+        );
+        assert_eq!(
+            ranges(ColorScaleRange::Row, None),
+            vec![vec![(0., 2.), (0., 4.)], vec![(0., 2.), (0., 4.)]]
+        );
+        assert_eq!(
+            ranges(ColorScaleRange::Figure, None),
+            vec![vec![(0., 4.), (0., 4.)], vec![(0., 4.), (0., 4.)]]
+        );
+        assert_eq!(
+            ranges(ColorScaleRange::Global, Some(5.)),
+            vec![vec![(0., 5.), (0., 5.)], vec![(0., 5.), (0., 5.)]]
+        );
+        assert_eq!(
+            ranges(ColorScaleRange::Fixed { min: 0.5, max: 1.5 }, None),
+            vec![vec![(0.5, 1.5), (0.5, 1.5)], vec![(0.5, 1.5), (0.5, 1.5)]]
+        );
+    }
+
+    #[test]
+    fn test_global_color_scale_range() {
+        let mut config = load_configuration_file(PathBuf::from(
+            "examples/example_experiment_1/example_1_config.toml",
+        ))
+        .unwrap();
+        config.pole_figures.as_mut().unwrap().color_scale_range = ColorScaleRange::Global;
+        let pole_figure_configuration = config.pole_figures.as_ref().unwrap();
+        let lambert = create_lambert_equal_area_gridpoint(51, "upper".to_string()).unwrap();
+        let color_gradient = set_color_gradient(
+            &pole_figure_configuration.color_scale,
+            pole_figure_configuration.reverse_color_scale,
+        )
+        .unwrap();
+        let tasks: Vec<ParticleTask> = [(1, 0.5), (2, 1.0)]
+            .iter()
+            .map(|(time_step, time)| ParticleTask {
+                lpo_dir: "examples/example_experiment_1/".to_string(),
+                time_step: *time_step,
+                time: *time,
+                particle_id: 0,
+            })
+            .collect();
+
+        // the first pass only keeps the maximum count of the pole figures of every task
+        let results: Vec<Option<ParticleTaskResult>> = tasks
+            .iter()
+            .map(|task| {
+                process_particle_task(task, &config, None, None, &lambert, &color_gradient, None)
+                    .unwrap()
+            })
+            .collect();
+        let global_max_count = global_max_count(&results);
+
+        // the second pass computes the pole figures again, which all get the same color scale
+        let mut task_max_counts = vec![];
+        for (task, result) in tasks.iter().zip(results.iter()) {
+            let deferred = result
+                .as_ref()
+                .unwrap()
+                .deferred_pole_figures
+                .as_ref()
+                .unwrap();
+            let pole_figure_grid = global_color_scale_pole_figure_grid(
+                task,
+                pole_figure_configuration,
+                config.compressed,
+                &lambert,
+                None,
+                global_max_count,
+            )
+            .unwrap();
+            let max_count = pole_figure_grid
+                .iter()
+                .flatten()
+                .map(|pole_figure| pole_figure.max_count)
+                .fold(0.0, f64::max);
+            assert_eq!(max_count, deferred.max_count);
+            for pole_figure in pole_figure_grid.iter().flatten() {
+                assert_eq!(pole_figure.color_scale_min, 0.0);
+                assert_eq!(pole_figure.color_scale_max, global_max_count);
+            }
+            task_max_counts.push(max_count);
+        }
+        assert_eq!(
+            task_max_counts.iter().cloned().fold(0.0, f64::max),
+            global_max_count
+        );
+    }
+
+    #[test]
+    fn test_gaussian_orientation_counts_in_chunks() {
+        let lambert = create_lambert_equal_area_gridpoint(21, "upper".to_string()).unwrap();
+        let mut particles = Array2::zeros((5, 3));
+        for i in 0..5 {
+            let angle = i as f64 * 0.3;
+            particles[[i, 0]] = angle.cos();
+            particles[[i, 1]] = angle.sin();
+        }
+
+        let counts = gaussian_orientation_counts(&particles, &lambert, None).unwrap();
+        // only enough memory for two particles per chunk
+        let chunked_counts =
+            gaussian_orientation_counts(&particles, &lambert, Some(2 * 21 * 21 * 8)).unwrap();
+
+        for (count, chunked_count) in counts.iter().zip(chunked_counts.iter()) {
+            assert!((count - chunked_count).abs() < 1e-12);
+        }
+    }
+
     #[test]
     fn test_euler_angle_and_rotation_matrix_functions_part_1() {
         let mut rot1 = Array2::zeros((3, 3));
@@ -1108,4 +3155,120 @@ mod tests {
             (rot2_expected[[2, 2]] - rot3[[2, 2]]).abs()
         );
     }
+
+    #[test]
+    fn test_read_particle_data_errors() {
+        let lpo_dir = std::env::temp_dir().join("cpo_analyzer_test_read_particle_data/");
+        fs::create_dir_all(lpo_dir.join("particle_CPO")).unwrap();
+        let lpo_dir = lpo_dir.to_str().unwrap();
+        let pole_figure_configuration = PoleFiguresConfiguration::default();
+        let grain_file = format!("{}particle_CPO/weighted_CPO-00001.0000.dat", lpo_dir);
+
+        // a missing euler angle and a truncated record are errors, not panics.
+        fs::write(
+            &grain_file,
+            "id mineral_0_EA_phi mineral_0_EA_theta mineral_0_EA_z mineral_1_EA_phi mineral_1_EA_theta mineral_1_EA_z\n0 0.5 0.5  0.1 0.5 0.1\n",
+        )
+        .unwrap();
+        let error = read_particle_data(lpo_dir, &pole_figure_configuration, 1, 0, false)
+            .err()
+            .unwrap();
+        assert!(error.contains("mineral_0_EA_z"), "{}", error);
+
+        fs::write(
+            &grain_file,
+            "id mineral_0_EA_phi mineral_0_EA_theta mineral_0_EA_z mineral_1_EA_phi mineral_1_EA_theta mineral_1_EA_z\n0 0.5 0.5\n",
+        )
+        .unwrap();
+        assert!(read_particle_data(lpo_dir, &pole_figure_configuration, 1, 0, false).is_err());
+        assert!(read_particle_data(lpo_dir, &pole_figure_configuration, 1, 0, true).is_err());
+
+        // a time step without files is not found.
+        assert!(
+            read_particle_data(lpo_dir, &pole_figure_configuration, 2, 0, false)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_symmetry_decomposition_against_aspect() {
+        let pole_figure_configuration = PoleFiguresConfiguration::default();
+        let elasticity_configuration = ElasticityConfiguration::default();
+        let (grains, aspect_record) = read_particle_data(
+            "examples/example_experiment_1/",
+            &pole_figure_configuration,
+            1,
+            0,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+
+        let minerals = [Mineral::Olivine, Mineral::Enstatite];
+        let stiffness: Vec<Array2<f64>> = minerals
+            .iter()
+            .map(|mineral| elasticity_configuration.stiffness(mineral))
+            .collect();
+        let phases: Vec<MineralPhase> = minerals
+            .iter()
+            .zip(stiffness.iter())
+            .map(|(mineral, stiffness)| MineralPhase {
+                stiffness,
+                rotation_matrices: grains.rotation_matrices(mineral),
+                volume_fraction: elasticity_configuration.volume_fraction(mineral),
+            })
+            .collect();
+        let averaged_stiffness = average_stiffness(&phases).unwrap();
+        let mut record = aspect_record.clone();
+        set_symmetry_decomposition(
+            &mut record,
+            &SymmetryDecomposition::from_stiffness(&averaged_stiffness.voigt),
+        );
+
+        // The example data only contains one grain per mineral, while the norms in the particle data come from an
+        // ASPECT aggregate which is almost isotropic, so only the full and isotropic norms can be compared.
+        let relative_difference = |ours: Option<f64>, aspect: Option<f64>| {
+            (ours.unwrap() - aspect.unwrap()).abs() / aspect.unwrap()
+        };
+        assert!(
+            relative_difference(record.full_norm_square, aspect_record.full_norm_square) < 0.02
+        );
+        assert!(
+            relative_difference(
+                record.isotropic_norm_square,
+                aspect_record.isotropic_norm_square
+            ) < 0.01
+        );
+
+        // Both decompositions split the full norm over the symmetry classes for every permutation of the SCCS.
+        for record in &[record, aspect_record] {
+            let sums = [
+                record.triclinic_norm_square_p1.unwrap()
+                    + record.monoclinic_norm_square_p1.unwrap()
+                    + record.orthohombic_norm_square_p1.unwrap()
+                    + record.tetragonal_norm_square_p1.unwrap()
+                    + record.hexagonal_norm_square_p1.unwrap(),
+                record.triclinic_norm_square_p2.unwrap()
+                    + record.monoclinic_norm_square_p2.unwrap()
+                    + record.orthohombic_norm_square_p2.unwrap()
+                    + record.tetragonal_norm_square_p2.unwrap()
+                    + record.hexagonal_norm_square_p2.unwrap(),
+                record.triclinic_norm_square_p3.unwrap()
+                    + record.monoclinic_norm_square_p3.unwrap()
+                    + record.orthohombic_norm_square_p3.unwrap()
+                    + record.tetragonal_norm_square_p3.unwrap()
+                    + record.hexagonal_norm_square_p3.unwrap(),
+            ];
+            let full = record.full_norm_square.unwrap();
+            for sum in &sums {
+                assert!(
+                    (sum + record.isotropic_norm_square.unwrap() - full).abs() < 1e-5 * full,
+                    "the norms of the symmetry classes ({}) do not add up to the full norm ({})",
+                    sum + record.isotropic_norm_square.unwrap(),
+                    full
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,162 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use ndarray::{Array1, Array2};
+
+/// Compute the eigenvalues and eigenvectors of a real symmetric matrix with the cyclic Jacobi method. The
+/// eigenvalues are sorted from large to small, and the eigenvectors are stored in the columns of the returned
+/// matrix in the same order.
+pub fn symmetric_eigen(matrix: &Array2<f64>) -> (Array1<f64>, Array2<f64>) {
+    let n = matrix.nrows();
+    let mut a = matrix.clone();
+    let mut v: Array2<f64> = Array2::eye(n);
+
+    for _sweep in 0..100 {
+        let off_diagonal: f64 = (0..n)
+            .flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[[i, j]] * a[[i, j]])
+            .sum();
+        let diagonal: f64 = (0..n).map(|i| a[[i, i]] * a[[i, i]]).sum();
+        if off_diagonal <= f64::EPSILON * f64::EPSILON * diagonal || off_diagonal == 0. {
+            break;
+        }
+
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[[p, q]] == 0. {
+                    continue;
+                }
+                // compute the rotation which zeroes a[p,q]
+                let theta = (a[[q, q]] - a[[p, p]]) / (2. * a[[p, q]]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.).sqrt());
+                let c = 1. / (t * t + 1.).sqrt();
+                let s = t * c;
+
+                for k in 0..n {
+                    let a_kp = a[[k, p]];
+                    let a_kq = a[[k, q]];
+                    a[[k, p]] = c * a_kp - s * a_kq;
+                    a[[k, q]] = s * a_kp + c * a_kq;
+                }
+                for k in 0..n {
+                    let a_pk = a[[p, k]];
+                    let a_qk = a[[q, k]];
+                    a[[p, k]] = c * a_pk - s * a_qk;
+                    a[[q, k]] = s * a_pk + c * a_qk;
+                }
+                for k in 0..n {
+                    let v_kp = v[[k, p]];
+                    let v_kq = v[[k, q]];
+                    v[[k, p]] = c * v_kp - s * v_kq;
+                    v[[k, q]] = s * v_kp + c * v_kq;
+                }
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|i, j| a[[*j, *j]].partial_cmp(&a[[*i, *i]]).unwrap());
+
+    let eigenvalues = Array1::from_shape_fn(n, |i| a[[order[i], order[i]]]);
+    let eigenvectors = Array2::from_shape_fn((n, n), |(i, j)| v[[i, order[j]]]);
+    (eigenvalues, eigenvectors)
+}
+
+/// Compute the inverse of a square matrix with Gauss-Jordan elimination with partial pivoting. Returns
+/// `None` if the matrix is singular.
+pub fn invert(matrix: &Array2<f64>) -> Option<Array2<f64>> {
+    let n = matrix.nrows();
+    let mut a = matrix.clone();
+    let mut inverse: Array2<f64> = Array2::eye(n);
+    let scale = a.iter().fold(0., |max: f64, value| max.max(value.abs()));
+
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|i, j| {
+                a[[*i, column]]
+                    .abs()
+                    .partial_cmp(&a[[*j, column]].abs())
+                    .unwrap()
+            })
+            .unwrap();
+        if a[[pivot, column]].abs() <= f64::EPSILON * scale {
+            return None;
+        }
+        for k in 0..n {
+            a.swap([column, k], [pivot, k]);
+            inverse.swap([column, k], [pivot, k]);
+        }
+
+        let diagonal = a[[column, column]];
+        for k in 0..n {
+            a[[column, k]] /= diagonal;
+            inverse[[column, k]] /= diagonal;
+        }
+        for row in 0..n {
+            if row == column {
+                continue;
+            }
+            let factor = a[[row, column]];
+            if factor == 0. {
+                continue;
+            }
+            for k in 0..n {
+                a[[row, k]] -= factor * a[[column, k]];
+                inverse[[row, k]] -= factor * inverse[[column, k]];
+            }
+        }
+    }
+    Some(inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::arr2;
+
+    #[test]
+    fn test_symmetric_eigen() {
+        let matrix = arr2(&[[4., 1., 2.], [1., 3., 0.5], [2., 0.5, 5.]]);
+        let (eigenvalues, eigenvectors) = symmetric_eigen(&matrix);
+
+        assert!(eigenvalues[0] >= eigenvalues[1] && eigenvalues[1] >= eigenvalues[2]);
+        assert!((eigenvalues.sum() - 12.).abs() < 1e-10);
+        for i in 0..3 {
+            let vector = eigenvectors.column(i);
+            let difference = matrix.dot(&vector) - &vector * eigenvalues[i];
+            assert!(difference.iter().all(|value| value.abs() < 1e-10));
+            assert!((vector.dot(&vector) - 1.).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_invert() {
+        let matrix = arr2(&[[0., 1., 2.], [1., 3., 0.5], [2., 0.5, 5.]]);
+        let inverse = invert(&matrix).unwrap();
+        let identity = matrix.dot(&inverse);
+        for i in 0..3 {
+            for j in 0..3 {
+                let expected = if i == j { 1. } else { 0. };
+                assert!((identity[[i, j]] - expected).abs() < 1e-12);
+            }
+        }
+        assert!(invert(&arr2(&[[1., 2.], [2., 4.]])).is_none());
+    }
+}
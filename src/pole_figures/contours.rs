@@ -0,0 +1,176 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use ndarray::Array2;
+
+/// A straight piece of a contour line, from the first to the second point.
+pub type ContourSegment = [(f64, f64); 2];
+
+/// Compute the contour line of `level` through the values given on the grid points with the coordinates `x` and `z`
+/// with the marching squares algorithm. The contour is returned as unconnected segments, one or two per grid cell
+/// the contour passes through. Saddle cells are resolved with the average of the four corners.
+pub fn contour_segments(
+    x: &Array2<f64>,
+    z: &Array2<f64>,
+    values: &Array2<f64>,
+    level: f64,
+) -> Vec<ContourSegment> {
+    let mut segments = Vec::new();
+    let (rows, columns) = values.dim();
+    for i in 0..rows.saturating_sub(1) {
+        for j in 0..columns.saturating_sub(1) {
+            // the corners of the cell in order around the cell
+            let corners = [(i, j), (i, j + 1), (i + 1, j + 1), (i + 1, j)];
+            let above: Vec<bool> = corners.iter().map(|&c| values[c] >= level).collect();
+
+            // the crossings of the contour with the edges of the cell, in the same order as the corners
+            let mut crossings = Vec::with_capacity(4);
+            for edge in 0..4 {
+                let (a, b) = (corners[edge], corners[(edge + 1) % 4]);
+                if above[edge] != above[(edge + 1) % 4] {
+                    let t = (level - values[a]) / (values[b] - values[a]);
+                    crossings.push((x[a] + t * (x[b] - x[a]), z[a] + t * (z[b] - z[a]), edge));
+                }
+            }
+
+            match crossings.len() {
+                2 => segments.push([
+                    (crossings[0].0, crossings[0].1),
+                    (crossings[1].0, crossings[1].1),
+                ]),
+                4 => {
+                    let center = corners.iter().map(|&c| values[c]).sum::<f64>() / 4.;
+                    let p: Vec<(f64, f64)> = crossings.iter().map(|c| (c.0, c.1)).collect();
+                    if (center >= level) == above[0] {
+                        // the first and third corner are connected through the center, so the contour cuts off the
+                        // second and fourth corner.
+                        segments.push([p[0], p[1]]);
+                        segments.push([p[2], p[3]]);
+                    } else {
+                        segments.push([p[3], p[0]]);
+                        segments.push([p[1], p[2]]);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+    segments
+}
+
+/// Clip the contour segments to the circle with the given radius around the origin. Segments which are completely
+/// outside of the circle are removed and segments crossing the circle are cut off at the circle.
+pub fn clip_to_circle(segments: &[ContourSegment], radius: f64) -> Vec<ContourSegment> {
+    let inside = |p: &(f64, f64)| p.0 * p.0 + p.1 * p.1 <= radius * radius;
+    segments
+        .iter()
+        .filter_map(|segment| {
+            let (p, q) = (segment[0], segment[1]);
+            match (inside(&p), inside(&q)) {
+                (true, true) => Some(*segment),
+                (false, false) => None,
+                (p_inside, _) => {
+                    let (start, end) = if p_inside { (p, q) } else { (q, p) };
+                    // solve |start + t (end - start)| = radius for t in [0, 1]
+                    let (dx, dz) = (end.0 - start.0, end.1 - start.1);
+                    let a = dx * dx + dz * dz;
+                    let b = 2. * (start.0 * dx + start.1 * dz);
+                    let c = start.0 * start.0 + start.1 * start.1 - radius * radius;
+                    let t = (-b + (b * b - 4. * a * c).max(0.).sqrt()) / (2. * a);
+                    Some([start, (start.0 + t * dx, start.1 + t * dz)])
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array;
+
+    fn grid(n: usize) -> (Array2<f64>, Array2<f64>) {
+        let x = Array2::from_shape_fn((n, n), |(_, j)| -1. + 2. * j as f64 / (n - 1) as f64);
+        let z = Array2::from_shape_fn((n, n), |(i, _)| 1. - 2. * i as f64 / (n - 1) as f64);
+        (x, z)
+    }
+
+    #[test]
+    fn test_contour_segments_circle() {
+        // the contour of the squared radius is a circle
+        let (x, z) = grid(101);
+        let values = Array::from_shape_fn((101, 101), |c| x[c] * x[c] + z[c] * z[c]);
+        let segments = contour_segments(&x, &z, &values, 0.25);
+        assert!(!segments.is_empty());
+        let mut length = 0.;
+        for segment in &segments {
+            for point in segment {
+                let radius = (point.0 * point.0 + point.1 * point.1).sqrt();
+                assert!((radius - 0.5).abs() < 1e-3, "radius: {}", radius);
+            }
+            length += ((segment[1].0 - segment[0].0).powi(2)
+                + (segment[1].1 - segment[0].1).powi(2))
+            .sqrt();
+        }
+        assert!(
+            (length - std::f64::consts::PI).abs() < 1e-2,
+            "length: {}",
+            length
+        );
+
+        // no contour outside of the range of the values
+        assert!(contour_segments(&x, &z, &values, 3.).is_empty());
+    }
+
+    #[test]
+    fn test_contour_segments_saddle() {
+        // a single cell with the high values at the top left and bottom right corner
+        let (x, z) = grid(2);
+        let values = Array2::from_shape_vec((2, 2), vec![1., 0., 0., 1.]).unwrap();
+
+        // the center is above the level, so the low corners at the top right and bottom left are cut off
+        let segments = contour_segments(&x, &z, &values, 0.4);
+        assert_eq!(segments.len(), 2);
+        assert!(segments[0].iter().all(|p| p.0 > 0. && p.1 > 0.));
+        assert!(segments[1].iter().all(|p| p.0 < 0. && p.1 < 0.));
+
+        // the center is below the level, so the high corners at the top left and bottom right are cut off
+        let segments = contour_segments(&x, &z, &values, 0.6);
+        assert_eq!(segments.len(), 2);
+        assert!(segments[0].iter().all(|p| p.0 < 0. && p.1 > 0.));
+        assert!(segments[1].iter().all(|p| p.0 > 0. && p.1 < 0.));
+    }
+
+    #[test]
+    fn test_clip_to_circle() {
+        let segments = vec![
+            [(0., 0.), (0.5, 0.)],
+            [(0., 0.), (2., 0.)],
+            [(2., 2.), (3., 2.)],
+            [(0., 3.), (0., 0.5)],
+        ];
+        let clipped = clip_to_circle(&segments, 1.);
+        assert_eq!(clipped.len(), 3);
+        assert_eq!(clipped[0], [(0., 0.), (0.5, 0.)]);
+        assert!((clipped[1][1].0 - 1.).abs() < 1e-12);
+        assert_eq!(clipped[2][0], (0., 0.5));
+        assert!((clipped[2][1].1 - 1.).abs() < 1e-12);
+    }
+}
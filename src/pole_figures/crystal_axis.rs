@@ -18,12 +18,106 @@
   <http://www.gnu.org/licenses/>.
 */
 
+use crate::pole_figures::minerals::Mineral;
+
+use ndarray::{arr1, Array1};
 use serde_derive::{Deserialize, Serialize};
+use std::fmt;
 
-/// A enum to define what axis of the pole figure should be plotted.
+/// A enum to define what axis of the pole figure should be plotted. Besides the crystal axes, directions and
+/// poles to planes can be given by their Miller indices, either as a single direction or plane, or as the family
+/// of all the directions or planes which are symmetrically equivalent to it. In the configuration file these are
+/// given as for example `{ Plane = [0, 1, 0] }` for (010) or `{ PlaneFamily = [1, 1, 0] }` for {110}.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum CrystalAxes {
     AAxis,
     BAxis,
     CAxis,
+    /// The direction [uvw].
+    Direction([i32; 3]),
+    /// The symmetrically equivalent directions <uvw>.
+    DirectionFamily([i32; 3]),
+    /// The pole to the plane (hkl).
+    Plane([i32; 3]),
+    /// The poles to the symmetrically equivalent planes {hkl}.
+    PlaneFamily([i32; 3]),
+}
+
+impl CrystalAxes {
+    /// Return the unit vectors in the Cartesian crystal frame of the mineral which are projected in the pole
+    /// figure of this axis.
+    pub fn crystal_directions(&self, mineral: &Mineral) -> Vec<Array1<f64>> {
+        let lattice_parameters = mineral.lattice_parameters();
+        match self {
+            CrystalAxes::AAxis => vec![arr1(&[1., 0., 0.])],
+            CrystalAxes::BAxis => vec![arr1(&[0., 1., 0.])],
+            CrystalAxes::CAxis => vec![arr1(&[0., 0., 1.])],
+            CrystalAxes::Direction(indices) => vec![lattice_parameters.direction(indices)],
+            CrystalAxes::DirectionFamily(indices) => mineral
+                .symmetry()
+                .equivalent_directions(&lattice_parameters.direction(indices)),
+            CrystalAxes::Plane(indices) => vec![lattice_parameters.plane_normal(indices)],
+            CrystalAxes::PlaneFamily(indices) => mineral
+                .symmetry()
+                .equivalent_directions(&lattice_parameters.plane_normal(indices)),
+        }
+    }
+
+    /// Return the label of the axis shown in the pole figure.
+    pub fn label(&self) -> String {
+        let indices = |indices: &[i32; 3]| -> String {
+            indices
+                .iter()
+                .map(|index| index.to_string())
+                .collect::<Vec<String>>()
+                .join("")
+        };
+        match self {
+            CrystalAxes::AAxis => "a-axis".to_string(),
+            CrystalAxes::BAxis => "b-axis".to_string(),
+            CrystalAxes::CAxis => "c-axis".to_string(),
+            CrystalAxes::Direction(miller) => format!("[{}]", indices(miller)),
+            CrystalAxes::DirectionFamily(miller) => format!("<{}>", indices(miller)),
+            CrystalAxes::Plane(miller) => format!("({})", indices(miller)),
+            CrystalAxes::PlaneFamily(miller) => format!("{{{}}}", indices(miller)),
+        }
+    }
+
+    /// Return the short name of the axis used in the file names of the pole figures. Negative Miller indices
+    /// are written with an `m` in front.
+    pub fn file_label(&self) -> String {
+        let indices = |indices: &[i32; 3]| -> String {
+            indices
+                .iter()
+                .map(|index| {
+                    if *index < 0 {
+                        format!("m{}", -index)
+                    } else {
+                        index.to_string()
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join("")
+        };
+        match self {
+            CrystalAxes::AAxis => "A".to_string(),
+            CrystalAxes::BAxis => "B".to_string(),
+            CrystalAxes::CAxis => "C".to_string(),
+            CrystalAxes::Direction(miller) => format!("uvw{}", indices(miller)),
+            CrystalAxes::DirectionFamily(miller) => format!("UVW{}", indices(miller)),
+            CrystalAxes::Plane(miller) => format!("hkl{}", indices(miller)),
+            CrystalAxes::PlaneFamily(miller) => format!("HKL{}", indices(miller)),
+        }
+    }
+}
+
+impl fmt::Display for CrystalAxes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CrystalAxes::AAxis => write!(f, "AAxis"),
+            CrystalAxes::BAxis => write!(f, "BAxis"),
+            CrystalAxes::CAxis => write!(f, "CAxis"),
+            _ => write!(f, "{}", self.label()),
+        }
+    }
 }
@@ -19,19 +19,60 @@
 */
 
 use ndarray::Array;
+use ndarray::Array2;
 use ndarray::Axis;
 use ndarray::Dim;
 use ndarray::Zip;
 
+/// A two dimensional array of `f64` values, as used for the grids in the [`Lambert`] structure.
+type Grid = Array<f64, Dim<[usize; 2]>>;
+
 /// A structure to hold the Lambert computation data.
+///
+/// The grid only depends on the number of sphere points and the hemisphere, so it can be created once and
+/// shared (immutably) between all the particles and times which are processed.
 #[derive(Debug)]
 pub struct Lambert {
-    pub x_plane: Array<f64, Dim<[usize; 2]>>,
-    pub z_plane: Array<f64, Dim<[usize; 2]>>,
+    pub x_plane: Grid,
+    pub z_plane: Grid,
     pub r_plane: f64,
-    pub x: Array<f64, Dim<[usize; 2]>>,
-    pub y: Array<f64, Dim<[usize; 2]>>,
-    pub z: Array<f64, Dim<[usize; 2]>>,
+    pub x: Grid,
+    pub y: Grid,
+    pub z: Grid,
+    /// The number of grid points in each direction.
+    pub sphere_points: usize,
+    /// The unit vectors of the grid points on the sphere, flattened into an array of size
+    /// `3 x (sphere_points * sphere_points)`. Point `[i, j]` of the grid is stored in column
+    /// `i * sphere_points + j`, so the dot product with a `n_grains x 3` array of axes gives all the
+    /// cosines between the grains and the grid points at once.
+    pub sphere_point_grid: Array2<f64>,
+    /// Whether the upper (positive Y) or lower hemisphere is projected.
+    pub upper_hemisphere: bool,
+}
+
+impl Lambert {
+    /// Return the position in the plane of the pole figure of an axis, given as a vector in the sample frame.
+    /// The axis is reversed if it points into the hemisphere which is not projected. This is the mapping from
+    /// x,y,z to X,Y mentioned at [`create_lambert_equal_area_gridpoint`], as used for markers and scatter plots.
+    pub fn project_axis(&self, vector: &[f64; 3]) -> (f64, f64) {
+        let length = (vector[0] * vector[0] + vector[1] * vector[1] + vector[2] * vector[2]).sqrt();
+        let sign = if (vector[1] < 0.) == self.upper_hemisphere {
+            -1.
+        } else {
+            1.
+        };
+        let [x, y, z] = [
+            sign * vector[0] / length,
+            sign * vector[1] / length,
+            sign * vector[2] / length,
+        ];
+        let scale = (2. / (1. + y.abs())).sqrt();
+        if self.upper_hemisphere {
+            (x * scale, z * scale)
+        } else {
+            (x * scale, -z * scale)
+        }
+    }
 }
 
 /// Create a grid of evenly spaced points for contouring pole figure
@@ -66,7 +107,7 @@ pub fn create_lambert_equal_area_gridpoint(
         .and(&x_plane)
         .and(&z_plane)
         .par_apply(|a, &x_plane, &z_plane| {
-            *a = if 1. - (x_plane * x_plane + z_plane * z_plane) / 4. > std::f64::EPSILON {
+            *a = if 1. - (x_plane * x_plane + z_plane * z_plane) / 4. > f64::EPSILON {
                 ((1. - (x_plane * x_plane + z_plane * z_plane) / 4.).abs()).sqrt() * x_plane
             } else {
                 0.
@@ -112,17 +153,34 @@ pub fn create_lambert_equal_area_gridpoint(
             *a = (x * x + y * y + z * z).sqrt();
         });
     let mag = mag;
-    x = x / &mag;
-    y = y / &mag;
-    z = z / &mag;
+    x /= &mag;
+    y /= &mag;
+    z /= &mag;
+
+    let sphere_point_grid = create_sphere_point_grid(&x, &y, &z, sphere_points);
 
     Ok(Lambert {
-        x_plane: x_plane,
-        z_plane: z_plane,
-        r_plane: r_plane,
-        x: x,
-        y: y,
-        z: z,
+        x_plane,
+        z_plane,
+        r_plane,
+        x,
+        y,
+        z,
+        sphere_points,
+        sphere_point_grid,
+        upper_hemisphere: hemisphere == "upper",
+    })
+}
+
+/// Flatten the x, y and z grids of the unit sphere into one `3 x (sphere_points * sphere_points)` array.
+fn create_sphere_point_grid(x: &Grid, y: &Grid, z: &Grid, sphere_points: usize) -> Array2<f64> {
+    Array2::from_shape_fn((3, sphere_points * sphere_points), |(component, index)| {
+        let (i, j) = (index / sphere_points, index % sphere_points);
+        match component {
+            0 => x[[i, j]],
+            1 => y[[i, j]],
+            _ => z[[i, j]],
+        }
     })
 }
 
@@ -130,10 +188,9 @@ pub fn create_lambert_equal_area_gridpoint(
 fn create_meshgrid(
     x_plane: &Array<f64, Dim<[usize; 1]>>,
     y_plane: &Array<f64, Dim<[usize; 1]>>,
-) -> Result<(Array<f64, Dim<[usize; 2]>>, Array<f64, Dim<[usize; 2]>>), Box<dyn std::error::Error>>
-{
-    let mut new_x: Array<f64, Dim<[usize; 2]>> = Array::zeros([x_plane.len(), y_plane.len()]);
-    let mut new_y: Array<f64, Dim<[usize; 2]>> = Array::zeros([x_plane.len(), y_plane.len()]);
+) -> Result<(Grid, Grid), Box<dyn std::error::Error>> {
+    let mut new_x: Grid = Array::zeros([x_plane.len(), y_plane.len()]);
+    let mut new_y: Grid = Array::zeros([x_plane.len(), y_plane.len()]);
     let mut counter = 0;
     let max_count = x_plane.len();
     for value in new_x.iter_mut() {
@@ -157,3 +214,59 @@ fn create_meshgrid(
 
     Ok((new_x, new_y))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sphere_point_grid() {
+        let sphere_points = 11;
+        let lambert =
+            create_lambert_equal_area_gridpoint(sphere_points, "upper".to_string()).unwrap();
+
+        assert_eq!(lambert.sphere_points, sphere_points);
+        assert_eq!(
+            lambert.sphere_point_grid.shape(),
+            &[3, sphere_points * sphere_points]
+        );
+        for i in 0..sphere_points {
+            for j in 0..sphere_points {
+                let column = i * sphere_points + j;
+                assert_eq!(lambert.sphere_point_grid[[0, column]], lambert.x[[i, j]]);
+                assert_eq!(lambert.sphere_point_grid[[1, column]], lambert.y[[i, j]]);
+                assert_eq!(lambert.sphere_point_grid[[2, column]], lambert.z[[i, j]]);
+
+                let length = lambert
+                    .sphere_point_grid
+                    .column(column)
+                    .dot(&lambert.sphere_point_grid.column(column));
+                assert!((length - 1.0).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_project_axis() {
+        for hemisphere in &["upper", "lower"] {
+            let lambert = create_lambert_equal_area_gridpoint(21, hemisphere.to_string()).unwrap();
+            for i in 0..21 {
+                for j in 0..21 {
+                    let (x_plane, z_plane) = (lambert.x_plane[[i, j]], lambert.z_plane[[i, j]]);
+                    if x_plane * x_plane + z_plane * z_plane > 1.9 {
+                        continue;
+                    }
+                    let vector = [lambert.x[[i, j]], lambert.y[[i, j]], lambert.z[[i, j]]];
+                    for sign in &[1., -1.] {
+                        let (x, z) = lambert.project_axis(&[
+                            sign * vector[0],
+                            sign * vector[1],
+                            sign * vector[2],
+                        ]);
+                        assert!((x - x_plane).abs() < 1e-10 && (z - z_plane).abs() < 1e-10);
+                    }
+                }
+            }
+        }
+    }
+}
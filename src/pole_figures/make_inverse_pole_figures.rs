@@ -0,0 +1,288 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use palette::gradient::Gradient;
+use palette::rgb::LinSrgb;
+use plotters::prelude::*;
+
+use crate::configuration::pole_figure_configuration::ColorScaling;
+use crate::pole_figures::make_pole_figures::color_scale_fraction;
+use crate::pole_figures::{
+    inverse_pole_figure::InversePoleFigure, lambert::Lambert, minerals::Mineral,
+    percentage::Percentage,
+};
+use crate::texture::symmetry::CrystalSymmetry;
+
+use std::path::Path;
+use std::time::Instant;
+
+/// Produce the inverse pole figures. The counts are computed on the Lambert grid with the [001] axis in the
+/// center, the [100] axis to the right and the [010] axis to the top, and only the fundamental sector of the
+/// crystal symmetry of each mineral is drawn. The colors cover the range from zero to the maximum count of every
+/// inverse pole figure with the `color_scaling` of the pole figures.
+#[allow(clippy::too_many_arguments)]
+pub fn make_inverse_pole_figures(
+    small_figure: bool,
+    no_description_text: bool,
+    n_grains: usize,
+    particle_id: u64,
+    inverse_pole_figure_grid: &[Vec<InversePoleFigure>],
+    lambert: &Lambert,
+    output_file: &Path,
+    time: f64,
+    color_scaling: &ColorScaling,
+    color_gradient: &Gradient<LinSrgb>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let clock = Instant::now();
+
+    let figure_height = if small_figure { 500 } else { 800 };
+    let legend_width = if small_figure { 150 } else { 200 };
+    let number_of_figures_horizontal: usize = inverse_pole_figure_grid.len();
+    if number_of_figures_horizontal < 1 {
+        println!("No figures to make. Exit.");
+        return Ok(());
+    }
+
+    let number_of_figures_vertical: usize = inverse_pole_figure_grid[0].len();
+    if number_of_figures_vertical < 1 {
+        println!("No figures to make. Exit.");
+        return Ok(());
+    }
+
+    let hp = Percentage {
+        total: figure_height as f64,
+    };
+    let font_size_header = if small_figure { 28 } else { 45 };
+    let font_size_figure = if small_figure { 30 } else { 50 };
+    let line_distance = 5.5;
+    let top_margin = 0.25;
+    let left_margin = 0.5;
+    let font_type = "helvetica";
+
+    let header_height = hp.calc(top_margin + line_distance) as u32 + font_size_header;
+    let total_figure_width: u32 = number_of_figures_horizontal as u32 * figure_height + 10;
+    let total_figure_height: u32 =
+        number_of_figures_vertical as u32 * figure_height + header_height;
+
+    let path_string = output_file.to_string_lossy().into_owned();
+
+    let root = BitMapBackend::new(
+        &path_string,
+        (total_figure_width + legend_width, total_figure_height),
+    )
+    .into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let (header, body) = root.split_vertically(header_height);
+
+    let wp = Percentage {
+        total: total_figure_width as f64 / number_of_figures_horizontal as f64,
+    };
+
+    header.draw(&Text::new(
+        format!(
+            "inverse pole figures: id={}, time={:.5e}, grains={}",
+            particle_id, time, n_grains
+        ),
+        (wp.calc(left_margin) as i32, hp.calc(top_margin) as i32),
+        (font_type, font_size_header).into_font(),
+    ))?;
+
+    let (left, right) = body.split_horizontally(total_figure_width);
+    let drawing_areas_horizontal = left.split_evenly((1, number_of_figures_horizontal));
+
+    let r = lambert.r_plane;
+    let npts = lambert.sphere_points;
+
+    for (horizontal_figure_number, drawing_area_horizontal) in
+        drawing_areas_horizontal.iter().enumerate()
+    {
+        let drawing_areas_vertical =
+            drawing_area_horizontal.split_evenly((number_of_figures_vertical, 1));
+        let right_areas = right.split_evenly((number_of_figures_vertical, 1));
+
+        for (vertical_figure_number, drawing_area) in drawing_areas_vertical.iter().enumerate() {
+            let inverse_pole_figure =
+                &inverse_pole_figure_grid[horizontal_figure_number][vertical_figure_number];
+            let max_count_value = inverse_pole_figure.max_count;
+            let symmetry = inverse_pole_figure.mineral.symmetry();
+
+            if horizontal_figure_number == 0 {
+                let mut chart = ChartBuilder::on(&right_areas[vertical_figure_number])
+                    .margin(25)
+                    .margin_right(2)
+                    .margin_left(10)
+                    .top_x_label_area_size(0)
+                    .y_label_area_size(100)
+                    .caption(
+                        format!("{:.2}", max_count_value),
+                        ("helvetica", font_size_figure),
+                    )
+                    .build_cartesian_2d(0.0..1.0, 0.0..max_count_value)?;
+
+                chart
+                    .configure_mesh()
+                    .x_labels(0)
+                    .y_labels(10)
+                    .y_label_offset(15)
+                    .disable_x_mesh()
+                    .disable_y_mesh()
+                    .label_style(("helvetica", font_size_figure))
+                    .draw()?;
+
+                let legend_size = 150;
+                for i in 0..legend_size {
+                    let value = i as f64 * max_count_value / legend_size as f64;
+                    let next_value = (i + 1) as f64 * max_count_value / legend_size as f64;
+                    let picked_color = color_gradient.get(color_scale_fraction(
+                        value,
+                        0.0,
+                        max_count_value,
+                        color_scaling,
+                    ) as f32);
+                    chart.draw_series(std::iter::once(Rectangle::new(
+                        [(0.0, value), (1.0, next_value)],
+                        RGBColor(
+                            (picked_color.red * 255.0) as u8,
+                            (picked_color.green * 255.0) as u8,
+                            (picked_color.blue * 255.0) as u8,
+                        )
+                        .filled(),
+                    )))?;
+                }
+            }
+
+            // Only the fundamental sector is drawn: the quarter of the circle with positive [100] and [010]
+            // components for orthorhombic minerals and the whole circle for triclinic minerals.
+            let (minimum, boundary_path): (f64, Vec<(f64, f64)>) = match symmetry {
+                CrystalSymmetry::Orthorhombic => {
+                    let mut path = vec![(0., 0.)];
+                    path.extend((0..=50).map(|i| {
+                        let angle = i as f64 / 50. * std::f64::consts::FRAC_PI_2;
+                        (r * angle.cos(), r * angle.sin())
+                    }));
+                    path.push((0., 0.));
+                    (0., path)
+                }
+                CrystalSymmetry::Triclinic => (
+                    -r,
+                    (0..=100)
+                        .map(|i| {
+                            let angle = i as f64 / 100. * 2. * std::f64::consts::PI;
+                            (r * angle.cos(), r * angle.sin())
+                        })
+                        .collect(),
+                ),
+            };
+            let in_sector = |x: f64, z: f64| {
+                (x * x + z * z).sqrt() < r + 0.001 && x >= minimum - 1e-9 && z >= minimum - 1e-9
+            };
+
+            let mut chart = ChartBuilder::on(drawing_area).build_cartesian_2d(
+                minimum - 0.1 * r..r + 0.15 * r,
+                minimum - 0.1 * r..r + 0.15 * r,
+            )?;
+
+            let counts = &inverse_pole_figure.counts;
+            for i in 0..npts - 1 {
+                for j in 0..npts - 1 {
+                    let corners = [
+                        (lambert.x_plane[[i + 1, j]], lambert.z_plane[[i + 1, j]]),
+                        (
+                            lambert.x_plane[[i + 1, j + 1]],
+                            lambert.z_plane[[i + 1, j + 1]],
+                        ),
+                        (lambert.x_plane[[i, j + 1]], lambert.z_plane[[i, j + 1]]),
+                        (lambert.x_plane[[i, j]], lambert.z_plane[[i, j]]),
+                    ];
+                    if !corners.iter().all(|(x, z)| in_sector(*x, *z)) {
+                        continue;
+                    }
+                    let picked_color = color_gradient.get(color_scale_fraction(
+                        counts[[i, j]],
+                        0.0,
+                        max_count_value,
+                        color_scaling,
+                    ) as f32);
+                    chart.draw_series(std::iter::once(Polygon::new(
+                        corners.to_vec(),
+                        RGBColor(
+                            (picked_color.red * 255.0) as u8,
+                            (picked_color.green * 255.0) as u8,
+                            (picked_color.blue * 255.0) as u8,
+                        )
+                        .filled(),
+                    )))?;
+                }
+            }
+            chart.draw_series(std::iter::once(PathElement::new(
+                boundary_path,
+                Into::<ShapeStyle>::into(&BLACK).stroke_width(5),
+            )))?;
+
+            let label_font = (font_type, font_size_figure).into_font();
+            chart.draw_series(
+                [
+                    ("[001]", (minimum - 0.08 * r, minimum - 0.02 * r)),
+                    ("[100]", (r - 0.1 * r, minimum - 0.02 * r)),
+                    ("[010]", (minimum + 0.02 * r, r + 0.12 * r)),
+                ]
+                .iter()
+                .map(|(label, position)| {
+                    Text::new(label.to_string(), *position, label_font.clone())
+                }),
+            )?;
+
+            if !no_description_text {
+                let mineral_string = match inverse_pole_figure.mineral {
+                    Mineral::Olivine => "olivine",
+                    Mineral::Enstatite => "enstatite",
+                };
+                drawing_area.draw(&Text::new(
+                    mineral_string.to_string(),
+                    (
+                        wp.calc(60.) as i32,
+                        hp.calc(top_margin + 0.0 * line_distance) as i32,
+                    ),
+                    (font_type, font_size_figure, FontStyle::Bold).into_font(),
+                ))?;
+                drawing_area.draw(&Text::new(
+                    inverse_pole_figure.sample_direction.label(),
+                    (
+                        wp.calc(60.) as i32,
+                        hp.calc(top_margin + 1.0 * line_distance) as i32,
+                    ),
+                    (font_type, font_size_figure, FontStyle::Bold).into_font(),
+                ))?;
+            }
+        }
+    }
+
+    // The figure is written here instead of when the drawing area is dropped, where an error is ignored.
+    root.present()?;
+
+    println!(
+        "    Made set of inverse pole figures for figure {}. Elapsed time: {:.2?}",
+        path_string,
+        clock.elapsed()
+    );
+
+    Ok(())
+}
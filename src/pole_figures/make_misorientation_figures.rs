@@ -0,0 +1,140 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use plotters::prelude::*;
+
+use crate::pole_figures::minerals::Mineral;
+use crate::texture::misorientation::MisorientationDistribution;
+
+use std::path::Path;
+
+/// Produce a figure with a histogram of the uncorrelated misorientation angles for each mineral, together with
+/// the theoretical distribution for randomly oriented grains and the M-index.
+pub fn make_misorientation_figures(
+    small_figure: bool,
+    particle_id: u64,
+    time: f64,
+    distributions: &[(Mineral, MisorientationDistribution)],
+    output_file: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if distributions.is_empty() {
+        println!("No figures to make. Exit.");
+        return Ok(());
+    }
+
+    let figure_height: u32 = if small_figure { 500 } else { 800 };
+    let figure_width: u32 = figure_height * 3 / 2;
+    let font_size = if small_figure { 24 } else { 36 };
+    let font_type = "helvetica";
+
+    let path_string = output_file.to_string_lossy().into_owned();
+    let root = BitMapBackend::new(
+        &path_string,
+        (figure_width * distributions.len() as u32, figure_height),
+    )
+    .into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let drawing_areas = root.split_evenly((1, distributions.len()));
+    for (drawing_area, (mineral, distribution)) in drawing_areas.iter().zip(distributions) {
+        // the distributions are shown in percent per bin.
+        let max_value = distribution
+            .observed
+            .iter()
+            .chain(distribution.random.iter())
+            .cloned()
+            .fold(0.0, f64::max)
+            * 100.
+            * 1.1;
+
+        let mut chart = ChartBuilder::on(drawing_area)
+            .caption(
+                format!(
+                    "{}, id={}, time={:.5e}, pairs={}, M-index={:.3}",
+                    mineral.to_string().to_lowercase(),
+                    particle_id,
+                    time,
+                    distribution.n_pairs,
+                    distribution.m_index()
+                ),
+                (font_type, font_size),
+            )
+            .margin(20)
+            .x_label_area_size(font_size * 2)
+            .y_label_area_size(font_size * 3)
+            .build_cartesian_2d(0.0..distribution.max_angle, 0.0..max_value.max(1e-3))?;
+
+        chart
+            .configure_mesh()
+            .disable_mesh()
+            .x_desc("misorientation angle (degree)")
+            .y_desc("frequency (%)")
+            .label_style((font_type, font_size * 3 / 4))
+            .axis_desc_style((font_type, font_size))
+            .draw()?;
+
+        chart
+            .draw_series(
+                distribution
+                    .observed
+                    .iter()
+                    .enumerate()
+                    .map(|(bin, value)| {
+                        Rectangle::new(
+                            [
+                                (bin as f64 * distribution.bin_width, 0.),
+                                ((bin + 1) as f64 * distribution.bin_width, value * 100.),
+                            ],
+                            RGBColor(120, 150, 200).filled(),
+                        )
+                    }),
+            )?
+            .label("uncorrelated")
+            .legend(|(x, y)| {
+                Rectangle::new(
+                    [(x, y - 5), (x + 20, y + 5)],
+                    RGBColor(120, 150, 200).filled(),
+                )
+            });
+
+        chart
+            .draw_series(LineSeries::new(
+                distribution.random.iter().enumerate().map(|(bin, value)| {
+                    ((bin as f64 + 0.5) * distribution.bin_width, value * 100.)
+                }),
+                Into::<ShapeStyle>::into(&BLACK).stroke_width(3),
+            ))?
+            .label("random")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLACK.stroke_width(3)));
+
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .label_font((font_type, font_size * 3 / 4))
+            .draw()?;
+    }
+
+    // The figure is written here instead of when the drawing area is dropped, where an error is ignored.
+    root.present()?;
+
+    Ok(())
+}
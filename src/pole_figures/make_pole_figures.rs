@@ -18,38 +18,515 @@
   <http://www.gnu.org/licenses/>.
 */
 
-use ndarray::Array;
 use ndarray::Zip;
+use ndarray::{Array, Array2};
+use palette::gradient::Gradient;
+use palette::rgb::LinSrgb;
+use plotters::coord::Shift;
 use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
 
-use crate::color_gradients::*;
+use crate::configuration::layout_configuration::LayoutConfiguration;
 use crate::configuration::particle_record::ParticleRecord;
+use crate::configuration::pole_figure_configuration::{
+    ColorBarOrientation, ColorScaleRange, ColorScaling, OutputFormat, PoleFiguresConfiguration,
+};
+use crate::elasticity::decomposition::HexagonalSymmetryAxis;
+use crate::elasticity::seismic_anisotropy::SeismicAnisotropy;
 use crate::pole_figures::{
-    crystal_axis::CrystalAxes, lambert::Lambert, minerals::Mineral, percentage::Percentage,
+    contours::{clip_to_circle, contour_segments},
+    lambert::Lambert,
+    minerals::Mineral,
+    percentage::Percentage,
     pole_figure::PoleFigure,
 };
+use crate::texture::fabric_strength::FabricStrength;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
-/// The main function responsible for actually producing the the pole figures.
+/// Return the lower end of a logarithmic color scale from `min` to `max`. A logarithmic scale can not start at zero,
+/// so if `min` is not positive the scale starts at a hundredth of `max`.
+fn log_scale_min(min: f64, max: f64) -> f64 {
+    if min > 0.0 {
+        min
+    } else {
+        max * 1e-2
+    }
+}
+
+/// Return the position on the color scale, between zero and one, of a value for a color scale from `min` to `max`
+/// with the given scaling. Values outside of the range are clipped to the range.
+pub(crate) fn color_scale_fraction(value: f64, min: f64, max: f64, scaling: &ColorScaling) -> f64 {
+    if max <= min {
+        return 0.0;
+    }
+    let linear_fraction = ((value - min) / (max - min)).clamp(0.0, 1.0);
+    match scaling {
+        ColorScaling::Linear => linear_fraction,
+        ColorScaling::Power { exponent } => linear_fraction.powf(*exponent),
+        ColorScaling::Log => {
+            let lower = log_scale_min(min, max);
+            if max <= lower || value <= 0.0 {
+                0.0
+            } else {
+                ((value / lower).ln() / (max / lower).ln()).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+/// Return the values at which to put the ticks of a color bar from `min` to `max`. For a linear or power law scale
+/// these are around five round numbers with steps of 1, 2 or 5 times a power of ten, for a logarithmic scale these
+/// are 1, 2 and 5 times the powers of ten in the range.
+fn color_bar_ticks(min: f64, max: f64, scaling: &ColorScaling) -> Vec<f64> {
+    if max <= min {
+        return vec![min];
+    }
+    if let ColorScaling::Log = scaling {
+        let lower = log_scale_min(min, max);
+        let tolerance = 1e-9 * (max - lower);
+        let ticks: Vec<f64> = (lower.log10().floor() as i32..=max.log10().ceil() as i32)
+            .flat_map(|exponent| {
+                [1.0, 2.0, 5.0]
+                    .iter()
+                    .map(move |mantissa| mantissa * 10_f64.powi(exponent))
+            })
+            .filter(|tick| *tick >= lower - tolerance && *tick <= max + tolerance)
+            .collect();
+        // a range within a single decade has too few of these ticks
+        if ticks.len() >= 2 {
+            return ticks;
+        }
+        return color_bar_ticks(lower, max, &ColorScaling::Linear);
+    }
+
+    let magnitude = 10_f64.powf(((max - min) / 5.0).log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| (max - min) / step <= 6.0)
+        .unwrap_or(10.0 * magnitude);
+    let tolerance = 1e-9 * step;
+    let first = (min / step - 1e-9).ceil() as i64;
+    let last = (max / step + 1e-9).floor() as i64;
+    (first..=last)
+        .map(|i| i as f64 * step)
+        .filter(|tick| *tick >= min - tolerance && *tick <= max + tolerance)
+        .collect()
+}
+
+/// Return the label of a tick of a color bar, with at most three decimals and without trailing zeros.
+fn color_bar_tick_label(value: f64) -> String {
+    let label = format!("{:.3}", value);
+    let label = label.trim_end_matches('0').trim_end_matches('.');
+    if label == "-0" {
+        "0".to_string()
+    } else {
+        label.to_string()
+    }
+}
+
+/// Return the color of a position on the color scale, between zero and one.
+fn gradient_color(color_gradient: &Gradient<LinSrgb>, fraction: f64) -> RGBColor {
+    let picked_color = color_gradient.get(fraction as f32);
+    RGBColor(
+        (picked_color.red * 255.0) as u8,
+        (picked_color.green * 255.0) as u8,
+        (picked_color.blue * 255.0) as u8,
+    )
+}
+
+/// The range and the look of a color bar.
+struct ColorBar<'a> {
+    /// The value at the start of the color scale.
+    min: f64,
+    /// The value at the end of the color scale.
+    max: f64,
+    scaling: &'a ColorScaling,
+    title: String,
+    /// Whether there are values above the range, which is shown by an arrow beyond the end of the color bar.
+    clipped_above: bool,
+    /// Whether there are values below the range, which is shown by an arrow beyond the start of the color bar.
+    clipped_below: bool,
+    horizontal: bool,
+    /// The number of evenly spaced colors the bar is drawn with.
+    steps: u32,
+}
+
+/// Draw a color bar on `area`. The colors are drawn evenly along the bar and the ticks are placed at the position
+/// of their value on the color scale, so a logarithmic or power law scale has unevenly spaced ticks.
+fn draw_color_bar<DB>(
+    area: &DrawingArea<DB, Shift>,
+    color_bar: &ColorBar,
+    color_gradient: &Gradient<LinSrgb>,
+    font_type: &str,
+    font_size: u32,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let arrow_length = 0.08;
+    let start = if color_bar.clipped_below {
+        -arrow_length
+    } else {
+        0.0
+    };
+    let end = 1.0
+        + if color_bar.clipped_above {
+            arrow_length
+        } else {
+            0.0
+        };
+    let label_style = TextStyle::from((font_type, font_size).into_font());
+    let tick_length = font_size as i32 / 4;
+    let ticks = color_bar_ticks(color_bar.min, color_bar.max, color_bar.scaling);
+    let tick_fractions = ticks
+        .iter()
+        .map(|tick| color_scale_fraction(*tick, color_bar.min, color_bar.max, color_bar.scaling));
+
+    // the bar is drawn in the coordinates of the position on the color scale along the bar and zero to one across
+    // the bar.
+    let legend_size = color_bar.steps;
+    let along = |fraction: f64, across: f64| {
+        if color_bar.horizontal {
+            (fraction, across)
+        } else {
+            (across, fraction)
+        }
+    };
+    let mut builder = ChartBuilder::on(area);
+    builder.caption(&color_bar.title, (font_type, font_size).into_font());
+    let mut chart = if color_bar.horizontal {
+        let (width, _) = area.dim_in_pixel();
+        builder
+            .margin(10)
+            .margin_left(width / 6)
+            .margin_right(width / 6)
+            .margin_bottom(font_size + 3 * tick_length as u32)
+            .build_cartesian_2d(start..end, 0.0..1.0)?
+    } else {
+        builder
+            .margin(25)
+            .margin_right(2)
+            .margin_left(font_size * 2 + 2 * tick_length as u32)
+            .build_cartesian_2d(0.0..1.0, start..end)?
+    };
+
+    chart.draw_series((0..legend_size).map(|i| {
+        let fraction = i as f64 / legend_size as f64;
+        Rectangle::new(
+            [
+                along(fraction, 0.0),
+                along((i + 1) as f64 / legend_size as f64, 1.0),
+            ],
+            gradient_color(color_gradient, fraction).filled(),
+        )
+    }))?;
+    if color_bar.clipped_above {
+        chart.draw_series(std::iter::once(Polygon::new(
+            vec![
+                along(1.0, 0.0),
+                along(1.0, 1.0),
+                along(1.0 + arrow_length, 0.5),
+            ],
+            gradient_color(color_gradient, 1.0).filled(),
+        )))?;
+    }
+    if color_bar.clipped_below {
+        chart.draw_series(std::iter::once(Polygon::new(
+            vec![along(0.0, 0.0), along(0.0, 1.0), along(-arrow_length, 0.5)],
+            gradient_color(color_gradient, 0.0).filled(),
+        )))?;
+    }
+    chart.draw_series(std::iter::once(Rectangle::new(
+        [along(0.0, 0.0), along(1.0, 1.0)],
+        Into::<ShapeStyle>::into(&BLACK).stroke_width(1),
+    )))?;
+
+    for (tick, fraction) in ticks.iter().zip(tick_fractions) {
+        let label = color_bar_tick_label(*tick);
+        if color_bar.horizontal {
+            chart.draw_series(std::iter::once(
+                EmptyElement::at((fraction, 0.0))
+                    + PathElement::new(vec![(0, 0), (0, tick_length)], BLACK)
+                    + Text::new(
+                        label,
+                        (0, 2 * tick_length),
+                        label_style.pos(Pos::new(HPos::Center, VPos::Top)),
+                    ),
+            ))?;
+        } else {
+            chart.draw_series(std::iter::once(
+                EmptyElement::at((0.0, fraction))
+                    + PathElement::new(vec![(0, 0), (-tick_length, 0)], BLACK)
+                    + Text::new(
+                        label,
+                        (-2 * tick_length, 0),
+                        label_style.pos(Pos::new(HPos::Right, VPos::Center)),
+                    ),
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+/// The style of the points in a scatter plot of the grain poles.
+pub struct ScatterStyle {
+    /// The radius of the points in pixels.
+    pub point_size: f64,
+    /// Whether the area of a point is proportional to the volume fraction of the grain, relative to the average.
+    pub size_by_volume_fraction: bool,
+    /// Whether the points are colored by mineral instead of black.
+    pub color_by_mineral: bool,
+}
+
+/// How the pole figures are drawn, which is read from the pole figure configuration.
+pub struct PoleFigureStyle<'a> {
+    pub small_figure: bool,
+    pub layout: &'a LayoutConfiguration,
+    pub no_description_text: bool,
+    pub elastisity_header: bool,
+    /// Whether the fabric strength indices are written in the header.
+    pub fabric_strength_header: bool,
+    /// Whether the seismic anisotropy is written in the header.
+    pub seismic_anisotropy_header: bool,
+    /// Whether the symmetry axis of the hexagonal part of the elastic tensor is marked on the pole figures.
+    pub hexagonal_axis_marker: bool,
+    pub smooth_density: bool,
+    pub contour_levels: &'a [f64],
+    pub contour_labels: bool,
+    pub contours_only: bool,
+    /// Whether every pole figure has its own color scale.
+    pub subplot_color_scale: bool,
+    pub scatter_style: ScatterStyle,
+    pub color_scaling: &'a ColorScaling,
+    pub color_bar_title: &'a str,
+    pub color_bar_orientation: &'a ColorBarOrientation,
+    pub color_gradient: &'a Gradient<LinSrgb>,
+}
+
+impl<'a> PoleFigureStyle<'a> {
+    /// Create the style of the pole figures of a pole figure configuration, drawn with the given color gradient.
+    pub fn new(
+        configuration: &'a PoleFiguresConfiguration,
+        color_gradient: &'a Gradient<LinSrgb>,
+    ) -> PoleFigureStyle<'a> {
+        PoleFigureStyle {
+            small_figure: configuration.small_figure,
+            layout: &configuration.layout,
+            no_description_text: configuration.no_description_text,
+            elastisity_header: configuration.elastisity_header,
+            fabric_strength_header: configuration.fabric_strength_header,
+            seismic_anisotropy_header: configuration.seismic_anisotropy_header,
+            hexagonal_axis_marker: configuration.hexagonal_axis_marker,
+            smooth_density: configuration.smooth_density,
+            contour_levels: &configuration.contour_levels,
+            contour_labels: configuration.contour_labels,
+            contours_only: configuration.contours_only,
+            subplot_color_scale: configuration.color_scale_range == ColorScaleRange::Subplot,
+            scatter_style: ScatterStyle {
+                point_size: configuration.scatter_point_size,
+                size_by_volume_fraction: configuration.scatter_size_by_volume_fraction,
+                color_by_mineral: configuration.scatter_color_by_mineral,
+            },
+            color_scaling: &configuration.color_scaling,
+            color_bar_title: &configuration.color_bar_title,
+            color_bar_orientation: &configuration.color_bar_orientation,
+            color_gradient,
+        }
+    }
+}
+
+/// The pole figures of a particle at a time, together with the particle information written in the header.
+pub struct ParticlePoleFigures<'a> {
+    pub particle_id: u64,
+    pub time: f64,
+    pub n_grains: usize,
+    pub particle_record: &'a ParticleRecord,
+    pub pole_figure_grid: &'a [Vec<PoleFigure>],
+    /// The labels of a comparison figure, which replace the particle information and the mineral and axis names.
+    pub labels: Option<&'a PoleFigureLabels>,
+    pub fabric_strength: &'a [FabricStrength],
+    pub hexagonal_axis: Option<&'a HexagonalSymmetryAxis>,
+    pub seismic_anisotropy: Option<&'a SeismicAnisotropy>,
+}
+
+/// Where the pole figures are drawn to.
+pub enum PoleFigureOutput<'a> {
+    /// A file in the given format.
+    File {
+        path: &'a Path,
+        format: &'a OutputFormat,
+    },
+    /// The next frame of an animation, with the particle id and the time in the header.
+    AnimationFrame(&'a mut PoleFigureAnimation),
+    /// A PNG file which is a frame of an animation, with the particle id and the time in the header.
+    PngFrame(&'a Path),
+}
+
+/// The text of a figure which compares pole figures of different particles, times or experiments. It replaces the
+/// particle information in the header and the mineral and axis written on every pole figure.
+pub struct PoleFigureLabels {
+    /// The line at the top of the figure, with what all the pole figures have in common.
+    pub title: String,
+    /// The two lines written on every pole figure, in the layout of the pole figure grid. The first line also names
+    /// the horizontal color bar of the row.
+    pub pole_figures: Vec<Vec<[String; 2]>>,
+}
+
+/// An animated GIF of pole figures. The GIF is created with the size of the first frame, so all the frames need to
+/// have the same layout.
+pub struct PoleFigureAnimation {
+    path: PathBuf,
+    /// The time every frame is shown, in milliseconds.
+    frame_delay: u32,
+    root: Option<DrawingArea<BitMapBackend<'static>, Shift>>,
+}
+
+impl PoleFigureAnimation {
+    /// Create an animation which is written to `path` when the first frame is drawn.
+    pub fn new(path: &Path, frame_delay: u32) -> PoleFigureAnimation {
+        PoleFigureAnimation {
+            path: path.to_path_buf(),
+            frame_delay,
+            root: None,
+        }
+    }
+
+    /// Return the drawing area for the next frame, which has to have the same size as the first frame.
+    fn frame_root(
+        &mut self,
+        size: (u32, u32),
+    ) -> Result<DrawingArea<BitMapBackend<'static>, Shift>, Box<dyn std::error::Error>> {
+        if self.root.is_none() {
+            self.root =
+                Some(BitMapBackend::gif(&self.path, size, self.frame_delay)?.into_drawing_area());
+        }
+        let root = self.root.as_ref().unwrap();
+        if root.dim_in_pixel() != size {
+            return Err(format!(
+                "the frame size {:?} differs from the size {:?} of the animation {}",
+                size,
+                root.dim_in_pixel(),
+                self.path.display()
+            )
+            .into());
+        }
+        Ok(root.clone())
+    }
+}
+
+/// The main function responsible for actually producing the the pole figures of a `figure` with the `style`. The
+/// figures are written as PNG or as SVG, or as a frame of an animation. The density field of every pole figure is
+/// drawn as a bitmap, so that the drawing time and the size of an SVG do not grow with the number of grid points,
+/// while the text and lines stay vector graphics. If `smooth_density` is set, the density is bilinearly
+/// interpolated between the grid points. Contour lines are drawn at the `contour_levels` in multiples of uniform
+/// density, optionally with labels, and if `contours_only` is set the density field and the color scale are left
+/// out. The poles of the grains are drawn as points with the `scatter_style` for the pole figures which contain
+/// them. The colors cover the range of the color scale of every pole figure with the `color_scaling`, and if
+/// `subplot_color_scale` is set this range differs per pole figure, so the color scale shows the fraction of the
+/// maximum and the maximum is written below every pole figure. Every row of pole figures gets a color bar with the
+/// `color_bar_title`, to the right of the row or, with a horizontal `color_bar_orientation`, below all the pole
+/// figures.
 pub fn make_pole_figures(
-    small_figure: bool,
-    no_description_text: bool,
-    elastisity_header: bool,
-    n_grains: usize,
-    particle_id: u64,
-    pole_figure_grid: &Vec<Vec<PoleFigure>>,
+    style: &PoleFigureStyle,
+    figure: &ParticlePoleFigures,
     lambert: &Lambert,
-    output_file: &Path,
-    particle_record: &ParticleRecord,
-    time: f64,
-    gam: f64,
-    color_gradient_selection: &ColorGradient,
+    output: PoleFigureOutput,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let clock = Instant::now();
+    let time_header = !matches!(output, PoleFigureOutput::File { .. });
+    match output {
+        PoleFigureOutput::File {
+            path,
+            format: OutputFormat::Svg,
+        } => {
+            let path_string = path.to_string_lossy().into_owned();
+            draw_pole_figures(
+                |size| Ok(SVGBackend::new(&path_string, size).into_drawing_area()),
+                time_header,
+                &path_string,
+                style,
+                figure,
+                lambert,
+            )
+        }
+        PoleFigureOutput::File { path, .. } | PoleFigureOutput::PngFrame(path) => {
+            let path_string = path.to_string_lossy().into_owned();
+            draw_pole_figures(
+                |size| Ok(BitMapBackend::new(&path_string, size).into_drawing_area()),
+                time_header,
+                &path_string,
+                style,
+                figure,
+                lambert,
+            )
+        }
+        PoleFigureOutput::AnimationFrame(animation) => {
+            let path_string = animation.path.to_string_lossy().into_owned();
+            draw_pole_figures(
+                |size| animation.frame_root(size),
+                time_header,
+                &path_string,
+                style,
+                figure,
+                lambert,
+            )
+        }
+    }
+}
 
-    let color_gradient = set_color_gradient(color_gradient_selection);
+/// Draw the pole figures on the drawing area created by `create_root` for the size of the figure. If `time_header`
+/// is set and there is no elasticity header, the particle id and the time are written on the first line of the
+/// header. If there are `labels`, their title is written there instead.
+fn draw_pole_figures<DB, F>(
+    create_root: F,
+    time_header: bool,
+    path_string: &str,
+    style: &PoleFigureStyle,
+    figure: &ParticlePoleFigures,
+    lambert: &Lambert,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+    F: FnOnce((u32, u32)) -> Result<DrawingArea<DB, Shift>, Box<dyn std::error::Error>>,
+{
+    let PoleFigureStyle {
+        small_figure,
+        layout,
+        no_description_text,
+        elastisity_header,
+        smooth_density,
+        contour_levels,
+        contour_labels,
+        contours_only,
+        subplot_color_scale,
+        ref scatter_style,
+        color_scaling,
+        color_bar_title,
+        color_bar_orientation,
+        color_gradient,
+        fabric_strength_header,
+        seismic_anisotropy_header,
+        hexagonal_axis_marker,
+    } = *style;
+    let ParticlePoleFigures {
+        particle_id,
+        time,
+        n_grains,
+        particle_record,
+        pole_figure_grid,
+        labels,
+        fabric_strength,
+        hexagonal_axis,
+        seismic_anisotropy,
+    } = *figure;
+    let fabric_strength = Some(fabric_strength).filter(|_| fabric_strength_header);
+    let seismic_anisotropy = seismic_anisotropy.filter(|_| seismic_anisotropy_header);
+    let hexagonal_axis = hexagonal_axis.filter(|_| hexagonal_axis_marker);
+    let clock = Instant::now();
 
     // Grid of points is a square and it extends outside the pole figure circumference.
     // Create mask to only plot color and contours within the pole figure
@@ -62,7 +539,7 @@ pub fn make_pole_figures(
         .par_apply(|a, x, z| {
             let radius = (x * x + z * z).sqrt();
             if radius >= lambert.r_plane + 0.001 {
-                *a = std::f64::NAN
+                *a = f64::NAN
             } else {
                 *a = 1.
             }
@@ -80,8 +557,18 @@ pub fn make_pole_figures(
         ));
     }
 
-    let figure_height = if small_figure { 500 } else { 800 };
-    let legend_width = if small_figure { 150 } else { 200 };
+    let figure_height = layout.panel_size(small_figure);
+    let horizontal_color_bar = *color_bar_orientation == ColorBarOrientation::Horizontal;
+    let legend_width = if horizontal_color_bar {
+        0
+    } else {
+        layout.color_bar_width(small_figure)
+    };
+    let legend_height = if horizontal_color_bar && !contours_only {
+        layout.color_bar_height(small_figure)
+    } else {
+        0
+    };
     let number_of_figures_horizontal: usize = pole_figure_grid.len();
     if number_of_figures_horizontal < 1 {
         println!("No figures to make. Exit.");
@@ -94,41 +581,66 @@ pub fn make_pole_figures(
         return Ok(());
     }
 
-    let total_figure_width: u32 = number_of_figures_horizontal as u32 * figure_height + 10;
-    let total_figure_height: u32 = if elastisity_header {
-        number_of_figures_vertical as u32 * figure_height + 100
+    let hp = Percentage {
+        total: figure_height as f64,
+    };
+    let font_size_header = layout.header_font_size(small_figure);
+    let line_distance = layout.line_distance;
+    let top_margin = layout.top_margin;
+    let left_margin = layout.left_margin;
+    let font_type = layout.font_family.as_str();
+
+    // The fabric strength and the seismic anisotropy are written on extra lines below the
+    // elasticity information, or from the first line if there is no elasticity information.
+    let time_line =
+        (time_header || labels.is_some() || layout.header_template.is_some()) && !elastisity_header;
+    let fabric_strength_line = if elastisity_header {
+        3.0
+    } else if time_line {
+        1.0
     } else {
-        number_of_figures_vertical as u32 * figure_height
+        0.0
+    };
+    let seismic_anisotropy_line =
+        fabric_strength_line + if fabric_strength.is_some() { 1.0 } else { 0.0 };
+    let extra_lines = [
+        time_line,
+        fabric_strength.is_some(),
+        seismic_anisotropy.is_some(),
+    ]
+    .iter()
+    .filter(|line| **line)
+    .count() as f64;
+    // the elasticity information takes three lines.
+    let header_lines = extra_lines + if elastisity_header { 3.0 } else { 0.0 };
+    let header_height: u32 = if header_lines == 0. {
+        0
+    } else {
+        hp.calc(top_margin + header_lines * line_distance) as u32 + font_size_header
     };
 
-    println!("    Before drawing: Elapsed time: {:.2?}", clock.elapsed());
-    let path_string = output_file.to_string_lossy().into_owned();
+    let total_figure_width: u32 =
+        number_of_figures_horizontal as u32 * figure_height + layout.scaled(10);
+    let total_figure_height: u32 =
+        number_of_figures_vertical as u32 * (figure_height + legend_height) + header_height;
 
-    let root = BitMapBackend::new(
-        &path_string,
-        (total_figure_width + legend_width, total_figure_height),
-    )
-    .into_drawing_area();
+    println!("    Before drawing: Elapsed time: {:.2?}", clock.elapsed());
+    let root = create_root((total_figure_width + legend_width, total_figure_height))?;
     root.fill(&WHITE)?;
 
     println!("    made root: Elapsed time: {:.2?}", clock.elapsed());
-    let (header, body) = if elastisity_header {
-        root.split_vertically(150)
-    } else {
-        root.split_vertically(0)
-    };
+    let (header, body) = root.split_vertically(header_height);
 
-    let hp = Percentage {
-        total: figure_height as f64,
-    };
     let wp = Percentage {
         total: total_figure_width as f64 / number_of_figures_horizontal as f64,
     };
-    let font_size_header = if small_figure { 28 } else { 45 };
-    let line_distance = 5.5;
-    let top_margin = 0.25;
-    let left_margin = 0.5;
-    let font_type = "helvetica";
+    // the five columns of the symmetry decomposition are spread evenly over the width of the figure by default.
+    let header_column_distance = match layout.header_column_distance {
+        Some(header_column_distance) => wp.calc(header_column_distance),
+        None => (total_figure_width + legend_width) as f64 / 5.0,
+    };
+    let header_column =
+        |column: f64| (wp.calc(left_margin) + column * header_column_distance) as i32;
 
     if elastisity_header {
         // Do stuff in header
@@ -167,11 +679,11 @@ pub fn make_pole_figures(
             pr.hexagonal_norm_square_p3.unwrap(),
         ];
 
-        let mut tric_sorted = tric_unsorted.clone();
-        let mut mono_sorted = mono_unsorted.clone();
-        let mut orth_sorted = orth_unsorted.clone();
-        let mut tetr_sorted = tetr_unsorted.clone();
-        let mut hexa_sorted = hexa_unsorted.clone();
+        let mut tric_sorted = tric_unsorted;
+        let mut mono_sorted = mono_unsorted;
+        let mut orth_sorted = orth_unsorted;
+        let mut tetr_sorted = tetr_unsorted;
+        let mut hexa_sorted = hexa_unsorted;
 
         let total_anisotropy =
             tric_sorted[0] + mono_sorted[0] + orth_sorted[0] + tetr_sorted[0] + hexa_sorted[0];
@@ -211,7 +723,9 @@ pub fn make_pole_figures(
 
         header
         .draw(&Text::new(
-            format!("id={}, time={:.5e}, position=({:.3e}:{:.3e}:{:.3e}), ODT={:.1}, grains={}, anisotropic%={:.4}",
+            match &layout.header_template {
+                Some(header_template) => header_text(header_template, particle_id, time, n_grains, particle_record, ""),
+                None => format!("id={}, time={:.5e}, position=({:.3e}:{:.3e}:{:.3e}), ODT={:.1}, grains={}, anisotropic%={:.4}",
             particle_id,
             time,
             particle_record.x,
@@ -219,6 +733,7 @@ pub fn make_pole_figures(
             particle_record.z.unwrap(),
             particle_record.olivine_deformation_type.unwrap(),
             n_grains,((total_anisotropy)/full_norm_square)*100.),
+            },
             ((wp.calc(left_margin) ) as i32, hp.calc(top_margin) as i32),
             (font_type, font_size_header).into_font(),
         ))?;
@@ -229,7 +744,7 @@ pub fn make_pole_figures(
                 hexa_perc_full[0], hexa_perc_full[1], hexa_perc_full[2]
             ),
             (
-                (left_margin) as i32,
+                header_column(0.0),
                 hp.calc(top_margin + 1.0 * line_distance) as i32,
             ),
             (font_type, font_size_header).into_font(),
@@ -243,7 +758,7 @@ pub fn make_pole_figures(
                 ((hexa_unsorted[2]) / (total_anisotropy)) * 100.
             ),
             (
-                (left_margin) as i32,
+                header_column(0.0),
                 hp.calc(top_margin + 2.0 * line_distance) as i32,
             ),
             (font_type, font_size_header).into_font(),
@@ -256,8 +771,7 @@ pub fn make_pole_figures(
                 tetr_perc_full[0], tetr_perc_full[1], tetr_perc_full[2]
             ),
             (
-                (left_margin + (total_figure_width as f64 + legend_width as f64 + 10.0) * 0.2)
-                    as i32,
+                header_column(1.0),
                 hp.calc(top_margin + 1.0 * line_distance) as i32,
             ),
             (font_type, font_size_header).into_font(),
@@ -270,8 +784,7 @@ pub fn make_pole_figures(
                 ((tetr_unsorted[2]) / (total_anisotropy)) * 100.
             ),
             (
-                (left_margin + (total_figure_width as f64 + legend_width as f64 + 10.0) * 0.2)
-                    as i32,
+                header_column(1.0),
                 hp.calc(top_margin + 2.0 * line_distance) as i32,
             ),
             (font_type, font_size_header).into_font(),
@@ -282,8 +795,7 @@ pub fn make_pole_figures(
                 orth_perc_full[0], orth_perc_full[1], orth_perc_full[2]
             ),
             (
-                (left_margin + (total_figure_width as f64 + legend_width as f64 + 10.0) * 0.4)
-                    as i32,
+                header_column(2.0),
                 hp.calc(top_margin + 1.0 * line_distance) as i32,
             ),
             (font_type, font_size_header).into_font(),
@@ -296,8 +808,7 @@ pub fn make_pole_figures(
                 ((orth_unsorted[2]) / (full_norm_square - isotropic)) * 100.
             ),
             (
-                (left_margin + (total_figure_width as f64 + legend_width as f64 + 10.0) * 0.4)
-                    as i32,
+                header_column(2.0),
                 hp.calc(top_margin + 2.0 * line_distance) as i32,
             ),
             (font_type, font_size_header).into_font(),
@@ -308,8 +819,7 @@ pub fn make_pole_figures(
                 mono_perc_full[0], mono_perc_full[1], mono_perc_full[2]
             ),
             (
-                (left_margin + (total_figure_width as f64 + legend_width as f64 + 10.0) * 0.6)
-                    as i32,
+                header_column(3.0),
                 hp.calc(top_margin + 1.0 * line_distance) as i32,
             ),
             (font_type, font_size_header).into_font(),
@@ -322,8 +832,7 @@ pub fn make_pole_figures(
                 ((mono_unsorted[2]) / (full_norm_square - isotropic)) * 100.
             ),
             (
-                (left_margin + (total_figure_width as f64 + legend_width as f64 + 10.0) * 0.6)
-                    as i32,
+                header_column(3.0),
                 hp.calc(top_margin + 2.0 * line_distance) as i32,
             ),
             (font_type, font_size_header).into_font(),
@@ -334,8 +843,7 @@ pub fn make_pole_figures(
                 tric_perc_full[0], tric_perc_full[1], tric_perc_full[2]
             ),
             (
-                (left_margin + (total_figure_width as f64 + legend_width as f64 + 10.0) * 0.8)
-                    as i32,
+                header_column(4.0),
                 hp.calc(top_margin + 1.0 * line_distance) as i32,
             ),
             (font_type, font_size_header).into_font(),
@@ -348,8 +856,7 @@ pub fn make_pole_figures(
                 ((tric_unsorted[2]) / (full_norm_square - isotropic)) * 100.
             ),
             (
-                (left_margin + (total_figure_width as f64 + legend_width as f64 + 10.0) * 0.8)
-                    as i32,
+                header_column(4.0),
                 hp.calc(top_margin + 2.0 * line_distance) as i32,
             ),
             (font_type, font_size_header).into_font(),
@@ -357,162 +864,310 @@ pub fn make_pole_figures(
 
         println!("    end header: Elapsed time: {:.2?}", clock.elapsed());
     }
+
+    if time_line {
+        header.draw(&Text::new(
+            match (&layout.header_template, labels) {
+                (Some(header_template), _) => header_text(
+                    header_template,
+                    particle_id,
+                    time,
+                    n_grains,
+                    particle_record,
+                    labels.map_or("", |labels| labels.title.as_str()),
+                ),
+                (None, Some(labels)) => labels.title.clone(),
+                (None, None) => format!("id={}, time={:.5e}", particle_id, time),
+            },
+            (wp.calc(left_margin) as i32, hp.calc(top_margin) as i32),
+            (font_type, font_size_header).into_font(),
+        ))?;
+    }
+    if let Some(fabric_strength) = fabric_strength {
+        let j_index_string = fabric_strength
+            .iter()
+            .map(|strength| format!("{}={:.2}", strength.mineral, strength.j_index))
+            .collect::<Vec<String>>()
+            .join(",");
+        let m_index_string = fabric_strength
+            .iter()
+            .map(|strength| format!("{}={:.3}", strength.mineral, strength.m_index))
+            .collect::<Vec<String>>()
+            .join(",");
+        header.draw(&Text::new(
+            format!("J-index: {}, M-index: {}", j_index_string, m_index_string),
+            (
+                wp.calc(left_margin) as i32,
+                hp.calc(top_margin + fabric_strength_line * line_distance) as i32,
+            ),
+            (font_type, font_size_header).into_font(),
+        ))?;
+    }
+    if let Some(seismic_anisotropy) = seismic_anisotropy {
+        header.draw(&Text::new(
+            format!(
+                "xi={:.3}, phi={:.3}, eta={:.3}, 2theta: B={:.2}%, G={:.2}% at {:.1}, 4theta: E={:.2}%",
+                seismic_anisotropy.xi,
+                seismic_anisotropy.phi,
+                seismic_anisotropy.eta,
+                seismic_anisotropy.b_amplitude(),
+                seismic_anisotropy.g_amplitude(),
+                seismic_anisotropy.g_fast_azimuth(),
+                seismic_anisotropy.e_amplitude()
+            ),
+            (
+                wp.calc(left_margin) as i32,
+                hp.calc(top_margin + seismic_anisotropy_line * line_distance) as i32,
+            ),
+            (font_type, font_size_header).into_font(),
+        ))?;
+    }
     println!("    start body: Elapsed time: {:.2?}", clock.elapsed());
     // do stuff in body:
 
-    let font_size_figure = if small_figure { 30 } else { 50 };
-    let (left, right) = body.split_horizontally(total_figure_width);
+    let font_size_figure = layout.panel_font_size(small_figure);
+    let (left, right) = if horizontal_color_bar {
+        body.split_vertically(number_of_figures_vertical as u32 * figure_height)
+    } else {
+        body.split_horizontally(total_figure_width)
+    };
 
     let drawing_areas_horizontal = left.split_evenly((1, number_of_figures_horizontal));
+    let color_bar_areas = right.split_evenly((number_of_figures_vertical, 1));
 
     for horizontal_figure_number in 0..number_of_figures_horizontal {
         let drawing_areas_vertical = drawing_areas_horizontal[horizontal_figure_number]
             .split_evenly((number_of_figures_vertical, 1));
-        let right_areas = right.split_evenly((number_of_figures_vertical, 1));
 
         for vertical_figure_number in 0..number_of_figures_vertical {
-            let max_count_value =
-                pole_figure_grid[horizontal_figure_number][vertical_figure_number].max_count;
-
-            if horizontal_figure_number == 0 {
-                let mut chart = ChartBuilder::on(&right_areas[vertical_figure_number])
-                    .margin(25)
-                    .margin_right(2)
-                    .margin_left(10)
-                    .top_x_label_area_size(0)
-                    .y_label_area_size(100)
-                    .caption(
-                        if small_figure {
-                            format!("{:.2}", max_count_value)
-                        } else {
-                            format!("{:.2}", max_count_value)
-                        },
-                        ("helvetica", font_size_figure),
-                    )
-                    .build_cartesian_2d(0.0..1.0, 0.0..max_count_value)?;
-
-                chart
-                    .configure_mesh()
-                    .x_labels(0)
-                    .y_labels(10)
-                    .y_label_offset(15)
-                    .disable_x_mesh()
-                    .disable_y_mesh()
-                    .label_style(("helvetica", font_size_figure))
-                    .draw()?;
-
-                let legend_size = 151;
+            let color_scale_min =
+                pole_figure_grid[horizontal_figure_number][vertical_figure_number].color_scale_min;
+            let color_scale_max =
+                pole_figure_grid[horizontal_figure_number][vertical_figure_number].color_scale_max;
 
-                let mut matrix = [max_count_value; 151];
-
-                for i in 0..legend_size - 1 {
-                    matrix[i] = i as f64 * max_count_value / (legend_size as f64 - 1.0);
+            let [first_label, second_label] = match labels {
+                Some(labels) => {
+                    labels.pole_figures[horizontal_figure_number][vertical_figure_number].clone()
                 }
+                None => [
+                    match pole_figure_grid[horizontal_figure_number][vertical_figure_number].mineral
+                    {
+                        Mineral::Olivine => "olivine".to_string(),
+                        Mineral::Enstatite => "enstatite".to_string(),
+                    },
+                    pole_figure_grid[horizontal_figure_number][vertical_figure_number]
+                        .crystal_axis
+                        .label(),
+                ],
+            };
 
-                for i in 0..legend_size - 1 {
-                    let picked_color = color_gradient
-                        .get(((matrix[i]).powf(gam) / (max_count_value.powf(gam))) as f32);
-                    let picked_rgb_color = RGBColor(
-                        (picked_color.red * 255.0) as u8,
-                        (picked_color.green * 255.0) as u8,
-                        (picked_color.blue * 255.0) as u8,
-                    );
-                    chart.draw_series(std::iter::once(Rectangle::new(
-                        [(0.0, matrix[i]), (0.0 + 1.0, matrix[i + 1])],
-                        picked_rgb_color.filled(),
-                    )))?;
-                }
+            if horizontal_figure_number == 0 && !contours_only {
+                // With a color scale per pole figure the color bar shows the fraction of the maximum, otherwise the
+                // range is the same for all the pole figures of the row.
+                let (legend_min, legend_max, title) = if subplot_color_scale {
+                    (0.0, 1.0, "relative".to_string())
+                } else {
+                    (
+                        color_scale_min,
+                        color_scale_max,
+                        color_bar_title.to_string(),
+                    )
+                };
+                draw_color_bar(
+                    &color_bar_areas[vertical_figure_number],
+                    &ColorBar {
+                        min: legend_min,
+                        max: legend_max,
+                        scaling: color_scaling,
+                        // the horizontal color bars are all below the pole figures, so they are named by row
+                        title: if horizontal_color_bar {
+                            format!("{} {}", first_label, title)
+                        } else {
+                            title
+                        },
+                        clipped_above: !subplot_color_scale
+                            && pole_figure_grid.iter().any(|column| {
+                                column[vertical_figure_number].max_count > color_scale_max
+                            }),
+                        clipped_below: !subplot_color_scale
+                            && pole_figure_grid.iter().any(|column| {
+                                column[vertical_figure_number].min_count < color_scale_min
+                            }),
+                        horizontal: horizontal_color_bar,
+                        steps: layout.color_bar_steps,
+                    },
+                    color_gradient,
+                    font_type,
+                    font_size_figure,
+                )?;
             }
 
-            let crystal_axis_string = match pole_figure_grid[horizontal_figure_number]
-                [vertical_figure_number]
-                .crystal_axis
-            {
-                CrystalAxes::AAxis => "a-axis",
-                CrystalAxes::BAxis => "b-axis",
-                CrystalAxes::CAxis => "c-axis",
-            };
-            let mineral_string =
-                match pole_figure_grid[horizontal_figure_number][vertical_figure_number].mineral {
-                    Mineral::Olivine => "olivine",
-                    Mineral::Enstatite => "enstatite",
-                };
             let mut chart = ChartBuilder::on(&drawing_areas_vertical[vertical_figure_number])
                 .build_cartesian_2d(
                     -lambert.r_plane - 0.05..lambert.r_plane + 0.15,
                     -lambert.r_plane - 0.05..lambert.r_plane + 0.15,
                 )?;
             let counts = &pole_figure_grid[horizontal_figure_number][vertical_figure_number].counts;
-            let npts = counts.shape()[0];
-
-            let mut current: Vec<Vec<Vec<(f64, f64)>>> = Vec::new();
-            for i in 0..npts - 1 {
-                let mut current_i: Vec<Vec<(f64, f64)>> = Vec::new();
-                for j in 0..npts - 1 {
-                    current_i.push(vec![
-                        (lambert.x_plane[[i + 1, j]], lambert.z_plane[[i + 1, j]]),
-                        (
-                            lambert.x_plane[[i + 1, j + 1]],
-                            lambert.z_plane[[i + 1, j + 1]],
-                        ),
-                        (lambert.x_plane[[i, j + 1]], lambert.z_plane[[i, j + 1]]),
-                        (lambert.x_plane[[i, j]], lambert.z_plane[[i, j]]),
-                    ]);
-                }
-                current.push(current_i);
-            }
 
-            let mut total_mask = mask.clone();
-            for i in 0..npts - 1 {
-                for j in 0..npts - 1 {
-                    if !mask[[i, j]].is_nan()
-                        && !mask[[i, j + 1]].is_nan()
-                        && !mask[[i + 1, j + 1]].is_nan()
-                        && !mask[[i + 1, j]].is_nan()
-                    {
-                        total_mask[[i, j]] = 1.0;
-                    } else {
-                        total_mask[[i, j]] = std::f64::NAN;
-                    }
-                }
+            if !contours_only {
+                // The density field is drawn as a single bitmap covering the plotting area, instead of a polygon for
+                // every grid cell.
+                let (width, height) = chart.plotting_area().dim_in_pixel();
+                let x_range = chart.x_range();
+                let y_range = chart.y_range();
+                let buffer = density_bitmap(
+                    counts,
+                    &mask,
+                    lambert.r_plane,
+                    (x_range.start, x_range.end),
+                    (y_range.start, y_range.end),
+                    (width, height),
+                    smooth_density,
+                    |value| {
+                        let color = gradient_color(
+                            color_gradient,
+                            color_scale_fraction(
+                                value,
+                                color_scale_min,
+                                color_scale_max,
+                                color_scaling,
+                            ),
+                        );
+                        [color.0, color.1, color.2]
+                    },
+                );
+                chart.draw_series(std::iter::once(
+                    BitMapElement::with_owned_buffer(
+                        (x_range.start, y_range.end),
+                        (width, height),
+                        buffer,
+                    )
+                    .ok_or("the size of the bitmap does not match the plotting area")?,
+                ))?;
             }
 
-            for i in 0..npts - 1 {
-                for j in 0..npts - 1 {
-                    let picked_color = color_gradient
-                        .get(((counts[[i, j]]).powf(gam) / (max_count_value.powf(gam))) as f32);
-                    let picked_rgb_color = RGBColor(
-                        (picked_color.red * 255.0) as u8,
-                        (picked_color.green * 255.0) as u8,
-                        (picked_color.blue * 255.0) as u8,
+            if !contour_levels.is_empty() {
+                let line_style = Into::<ShapeStyle>::into(&BLACK)
+                    .stroke_width(layout.scaled(if small_figure { 2 } else { 3 }));
+                let label_font_size = font_size_figure as f64 * 0.6;
+                for level in contour_levels {
+                    let segments = clip_to_circle(
+                        &contour_segments(&lambert.x_plane, &lambert.z_plane, counts, *level),
+                        lambert.r_plane,
                     );
+                    chart.draw_series(
+                        segments
+                            .iter()
+                            .map(|segment| PathElement::new(segment.to_vec(), line_style)),
+                    )?;
 
-                    if !mask[[i, j]].is_nan() {
-                        chart.draw_series(std::iter::once(Polygon::new(
-                            current[i][j].clone(),
-                            picked_rgb_color.filled(),
+                    // The label is placed on the segment closest to half the radius of the pole figure.
+                    let label_position = segments
+                        .iter()
+                        .map(|segment| {
+                            (
+                                (segment[0].0 + segment[1].0) / 2.,
+                                (segment[0].1 + segment[1].1) / 2.,
+                            )
+                        })
+                        .min_by(|a, b| {
+                            let distance = |p: &(f64, f64)| {
+                                ((p.0 * p.0 + p.1 * p.1).sqrt() - 0.5 * lambert.r_plane).abs()
+                            };
+                            distance(a).partial_cmp(&distance(b)).unwrap()
+                        });
+                    if let (true, Some(position)) = (contour_labels, label_position) {
+                        chart.draw_series(std::iter::once(Circle::new(
+                            position,
+                            (label_font_size * 0.8) as i32,
+                            WHITE.filled(),
+                        )))?;
+                        chart.draw_series(std::iter::once(Text::new(
+                            format!("{}", level),
+                            position,
+                            TextStyle::from((font_type, label_font_size).into_font())
+                                .pos(Pos::new(HPos::Center, VPos::Center)),
                         )))?;
                     }
                 }
             }
+            let pole_figure = &pole_figure_grid[horizontal_figure_number][vertical_figure_number];
+            if subplot_color_scale && !contours_only {
+                chart.draw_series(std::iter::once(Text::new(
+                    format!("max={:.2}", color_scale_max),
+                    (lambert.r_plane + 0.15, -lambert.r_plane - 0.05),
+                    TextStyle::from((font_type, font_size_figure as f64 * 0.6).into_font())
+                        .pos(Pos::new(HPos::Right, VPos::Bottom)),
+                )))?;
+            }
+
+            if !pole_figure.grain_poles.is_empty() {
+                let average_volume_fraction =
+                    pole_figure.grain_volume_fractions.iter().sum::<f64>()
+                        / pole_figure.grain_volume_fractions.len() as f64;
+                let color = if scatter_style.color_by_mineral {
+                    let (red, green, blue) = pole_figure.mineral.scatter_color();
+                    RGBColor(red, green, blue)
+                } else {
+                    BLACK
+                };
+                chart.draw_series(
+                    pole_figure
+                        .grain_poles
+                        .iter()
+                        .zip(pole_figure.grain_volume_fractions.iter())
+                        .map(|(position, volume_fraction)| {
+                            let radius = if scatter_style.size_by_volume_fraction {
+                                scatter_style.point_size
+                                    * (volume_fraction / average_volume_fraction).sqrt()
+                            } else {
+                                scatter_style.point_size
+                            };
+                            Circle::new(*position, radius.round().max(1.) as i32, color.filled())
+                        }),
+                )?;
+            }
+
             chart.draw_series(std::iter::once(PathElement::new(
                 circle_path.clone(),
-                Into::<ShapeStyle>::into(&BLACK).stroke_width(5),
+                Into::<ShapeStyle>::into(&BLACK).stroke_width(layout.scaled(5)),
             )))?;
 
+            if let Some(hexagonal_axis) = hexagonal_axis {
+                let position = lambert.project_axis(&[
+                    hexagonal_axis.vector[0],
+                    hexagonal_axis.vector[1],
+                    hexagonal_axis.vector[2],
+                ]);
+                let marker_size = layout.scaled(if small_figure { 10 } else { 16 }) as i32;
+                let marker_style = if hexagonal_axis.fast {
+                    Into::<ShapeStyle>::into(&BLACK).filled()
+                } else {
+                    Into::<ShapeStyle>::into(&BLACK).stroke_width(layout.scaled(4))
+                };
+                chart.draw_series(std::iter::once(Circle::new(
+                    position,
+                    marker_size,
+                    marker_style,
+                )))?;
+                chart.draw_series(std::iter::once(Circle::new(
+                    position,
+                    marker_size + layout.scaled(3) as i32,
+                    Into::<ShapeStyle>::into(&WHITE).stroke_width(layout.scaled(3)),
+                )))?;
+            }
+
             if !no_description_text {
-                drawing_areas_vertical[vertical_figure_number]
-                    .draw(&Text::new(
-                        format!("{}", crystal_axis_string),
-                        (
-                            wp.calc(left_margin) as i32,
-                            hp.calc(top_margin + 1.0 * line_distance) as i32,
-                        ),
-                        (font_type, font_size_figure, FontStyle::Bold).into_font(),
-                    ))
-                    .unwrap();
                 drawing_areas_vertical[vertical_figure_number].draw(&Text::new(
-                    format!("{}", mineral_string),
+                    second_label,
+                    (
+                        wp.calc(left_margin) as i32,
+                        hp.calc(top_margin + 1.0 * line_distance) as i32,
+                    ),
+                    (font_type, font_size_figure, FontStyle::Bold).into_font(),
+                ))?;
+                drawing_areas_vertical[vertical_figure_number].draw(&Text::new(
+                    first_label,
                     (
                         wp.calc(left_margin) as i32,
                         hp.calc(top_margin + 0.0 * line_distance) as i32,
@@ -520,19 +1175,19 @@ pub fn make_pole_figures(
                     (font_type, font_size_figure, FontStyle::Bold).into_font(),
                 ))?;
             }
-            drawing_areas_vertical[vertical_figure_number].draw(&Text::new(
-                format!("Z"),
-                (wp.calc(46.4) as i32, (hp.calc(11.) - 100.) as i32),
-                (font_type, font_size_figure).into_font(),
-            ))?;
-            drawing_areas_vertical[vertical_figure_number].draw(&Text::new(
-                format!("X"),
-                (
-                    wp.calc(96.0) as i32,
-                    if small_figure { 235 } else { 385 } as i32,
-                ),
-                (font_type, font_size_figure).into_font(),
-            ))?;
+            // The Z axis points up and the X axis to the right, so they are labelled just outside of the net.
+            chart.draw_series(std::iter::once(Text::new(
+                "Z".to_string(),
+                (0.0, bd_radius + 0.01),
+                TextStyle::from((font_type, font_size_figure).into_font())
+                    .pos(Pos::new(HPos::Center, VPos::Bottom)),
+            )))?;
+            chart.draw_series(std::iter::once(Text::new(
+                "X".to_string(),
+                (bd_radius + 0.01, 0.0),
+                TextStyle::from((font_type, font_size_figure).into_font())
+                    .pos(Pos::new(HPos::Left, VPos::Center)),
+            )))?;
 
             println!(
                 "      made pole figures subfigure {}:{}. Elapsed time: {:.2?}",
@@ -545,6 +1200,9 @@ pub fn make_pole_figures(
         }
     }
 
+    // The figure is written here instead of when the drawing area is dropped, where an error is ignored.
+    root.present()?;
+
     println!(
         "    Made set of polefigures for figure {}. Elapsed time: {:.2?}",
         path_string,
@@ -553,3 +1211,224 @@ pub fn make_pole_figures(
 
     Ok(())
 }
+
+/// Return the first line of the header from the `header_template`, with the placeholders replaced by the
+/// information of the particle. The `title` is the title of a comparison figure.
+fn header_text(
+    header_template: &str,
+    particle_id: u64,
+    time: f64,
+    n_grains: usize,
+    particle_record: &ParticleRecord,
+    title: &str,
+) -> String {
+    header_template
+        .replace("{id}", &particle_id.to_string())
+        .replace("{time}", &format!("{:.5e}", time))
+        .replace("{grains}", &n_grains.to_string())
+        .replace("{x}", &format!("{:.3e}", particle_record.x))
+        .replace("{y}", &format!("{:.3e}", particle_record.y))
+        .replace(
+            "{z}",
+            &particle_record
+                .z
+                .map_or(String::new(), |z| format!("{:.3e}", z)),
+        )
+        .replace(
+            "{odt}",
+            &particle_record
+                .olivine_deformation_type
+                .map_or(String::new(), |odt| format!("{:.1}", odt)),
+        )
+        .replace("{title}", title)
+}
+
+/// Compute the RGB pixels of the density field of a pole figure for a plotting area of `size` pixels which spans
+/// `x_range` horizontally and `z_range` vertically. The pixel gets the color of the grid point at the top left
+/// corner of the grid cell it is in, or, if `smooth` is set, the color of the bilinear interpolation of the four
+/// corners of the cell. Pixels outside of the circle with radius `r_plane` or outside of the mask stay white.
+#[allow(clippy::too_many_arguments)]
+fn density_bitmap<C>(
+    counts: &Array2<f64>,
+    mask: &Array2<f64>,
+    r_plane: f64,
+    x_range: (f64, f64),
+    z_range: (f64, f64),
+    size: (u32, u32),
+    smooth: bool,
+    color: C,
+) -> Vec<u8>
+where
+    C: Fn(f64) -> [u8; 3],
+{
+    let (width, height) = size;
+    let npts = counts.shape()[0];
+    let grid_coordinate =
+        |coordinate: f64| (coordinate + r_plane) / (2. * r_plane) * (npts - 1) as f64;
+    let mut buffer = vec![255u8; (width * height * 3) as usize];
+    for py in 0..height {
+        let z = z_range.1 - (py as f64 + 0.5) / height as f64 * (z_range.1 - z_range.0);
+        // the rows of the grid go from the top to the bottom of the pole figure
+        let u = grid_coordinate(-z);
+        let i = (u.max(0.).floor() as usize).min(npts - 2);
+        for px in 0..width {
+            let x = x_range.0 + (px as f64 + 0.5) / width as f64 * (x_range.1 - x_range.0);
+            if (x * x + z * z).sqrt() >= r_plane {
+                continue;
+            }
+            let v = grid_coordinate(x);
+            let j = (v.max(0.).floor() as usize).min(npts - 2);
+
+            let corners_in_mask = !mask[[i, j]].is_nan()
+                && !mask[[i, j + 1]].is_nan()
+                && !mask[[i + 1, j]].is_nan()
+                && !mask[[i + 1, j + 1]].is_nan();
+            let value = if smooth && corners_in_mask {
+                let (du, dv) = ((u - i as f64).min(1.), (v - j as f64).min(1.));
+                (1. - du) * (1. - dv) * counts[[i, j]]
+                    + (1. - du) * dv * counts[[i, j + 1]]
+                    + du * (1. - dv) * counts[[i + 1, j]]
+                    + du * dv * counts[[i + 1, j + 1]]
+            } else if !mask[[i, j]].is_nan() {
+                counts[[i, j]]
+            } else {
+                continue;
+            };
+
+            let index = ((py * width + px) * 3) as usize;
+            buffer[index..index + 3].copy_from_slice(&color(value));
+        }
+    }
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(value: f64) -> [u8; 3] {
+        let v = (value * 100.) as u8;
+        [v, v, v]
+    }
+
+    #[test]
+    fn test_header_text() {
+        let particle_record: ParticleRecord =
+            toml::from_str("id = 3\nx = 1.0\ny = 2.0\nolivine_deformation_type = 1.0").unwrap();
+        assert_eq!(
+            header_text(
+                "{title}: id={id}, t={time}, n={grains}, ({x},{y},{z}), ODT={odt}",
+                3,
+                0.5,
+                10,
+                &particle_record,
+                "olivine"
+            ),
+            "olivine: id=3, t=5.00000e-1, n=10, (1.000e0,2.000e0,), ODT=1.0"
+        );
+    }
+
+    #[test]
+    fn test_color_scale_fraction() {
+        let linear = ColorScaling::Linear;
+        let power = ColorScaling::Power { exponent: 0.5 };
+        assert_eq!(color_scale_fraction(1.0, 0.0, 2.0, &linear), 0.5);
+        assert_eq!(color_scale_fraction(1.0, 0.0, 2.0, &power), 0.5_f64.sqrt());
+        assert_eq!(color_scale_fraction(1.5, 1.0, 3.0, &linear), 0.25);
+        // values outside of the range are clipped
+        assert_eq!(color_scale_fraction(4.0, 1.0, 3.0, &linear), 1.0);
+        assert_eq!(color_scale_fraction(0.5, 1.0, 3.0, &linear), 0.0);
+        assert_eq!(color_scale_fraction(0.5, 1.0, 1.0, &linear), 0.0);
+
+        let log = ColorScaling::Log;
+        assert!((color_scale_fraction(1.0, 0.1, 10.0, &log) - 0.5).abs() < 1e-12);
+        // without a positive minimum the scale starts at a hundredth of the maximum
+        assert!((color_scale_fraction(10.0, 0.0, 100.0, &log) - 0.5).abs() < 1e-12);
+        assert_eq!(color_scale_fraction(0.0, 0.0, 100.0, &log), 0.0);
+        assert_eq!(color_scale_fraction(200.0, 0.0, 100.0, &log), 1.0);
+    }
+
+    #[test]
+    fn test_color_bar_ticks() {
+        let linear = ColorScaling::Linear;
+        assert_eq!(
+            color_bar_ticks(0.0, 1.0, &linear),
+            vec![0.0, 0.2, 0.4, 0.6000000000000001, 0.8, 1.0]
+        );
+        assert_eq!(color_bar_ticks(0.3, 7.4, &linear), vec![2.0, 4.0, 6.0]);
+        assert_eq!(
+            color_bar_ticks(0.5, 3.0, &ColorScaling::Power { exponent: 0.5 }),
+            vec![0.5, 1.0, 1.5, 2.0, 2.5, 3.0]
+        );
+        assert_eq!(
+            color_bar_ticks(0.0, 10.0, &ColorScaling::Log),
+            vec![0.1, 0.2, 0.5, 1.0, 2.0, 5.0, 10.0]
+        );
+        // within a single decade the ticks of a linear scale are used
+        assert_eq!(
+            color_bar_ticks(2.1, 4.9, &ColorScaling::Log),
+            vec![2.5, 3.0, 3.5, 4.0, 4.5]
+        );
+        assert_eq!(color_bar_ticks(1.0, 1.0, &linear), vec![1.0]);
+    }
+
+    #[test]
+    fn test_color_bar_tick_label() {
+        assert_eq!(color_bar_tick_label(0.6000000000000001), "0.6");
+        assert_eq!(color_bar_tick_label(10.0), "10");
+        assert_eq!(color_bar_tick_label(0.05), "0.05");
+        assert_eq!(color_bar_tick_label(-0.0), "0");
+    }
+
+    #[test]
+    fn test_density_bitmap() {
+        // a 3x3 grid on [-1, 1] with the density increasing from left to right
+        let counts =
+            Array2::from_shape_vec((3, 3), vec![0., 1., 2., 0., 1., 2., 0., 1., 2.]).unwrap();
+        let mask = Array2::from_elem((3, 3), 1.);
+
+        let buffer = density_bitmap(
+            &counts,
+            &mask,
+            1.,
+            (-1., 1.),
+            (-1., 1.),
+            (4, 4),
+            false,
+            gray,
+        );
+        assert_eq!(buffer.len(), 4 * 4 * 3);
+        // the corners are outside of the circle
+        assert_eq!(&buffer[0..3], &[255, 255, 255]);
+        // the pixels left of the center get the color of the left column, the ones right of the center the color
+        // of the center column.
+        let row = 4 * 3;
+        assert_eq!(&buffer[row..row + 3], &[0, 0, 0]);
+        assert_eq!(&buffer[row + 3..row + 6], &[0, 0, 0]);
+        assert_eq!(&buffer[row + 6..row + 9], &[100, 100, 100]);
+        assert_eq!(&buffer[row + 9..row + 12], &[100, 100, 100]);
+
+        let buffer = density_bitmap(&counts, &mask, 1., (-1., 1.), (-1., 1.), (4, 4), true, gray);
+        // the pixel centers are at x = -0.75, -0.25, 0.25 and 0.75, so the linear density is 0.25, 0.75, 1.25 and 1.75
+        assert_eq!(&buffer[row..row + 3], &[25, 25, 25]);
+        assert_eq!(&buffer[row + 3..row + 6], &[75, 75, 75]);
+        assert_eq!(&buffer[row + 6..row + 9], &[125, 125, 125]);
+        assert_eq!(&buffer[row + 9..row + 12], &[175, 175, 175]);
+
+        // pixels in a masked cell stay white
+        let mut mask = mask;
+        mask[[1, 0]] = f64::NAN;
+        let buffer = density_bitmap(
+            &counts,
+            &mask,
+            1.,
+            (-1., 1.),
+            (-1., 1.),
+            (4, 4),
+            false,
+            gray,
+        );
+        assert_eq!(&buffer[2 * row..2 * row + 3], &[255, 255, 255]);
+        assert_eq!(&buffer[2 * row + 6..2 * row + 9], &[100, 100, 100]);
+    }
+}
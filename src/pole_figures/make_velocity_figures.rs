@@ -0,0 +1,280 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use palette::gradient::Gradient;
+use palette::rgb::LinSrgb;
+use plotters::prelude::*;
+
+use crate::elasticity::christoffel::SeismicVelocities;
+use crate::pole_figures::{lambert::Lambert, percentage::Percentage};
+
+use ndarray::Array2;
+use std::path::Path;
+use std::time::Instant;
+
+/// Produce the seismic velocity figures: the P-wave velocity, the fast and slow shear wave velocities and the
+/// shear wave anisotropy as a function of the propagation direction, on the same hemisphere projection as the
+/// pole figures. The polarization of the fast shear wave is drawn as ticks on the anisotropy figure. Each figure
+/// has its own color scale from the minimum to the maximum value in the hemisphere.
+#[allow(clippy::too_many_arguments)]
+pub fn make_velocity_figures(
+    small_figure: bool,
+    no_description_text: bool,
+    particle_id: u64,
+    velocities: &SeismicVelocities,
+    lambert: &Lambert,
+    output_file: &Path,
+    time: f64,
+    color_gradient: &Gradient<LinSrgb>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let clock = Instant::now();
+
+    let figure_height = if small_figure { 500 } else { 800 };
+    let legend_width = if small_figure { 150 } else { 200 };
+    let hp = Percentage {
+        total: figure_height as f64,
+    };
+    let font_size_header = if small_figure { 28 } else { 45 };
+    let font_size_figure = if small_figure { 30 } else { 50 };
+    let line_distance = 5.5;
+    let top_margin = 0.25;
+    let left_margin = 0.5;
+    let font_type = "helvetica";
+
+    let panels: [(&str, &str, &Array2<f64>); 4] = [
+        ("Vp", "km/s", &velocities.vp),
+        ("Vs1", "km/s", &velocities.vs1),
+        ("Vs2", "km/s", &velocities.vs2),
+        ("AVs", "%", &velocities.shear_wave_anisotropy),
+    ];
+
+    let header_height = hp.calc(top_margin + line_distance) as u32 + font_size_header;
+    let panel_width = figure_height + legend_width;
+    let total_figure_width: u32 = panels.len() as u32 * panel_width + 10;
+    let total_figure_height: u32 = figure_height + header_height;
+
+    let path_string = output_file.to_string_lossy().into_owned();
+
+    let root = BitMapBackend::new(&path_string, (total_figure_width, total_figure_height))
+        .into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let (header, body) = root.split_vertically(header_height);
+
+    let wp = Percentage {
+        total: figure_height as f64,
+    };
+
+    header.draw(&Text::new(
+        format!("seismic velocities: id={}, time={:.5e}", particle_id, time),
+        (wp.calc(left_margin) as i32, hp.calc(top_margin) as i32),
+        (font_type, font_size_header).into_font(),
+    ))?;
+
+    let r = lambert.r_plane;
+    let npts = lambert.sphere_points;
+    let in_circle = |i: usize, j: usize| {
+        let (x, z) = (lambert.x_plane[[i, j]], lambert.z_plane[[i, j]]);
+        (x * x + z * z).sqrt() < r + 0.001
+    };
+
+    let circle_path: Vec<(f64, f64)> = (0..=100)
+        .map(|i| {
+            let angle = i as f64 / 100. * 2. * std::f64::consts::PI;
+            (r * angle.sin(), r * angle.cos())
+        })
+        .collect();
+
+    let panel_areas = body.split_evenly((1, panels.len()));
+    for (panel_number, (name, unit, values)) in panels.iter().enumerate() {
+        let (figure_area, legend_area) =
+            panel_areas[panel_number].split_horizontally(figure_height);
+
+        let (minimum, maximum) = (0..npts)
+            .flat_map(|i| (0..npts).map(move |j| (i, j)))
+            .filter(|(i, j)| in_circle(*i, *j))
+            .map(|(i, j)| values[[i, j]])
+            .fold((f64::MAX, f64::MIN), |(minimum, maximum), value| {
+                (minimum.min(value), maximum.max(value))
+            });
+        // avoid a zero range for isotropic media.
+        let range = if maximum - minimum > 1e-12 {
+            maximum - minimum
+        } else {
+            1.
+        };
+        let picked_color = |value: f64| {
+            let color = color_gradient.get(((value - minimum) / range) as f32);
+            RGBColor(
+                (color.red * 255.0) as u8,
+                (color.green * 255.0) as u8,
+                (color.blue * 255.0) as u8,
+            )
+        };
+
+        let mut legend = ChartBuilder::on(&legend_area)
+            .margin(25)
+            .margin_right(2)
+            .margin_left(10)
+            .top_x_label_area_size(0)
+            .y_label_area_size(100)
+            .caption(*unit, ("helvetica", font_size_figure))
+            .build_cartesian_2d(0.0..1.0, minimum..minimum + range)?;
+        legend
+            .configure_mesh()
+            .x_labels(0)
+            .y_labels(5)
+            .y_label_offset(15)
+            .disable_x_mesh()
+            .disable_y_mesh()
+            .label_style(("helvetica", font_size_figure))
+            .draw()?;
+        let legend_size = 150;
+        for i in 0..legend_size {
+            let value = minimum + i as f64 * range / legend_size as f64;
+            let next_value = minimum + (i + 1) as f64 * range / legend_size as f64;
+            legend.draw_series(std::iter::once(Rectangle::new(
+                [(0.0, value), (1.0, next_value)],
+                picked_color(value).filled(),
+            )))?;
+        }
+
+        let mut chart = ChartBuilder::on(&figure_area)
+            .build_cartesian_2d(-r - 0.05..r + 0.15, -r - 0.05..r + 0.15)?;
+        for i in 0..npts - 1 {
+            for j in 0..npts - 1 {
+                if !(in_circle(i, j)
+                    && in_circle(i + 1, j)
+                    && in_circle(i, j + 1)
+                    && in_circle(i + 1, j + 1))
+                {
+                    continue;
+                }
+                chart.draw_series(std::iter::once(Polygon::new(
+                    vec![
+                        (lambert.x_plane[[i + 1, j]], lambert.z_plane[[i + 1, j]]),
+                        (
+                            lambert.x_plane[[i + 1, j + 1]],
+                            lambert.z_plane[[i + 1, j + 1]],
+                        ),
+                        (lambert.x_plane[[i, j + 1]], lambert.z_plane[[i, j + 1]]),
+                        (lambert.x_plane[[i, j]], lambert.z_plane[[i, j]]),
+                    ],
+                    picked_color(values[[i, j]]).filled(),
+                )))?;
+            }
+        }
+        chart.draw_series(std::iter::once(PathElement::new(
+            circle_path.clone(),
+            Into::<ShapeStyle>::into(&BLACK).stroke_width(5),
+        )))?;
+
+        if *name == "AVs" {
+            chart.draw_series(
+                polarization_ticks(velocities, lambert)
+                    .into_iter()
+                    .map(|tick| {
+                        PathElement::new(tick, Into::<ShapeStyle>::into(&BLACK).stroke_width(3))
+                    }),
+            )?;
+        }
+
+        if !no_description_text {
+            figure_area.draw(&Text::new(
+                name.to_string(),
+                (
+                    wp.calc(left_margin) as i32,
+                    hp.calc(top_margin + 0.0 * line_distance) as i32,
+                ),
+                (font_type, font_size_figure, FontStyle::Bold).into_font(),
+            ))?;
+            figure_area.draw(&Text::new(
+                format!("{:.2}-{:.2}", minimum, maximum),
+                (
+                    wp.calc(left_margin) as i32,
+                    hp.calc(top_margin + 1.0 * line_distance) as i32,
+                ),
+                (font_type, font_size_figure).into_font(),
+            ))?;
+        }
+        figure_area.draw(&Text::new(
+            "Z".to_string(),
+            (wp.calc(46.4) as i32, (hp.calc(11.) - 100.) as i32),
+            (font_type, font_size_figure).into_font(),
+        ))?;
+        figure_area.draw(&Text::new(
+            "X".to_string(),
+            (wp.calc(96.0) as i32, if small_figure { 235 } else { 385 }),
+            (font_type, font_size_figure).into_font(),
+        ))?;
+    }
+
+    // The figure is written here instead of when the drawing area is dropped, where an error is ignored.
+    root.present()?;
+
+    println!(
+        "    Made seismic velocity figures {}. Elapsed time: {:.2?}",
+        path_string,
+        clock.elapsed()
+    );
+
+    Ok(())
+}
+
+/// Return the ticks showing the polarization of the fast shear wave on a subset of the grid points, as the end
+/// points in the figure. The tick direction is the projection of the polarization vector onto the figure.
+fn polarization_ticks(velocities: &SeismicVelocities, lambert: &Lambert) -> Vec<Vec<(f64, f64)>> {
+    let mut ticks = vec![];
+    let npts = lambert.sphere_points;
+    let step = (npts / 15).max(1);
+    let tick_length = 0.08 * lambert.r_plane;
+    let epsilon = 1e-3;
+    for i in (step / 2..npts).step_by(step) {
+        for j in (step / 2..npts).step_by(step) {
+            let (x, z) = (lambert.x_plane[[i, j]], lambert.z_plane[[i, j]]);
+            if (x * x + z * z).sqrt() > 0.95 * lambert.r_plane {
+                continue;
+            }
+            let direction = [lambert.x[[i, j]], lambert.y[[i, j]], lambert.z[[i, j]]];
+            let polarization = [
+                velocities.vs1_polarization[[i, j, 0]],
+                velocities.vs1_polarization[[i, j, 1]],
+                velocities.vs1_polarization[[i, j, 2]],
+            ];
+            // move a small step along the polarization from the grid point, and project it onto the figure.
+            let (x_step, z_step) = lambert.project_axis(&[
+                direction[0] + epsilon * polarization[0],
+                direction[1] + epsilon * polarization[1],
+                direction[2] + epsilon * polarization[2],
+            ]);
+            let (dx, dz) = (x_step - x, z_step - z);
+            let length = (dx * dx + dz * dz).sqrt();
+            if length < 1e-12 {
+                continue;
+            }
+            let (dx, dz) = (
+                0.5 * tick_length * dx / length,
+                0.5 * tick_length * dz / length,
+            );
+            ticks.push(vec![(x - dx, z - dz), (x + dx, z + dz)]);
+        }
+    }
+    ticks
+}
@@ -18,11 +18,89 @@
   <http://www.gnu.org/licenses/>.
 */
 
+use crate::texture::lattice::LatticeParameters;
+use crate::texture::symmetry::CrystalSymmetry;
+
+use ndarray::{arr2, Array2};
 use serde_derive::Deserialize;
+use strum::Display;
 
 /// A enum to define what mineral of the pole figure should be plotted.
-#[derive(Deserialize, Clone)]
+#[derive(Display, Deserialize, Clone, Debug, PartialEq)]
 pub enum Mineral {
     Olivine,
     Enstatite,
 }
+
+impl Mineral {
+    /// Return the crystal symmetry of the mineral. Both olivine and enstatite are orthorhombic.
+    pub fn symmetry(&self) -> CrystalSymmetry {
+        match self {
+            Mineral::Olivine => CrystalSymmetry::Orthorhombic,
+            Mineral::Enstatite => CrystalSymmetry::Orthorhombic,
+        }
+    }
+
+    /// Return the lattice parameters of the mineral, for forsterite (Smyth and Hazen, 1973) and
+    /// orthoenstatite (Hugh-Jones and Angel, 1994).
+    pub fn lattice_parameters(&self) -> LatticeParameters {
+        match self {
+            Mineral::Olivine => LatticeParameters {
+                a: 4.756,
+                b: 10.207,
+                c: 5.980,
+                alpha: 90.,
+                beta: 90.,
+                gamma: 90.,
+            },
+            Mineral::Enstatite => LatticeParameters {
+                a: 18.227,
+                b: 8.819,
+                c: 5.179,
+                alpha: 90.,
+                beta: 90.,
+                gamma: 90.,
+            },
+        }
+    }
+
+    /// Return the single crystal stiffness of the mineral in GPa, as a 6x6 matrix in Voigt notation in the crystal
+    /// frame. These are the values used by D-Rex and ASPECT, for olivine from Abramson et al. (1997) and for
+    /// enstatite from Chai et al. (1997).
+    pub fn stiffness(&self) -> Array2<f64> {
+        match self {
+            Mineral::Olivine => arr2(&[
+                [320.71, 69.84, 71.22, 0., 0., 0.],
+                [69.84, 197.25, 74.8, 0., 0., 0.],
+                [71.22, 74.8, 234.32, 0., 0., 0.],
+                [0., 0., 0., 63.77, 0., 0.],
+                [0., 0., 0., 0., 77.67, 0.],
+                [0., 0., 0., 0., 0., 78.36],
+            ]),
+            Mineral::Enstatite => arr2(&[
+                [236.9, 79.6, 63.2, 0., 0., 0.],
+                [79.6, 180.5, 56.8, 0., 0., 0.],
+                [63.2, 56.8, 230.4, 0., 0., 0.],
+                [0., 0., 0., 84.3, 0., 0.],
+                [0., 0., 0., 0., 79.4, 0.],
+                [0., 0., 0., 0., 0., 80.1],
+            ]),
+        }
+    }
+
+    /// Return the density of the mineral in kg/m³, for the same olivine and enstatite as the stiffness.
+    pub fn density(&self) -> f64 {
+        match self {
+            Mineral::Olivine => 3355.,
+            Mineral::Enstatite => 3200.,
+        }
+    }
+
+    /// Return the color of the points of the mineral in a scatter plot of the grain poles, as RGB values.
+    pub fn scatter_color(&self) -> (u8, u8, u8) {
+        match self {
+            Mineral::Olivine => (62, 130, 40),
+            Mineral::Enstatite => (190, 90, 30),
+        }
+    }
+}
@@ -18,9 +18,15 @@
   <http://www.gnu.org/licenses/>.
 */
 
+pub mod contours;
 pub mod crystal_axis;
+pub mod inverse_pole_figure;
 pub mod lambert;
+pub mod make_inverse_pole_figures;
+pub mod make_misorientation_figures;
 pub mod make_pole_figures;
+pub mod make_velocity_figures;
 pub mod minerals;
 pub mod percentage;
 pub mod pole_figure;
+pub mod sample_direction;
@@ -30,4 +30,13 @@ pub struct PoleFigure {
     pub crystal_axis: CrystalAxes,
     pub counts: Array2<f64>,
     pub max_count: f64,
+    pub min_count: f64,
+    /// The range of the counts covered by the color scale.
+    pub color_scale_min: f64,
+    pub color_scale_max: f64,
+    /// The positions of the projected axes of the grains in the plane of the pole figure, only computed for a
+    /// scatter plot of the grain poles.
+    pub grain_poles: Vec<(f64, f64)>,
+    /// The volume fractions of the grains of the `grain_poles`.
+    pub grain_volume_fractions: Vec<f64>,
 }
@@ -0,0 +1,68 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use ndarray::{arr1, Array1};
+use serde_derive::Deserialize;
+
+/// A enum to define the sample direction shown in an inverse pole figure. Besides the axes of the sample frame,
+/// any direction can be given as a vector, for example `{ Vector = [1.0, 0.0, 1.0] }`.
+#[derive(Deserialize, Debug, Clone)]
+pub enum SampleDirection {
+    X,
+    Y,
+    Z,
+    Vector([f64; 3]),
+}
+
+impl SampleDirection {
+    /// Return the unit vector of the direction in the sample frame.
+    pub fn vector(&self) -> Array1<f64> {
+        match self {
+            SampleDirection::X => arr1(&[1., 0., 0.]),
+            SampleDirection::Y => arr1(&[0., 1., 0.]),
+            SampleDirection::Z => arr1(&[0., 0., 1.]),
+            SampleDirection::Vector(vector) => {
+                let vector = arr1(vector);
+                let length = vector.dot(&vector).sqrt();
+                vector / length
+            }
+        }
+    }
+
+    /// Return the label of the direction shown in the inverse pole figure.
+    pub fn label(&self) -> String {
+        match self {
+            SampleDirection::X => "X".to_string(),
+            SampleDirection::Y => "Y".to_string(),
+            SampleDirection::Z => "Z".to_string(),
+            SampleDirection::Vector(vector) => {
+                format!("({},{},{})", vector[0], vector[1], vector[2])
+            }
+        }
+    }
+
+    /// Return the short name of the direction used in the file names of the inverse pole figures.
+    pub fn file_label(&self) -> String {
+        match self {
+            SampleDirection::Vector(vector) => format!("{}_{}_{}", vector[0], vector[1], vector[2]),
+            _ => self.label(),
+        }
+    }
+}
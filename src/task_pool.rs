@@ -0,0 +1,196 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use rayon::prelude::*;
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+
+/// The thread pool in which the tasks are processed, together with an optional global limit on the memory of the
+/// tasks which run at the same time.
+pub struct TaskPool {
+    pub thread_pool: rayon::ThreadPool,
+    /// The maximum memory in bytes of all the tasks which run at the same time.
+    pub max_memory: Option<usize>,
+}
+
+impl TaskPool {
+    /// Run `task` for all the `items` in the thread pool and return the results in the order of the items. After
+    /// the first error no new tasks are started, and the error of the first item which failed is returned.
+    ///
+    /// A task returns its result together with the memory in bytes it used. Without a memory limit, all the items
+    /// are processed at once by the threads of the pool. With a memory limit, a task only starts when its estimated
+    /// memory fits in the limit together with the estimates of the running tasks. The estimate is the largest memory
+    /// used by the tasks which are finished, or the whole limit as long as no task is finished, so that the first
+    /// task runs on its own. A task which returns zero memory does not change the estimate.
+    pub fn run<T, R, F>(&self, items: &[T], task: F) -> Result<Vec<R>, String>
+    where
+        T: Sync,
+        R: Send,
+        F: Fn(&T) -> Result<(R, usize), String> + Sync,
+    {
+        let max_memory = match self.max_memory {
+            Some(max_memory) => max_memory,
+            None => {
+                return self.thread_pool.install(|| {
+                    items
+                        .par_iter()
+                        .map(|item| task(item).map(|(result, _)| result))
+                        .collect()
+                })
+            }
+        };
+
+        let memory_budget = MemoryBudget::new(max_memory);
+        let next_item = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let results: Mutex<Vec<Option<Result<R, String>>>> =
+            Mutex::new(items.iter().map(|_| None).collect());
+
+        // The tasks are started from threads outside the pool, so that waiting for memory never blocks a thread of
+        // the pool which a running task needs for its own parallel work.
+        std::thread::scope(|scope| {
+            for _ in 0..self.thread_pool.current_num_threads().min(items.len()) {
+                scope.spawn(|| loop {
+                    let index = next_item.fetch_add(1, Ordering::SeqCst);
+                    if index >= items.len() || failed.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let reserved_memory = memory_budget.reserve();
+                    let result = self.thread_pool.install(|| task(&items[index]));
+                    let used_memory = result.as_ref().map_or(0, |(_, memory)| *memory);
+                    memory_budget.release(reserved_memory, used_memory);
+
+                    let result = result.map(|(result, _)| result);
+                    if result.is_err() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    results.lock().unwrap()[index] = Some(result);
+                });
+            }
+        });
+
+        // The items after an error may not have a result, but then the error is returned anyway.
+        results
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+/// Keeps track of the memory reserved by the running tasks and of the estimate of the memory of a task.
+struct MemoryBudget {
+    max_memory: usize,
+    state: Mutex<MemoryBudgetState>,
+    changed: Condvar,
+}
+
+struct MemoryBudgetState {
+    reserved_memory: usize,
+    task_memory: Option<usize>,
+}
+
+impl MemoryBudget {
+    fn new(max_memory: usize) -> MemoryBudget {
+        MemoryBudget {
+            max_memory,
+            state: Mutex::new(MemoryBudgetState {
+                reserved_memory: 0,
+                task_memory: None,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Wait until the estimated memory of a task fits in the limit, reserve it and return the reserved memory.
+    fn reserve(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let memory = state
+                .task_memory
+                .unwrap_or(self.max_memory)
+                .min(self.max_memory);
+            if state.reserved_memory + memory <= self.max_memory {
+                state.reserved_memory += memory;
+                return memory;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    /// Release the memory reserved for a task and update the estimate with the memory the task used.
+    fn release(&self, reserved_memory: usize, used_memory: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.reserved_memory -= reserved_memory;
+        if used_memory > 0 {
+            state.task_memory = Some(state.task_memory.unwrap_or(0).max(used_memory));
+        }
+        self.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_limit() {
+        let task_pool = TaskPool {
+            thread_pool: rayon::ThreadPoolBuilder::new()
+                .num_threads(4)
+                .build()
+                .unwrap(),
+            max_memory: Some(100),
+        };
+        let running_memory = AtomicUsize::new(0);
+        let max_running_memory = AtomicUsize::new(0);
+        let items: Vec<usize> = (0..40).collect();
+
+        // every task uses 30 bytes, so at most three of them fit in the limit at the same time.
+        let results = task_pool
+            .run(&items, |item| {
+                let running = running_memory.fetch_add(30, Ordering::SeqCst) + 30;
+                max_running_memory.fetch_max(running, Ordering::SeqCst);
+                std::thread::sleep(std::time::Duration::from_millis(2));
+                running_memory.fetch_sub(30, Ordering::SeqCst);
+                Ok((item * 2, 30))
+            })
+            .unwrap();
+        assert_eq!(
+            results,
+            items.iter().map(|item| item * 2).collect::<Vec<_>>()
+        );
+        assert!(max_running_memory.load(Ordering::SeqCst) <= 90);
+
+        let error = task_pool
+            .run(&items, |item| {
+                if *item == 5 {
+                    Err(format!("item {}", item))
+                } else {
+                    Ok((*item, 30))
+                }
+            })
+            .err()
+            .unwrap();
+        assert_eq!(error, "item 5");
+    }
+}
@@ -0,0 +1,236 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use crate::texture::orientation_tensor::OrientationTensor;
+
+use strum::Display;
+
+/// The olivine fabric types as defined by Karato et al. (2008), plus the axial-[010] AG-type.
+#[derive(Display, Clone, Copy, Debug, PartialEq)]
+pub enum OlivineFabricType {
+    /// [100] parallel to the shear direction, [010] normal to the shear plane.
+    A,
+    /// [001] parallel to the shear direction, [010] normal to the shear plane.
+    B,
+    /// [001] parallel to the shear direction, [100] normal to the shear plane.
+    C,
+    /// [100] parallel to the shear direction, [010] and [001] in girdles normal to it (axial-[100]).
+    D,
+    /// [100] parallel to the shear direction, [001] normal to the shear plane.
+    E,
+    /// [010] normal to the shear plane, [100] in a girdle in the shear plane (axial-[010]).
+    AG,
+    /// The fabric could not be assigned to any of the types.
+    Unclassified,
+}
+
+impl OlivineFabricType {
+    /// Convert the olivine deformation type stored by ASPECT for a particle to the fabric type it is expected
+    /// to produce. Returns `None` if the deformation type does not correspond to an olivine fabric type, such as
+    /// passive rotation.
+    pub fn from_deformation_type(deformation_type: f64) -> Option<OlivineFabricType> {
+        match deformation_type.round() as i64 {
+            1 => Some(OlivineFabricType::A),
+            2 => Some(OlivineFabricType::B),
+            3 => Some(OlivineFabricType::C),
+            4 => Some(OlivineFabricType::D),
+            5 => Some(OlivineFabricType::E),
+            _ => None,
+        }
+    }
+}
+
+/// Stores the BA-index and the fabric type of the olivine grains of a particle.
+#[derive(Clone, Debug)]
+pub struct OlivineFabric {
+    pub ba_index: f64,
+    pub fabric_type: OlivineFabricType,
+}
+
+impl OlivineFabric {
+    /// Determine the olivine fabric from the orientation tensors of the [100], [010] and [001] axes.
+    /// The shear direction and the shear plane normal are given in the sample frame.
+    pub fn from_orientation_tensors(
+        a_axis: &OrientationTensor,
+        b_axis: &OrientationTensor,
+        c_axis: &OrientationTensor,
+        shear_direction: &[f64; 3],
+        shear_plane_normal: &[f64; 3],
+    ) -> OlivineFabric {
+        let ba_index = ba_index(a_axis, b_axis);
+        OlivineFabric {
+            ba_index,
+            fabric_type: classify_olivine_fabric(
+                ba_index,
+                [a_axis, b_axis, c_axis],
+                shear_direction,
+                shear_plane_normal,
+            ),
+        }
+    }
+}
+
+/// Compute the BA-index (Mainprice et al., 2014) from the orientation tensors of the olivine [100] and [010]
+/// axes, using the Vollmer point and girdle proportions:
+/// `BA = 0.5 (2 - P010 / (G010 + P010) - G100 / (G100 + P100))`.
+/// It is zero for an axial-[010] fabric, one for an axial-[100] fabric and around 0.5 for an orthorhombic fabric.
+pub fn ba_index(a_axis: &OrientationTensor, b_axis: &OrientationTensor) -> f64 {
+    let (point_010, girdle_010) = (b_axis.point(), b_axis.girdle());
+    let (point_100, girdle_100) = (a_axis.point(), a_axis.girdle());
+    0.5 * (2. - point_010 / (girdle_010 + point_010) - girdle_100 / (girdle_100 + point_100))
+}
+
+/// Classify an olivine fabric. Axial fabrics are recognized from the BA-index (below 0.35 for
+/// axial-[010] and above 0.65 for axial-[100]). Orthorhombic fabrics are classified by which of the crystal axes
+/// has its maximum concentration closest to the shear direction and which to the shear plane normal.
+pub fn classify_olivine_fabric(
+    ba_index: f64,
+    axes: [&OrientationTensor; 3],
+    shear_direction: &[f64; 3],
+    shear_plane_normal: &[f64; 3],
+) -> OlivineFabricType {
+    if ba_index.is_nan() {
+        return OlivineFabricType::Unclassified;
+    }
+    if ba_index < 0.35 {
+        return OlivineFabricType::AG;
+    }
+    if ba_index > 0.65 {
+        return OlivineFabricType::D;
+    }
+
+    // the absolute cosine between the maximum eigenvector of each crystal axis and the given direction
+    let alignment = |direction: &[f64; 3]| -> Vec<f64> {
+        let length = direction.iter().map(|x| x * x).sum::<f64>().sqrt();
+        axes.iter()
+            .map(|tensor| {
+                let eigenvector = tensor.eigenvectors.column(0);
+                (0..3)
+                    .map(|i| eigenvector[i] * direction[i])
+                    .sum::<f64>()
+                    .abs()
+                    / length
+            })
+            .collect()
+    };
+
+    let direction_alignment = alignment(shear_direction);
+    let normal_alignment = alignment(shear_plane_normal);
+
+    let direction_axis = argmax(&direction_alignment, None);
+    let normal_axis = argmax(&normal_alignment, Some(direction_axis));
+
+    match (direction_axis, normal_axis) {
+        (0, 1) => OlivineFabricType::A,
+        (2, 1) => OlivineFabricType::B,
+        (2, 0) => OlivineFabricType::C,
+        (0, 2) => OlivineFabricType::E,
+        _ => OlivineFabricType::Unclassified,
+    }
+}
+
+/// Return the index of the largest value, skipping the index `exclude` if provided.
+fn argmax(values: &[f64], exclude: Option<usize>) -> usize {
+    values
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| Some(*i) != exclude)
+        .fold((0, f64::MIN), |max, (i, value)| {
+            if *value > max.1 {
+                (i, *value)
+            } else {
+                max
+            }
+        })
+        .0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array2;
+
+    /// Create `n` unit vectors spread by `spread` degree around `direction`, rotating about `axis`.
+    fn spread_vectors(direction: [f64; 3], axis: [f64; 3], spread: f64, n: usize) -> Array2<f64> {
+        Array2::from_shape_fn((n, 3), |(i, j)| {
+            let angle = (spread * (i as f64 / (n - 1) as f64 - 0.5)).to_radians();
+            direction[j] * angle.cos() + axis[j] * angle.sin()
+        })
+    }
+
+    #[test]
+    fn test_olivine_fabric_types() {
+        let x = [1., 0., 0.];
+        let y = [0., 1., 0.];
+        let z = [0., 0., 1.];
+        let tensor = |direction, axis, spread| {
+            OrientationTensor::from_axis_vectors(&spread_vectors(direction, axis, spread, 101))
+        };
+
+        // A-type: [100] along the shear direction (X), [010] normal to the shear plane (Z).
+        let fabric = OlivineFabric::from_orientation_tensors(
+            &tensor(x, y, 20.),
+            &tensor(z, y, 20.),
+            &tensor(y, x, 20.),
+            &x,
+            &z,
+        );
+        assert!((fabric.ba_index - 0.5).abs() < 0.15);
+        assert_eq!(fabric.fabric_type, OlivineFabricType::A);
+
+        // C-type: [001] along the shear direction, [100] normal to the shear plane.
+        let fabric = OlivineFabric::from_orientation_tensors(
+            &tensor(z, y, 20.),
+            &tensor(y, x, 20.),
+            &tensor(x, y, 20.),
+            &x,
+            &z,
+        );
+        assert_eq!(fabric.fabric_type, OlivineFabricType::C);
+
+        // AG-type: [010] point maximum, [100] girdle.
+        let fabric = OlivineFabric::from_orientation_tensors(
+            &tensor(x, y, 180.),
+            &tensor(z, y, 5.),
+            &tensor(y, x, 180.),
+            &x,
+            &z,
+        );
+        assert!(fabric.ba_index < 0.35);
+        assert_eq!(fabric.fabric_type, OlivineFabricType::AG);
+
+        // D-type: [100] point maximum, [010] girdle.
+        let fabric = OlivineFabric::from_orientation_tensors(
+            &tensor(x, y, 5.),
+            &tensor(z, y, 180.),
+            &tensor(y, z, 180.),
+            &x,
+            &z,
+        );
+        assert!(fabric.ba_index > 0.65);
+        assert_eq!(fabric.fabric_type, OlivineFabricType::D);
+
+        assert_eq!(
+            OlivineFabricType::from_deformation_type(2.0),
+            Some(OlivineFabricType::B)
+        );
+        assert_eq!(OlivineFabricType::from_deformation_type(0.0), None);
+    }
+}
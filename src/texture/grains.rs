@@ -0,0 +1,149 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use crate::pole_figures::{crystal_axis::CrystalAxes, minerals::Mineral};
+
+use ndarray::Array2;
+
+/// Stores the orientations of all the grains of a single particle at a single time, as rotation
+/// matrices. The rows of each rotation matrix are the a-, b- and c-axis of the grain. The volume fractions
+/// have one value per grain, and are all equal if the grain data does not contain them.
+pub struct ParticleGrains {
+    pub olivine: Vec<Array2<f64>>,
+    pub enstatite: Vec<Array2<f64>>,
+    pub olivine_volume_fractions: Vec<f64>,
+    pub enstatite_volume_fractions: Vec<f64>,
+}
+
+impl ParticleGrains {
+    /// Return the rotation matrices of all the grains of a mineral.
+    pub fn rotation_matrices(&self, mineral: &Mineral) -> &[Array2<f64>] {
+        match mineral {
+            Mineral::Olivine => &self.olivine,
+            Mineral::Enstatite => &self.enstatite,
+        }
+    }
+
+    /// Return the volume fractions of all the grains of a mineral.
+    pub fn volume_fractions(&self, mineral: &Mineral) -> &[f64] {
+        match mineral {
+            Mineral::Olivine => &self.olivine_volume_fractions,
+            Mineral::Enstatite => &self.enstatite_volume_fractions,
+        }
+    }
+
+    /// Return the volume fraction of the grain of every row of [`ParticleGrains::axis_vectors`], so the
+    /// volume fraction of a grain is repeated for every symmetrically equivalent direction.
+    pub fn axis_volume_fractions(&self, mineral: &Mineral, axis: &CrystalAxes) -> Vec<f64> {
+        let directions_per_grain = match axis {
+            CrystalAxes::AAxis | CrystalAxes::BAxis | CrystalAxes::CAxis => 1,
+            _ => axis.crystal_directions(mineral).len(),
+        };
+        self.volume_fractions(mineral)
+            .iter()
+            .flat_map(|volume_fraction| std::iter::repeat_n(*volume_fraction, directions_per_grain))
+            .collect()
+    }
+
+    /// Return the number of grains per mineral.
+    pub fn n_grains(&self) -> usize {
+        self.olivine.len()
+    }
+
+    /// Return an array with the directions in the sample frame of the crystal axis of the mineral. For the
+    /// a-, b- and c-axis there is one row per grain. For Miller indices there is one row per grain and
+    /// symmetrically equivalent direction.
+    pub fn axis_vectors(&self, mineral: &Mineral, axis: &CrystalAxes) -> Array2<f64> {
+        let rotation_matrices = self.rotation_matrices(mineral);
+        let row = match axis {
+            CrystalAxes::AAxis => Some(0),
+            CrystalAxes::BAxis => Some(1),
+            CrystalAxes::CAxis => Some(2),
+            _ => None,
+        };
+        if let Some(row) = row {
+            let mut axis_vectors = Array2::zeros((rotation_matrices.len(), 3));
+            for (mut axis_vector, rotation_matrix) in
+                axis_vectors.outer_iter_mut().zip(rotation_matrices)
+            {
+                axis_vector.assign(&rotation_matrix.row(row));
+            }
+            return axis_vectors;
+        }
+
+        // The rows of the rotation matrix are the crystal axes in the sample frame, so a vector in the
+        // crystal frame is rotated to the sample frame by the transpose of the rotation matrix.
+        let crystal_directions = axis.crystal_directions(mineral);
+        let mut axis_vectors =
+            Array2::zeros((rotation_matrices.len() * crystal_directions.len(), 3));
+        for (mut axis_vector, (rotation_matrix, crystal_direction)) in
+            axis_vectors.outer_iter_mut().zip(
+                rotation_matrices
+                    .iter()
+                    .flat_map(|r| crystal_directions.iter().map(move |d| (r, d))),
+            )
+        {
+            axis_vector.assign(&rotation_matrix.t().dot(crystal_direction));
+        }
+        axis_vectors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euler_angles_to_rotation_matrix;
+    use ndarray::Array;
+
+    #[test]
+    fn test_miller_index_axis_vectors() {
+        let rotation_matrix =
+            euler_angles_to_rotation_matrix(Array::from(vec![0.3, 1.1, -0.7])).unwrap();
+        let grains = ParticleGrains {
+            olivine: vec![rotation_matrix.clone(); 2],
+            enstatite: vec![],
+            olivine_volume_fractions: vec![0.25, 0.75],
+            enstatite_volume_fractions: vec![],
+        };
+
+        // the pole to (010) is the b-axis for an orthorhombic mineral.
+        let b_axis = grains.axis_vectors(&Mineral::Olivine, &CrystalAxes::BAxis);
+        let plane = grains.axis_vectors(&Mineral::Olivine, &CrystalAxes::Plane([0, 1, 0]));
+        assert_eq!(plane.nrows(), 2);
+        assert!((&b_axis - &plane).iter().all(|x| x.abs() < 1e-12));
+
+        // {110} has two axially distinct poles for an orthorhombic mineral.
+        let family = grains.axis_vectors(&Mineral::Olivine, &CrystalAxes::PlaneFamily([1, 1, 0]));
+        assert_eq!(family.nrows(), 4);
+        assert_eq!(
+            grains.axis_volume_fractions(&Mineral::Olivine, &CrystalAxes::PlaneFamily([1, 1, 0])),
+            vec![0.25, 0.25, 0.75, 0.75]
+        );
+        assert_eq!(
+            grains.axis_volume_fractions(&Mineral::Olivine, &CrystalAxes::BAxis),
+            vec![0.25, 0.75]
+        );
+        let c_axis = rotation_matrix.row(2);
+        for row in family.outer_iter() {
+            assert!(row.dot(&c_axis).abs() < 1e-12);
+            assert!((row.dot(&row) - 1.).abs() < 1e-12);
+        }
+    }
+}
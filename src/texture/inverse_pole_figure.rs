@@ -0,0 +1,102 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use crate::texture::symmetry::CrystalSymmetry;
+
+use ndarray::{Array1, Array2};
+
+/// Return the direction in the crystal frame of a sample direction for every grain, together with all its
+/// symmetrically equivalent directions, as an array with one row per grain and equivalent direction. The rows
+/// of the rotation matrices are the crystal axes in the sample frame, so the crystal components of a sample
+/// direction are the product of the rotation matrix with that direction.
+pub fn sample_direction_in_crystal_frame(
+    rotation_matrices: &[Array2<f64>],
+    sample_direction: &Array1<f64>,
+    symmetry: CrystalSymmetry,
+) -> Array2<f64> {
+    let length = sample_direction.dot(sample_direction).sqrt();
+    let symmetry_rotations = symmetry.rotations();
+    let mut crystal_directions =
+        Array2::zeros((rotation_matrices.len() * symmetry_rotations.len(), 3));
+    let mut row = 0;
+    for rotation_matrix in rotation_matrices {
+        let crystal_direction = rotation_matrix.dot(sample_direction) / length;
+        for symmetry_rotation in &symmetry_rotations {
+            crystal_directions
+                .row_mut(row)
+                .assign(&symmetry_rotation.dot(&crystal_direction));
+            row += 1;
+        }
+    }
+    crystal_directions
+}
+
+/// Return the symmetrically equivalent direction in the fundamental sector of the symmetry, taking into account
+/// that the directions are axial. For orthorhombic minerals this is the octant with all components positive,
+/// for triclinic minerals the hemisphere with a positive c component.
+pub fn reduce_to_fundamental_sector(
+    crystal_direction: &Array1<f64>,
+    symmetry: CrystalSymmetry,
+) -> Array1<f64> {
+    match symmetry {
+        CrystalSymmetry::Triclinic => {
+            if crystal_direction[2] < 0. {
+                -crystal_direction
+            } else {
+                crystal_direction.clone()
+            }
+        }
+        CrystalSymmetry::Orthorhombic => crystal_direction.mapv(f64::abs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euler_angles_to_rotation_matrix;
+    use ndarray::{arr1, Array};
+
+    #[test]
+    fn test_sample_direction_in_crystal_frame() {
+        let rotation_matrix =
+            euler_angles_to_rotation_matrix(Array::from(vec![0.3, 1.1, -0.7])).unwrap();
+        let sample_direction = arr1(&[0., 0., 2.]);
+        let symmetry = CrystalSymmetry::Orthorhombic;
+
+        let crystal_directions = sample_direction_in_crystal_frame(
+            std::slice::from_ref(&rotation_matrix),
+            &sample_direction,
+            symmetry,
+        );
+        assert_eq!(crystal_directions.nrows(), 4);
+
+        // the component along each crystal axis is the cosine between that axis and the sample direction
+        let reduced = reduce_to_fundamental_sector(&crystal_directions.row(0).to_owned(), symmetry);
+        for i in 0..3 {
+            assert!((reduced[i] - rotation_matrix[[i, 2]].abs()).abs() < 1e-12);
+        }
+
+        // all the symmetric equivalents reduce to the same direction
+        for row in crystal_directions.outer_iter() {
+            let other = reduce_to_fundamental_sector(&row.to_owned(), symmetry);
+            assert!((&other - &reduced).iter().all(|x| x.abs() < 1e-12));
+        }
+    }
+}
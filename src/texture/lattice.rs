@@ -0,0 +1,125 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use ndarray::{arr1, Array1, Array2};
+
+/// The lattice parameters of the unit cell of a mineral. The lengths are in Ångström and the angles in degree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LatticeParameters {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub alpha: f64,
+    pub beta: f64,
+    pub gamma: f64,
+}
+
+impl LatticeParameters {
+    /// Return the matrix with the lattice vectors a, b and c as columns in the Cartesian crystal frame. The a
+    /// lattice vector is along the x axis and the b lattice vector is in the x-y plane, so for lattices with
+    /// orthogonal axes the Cartesian crystal frame is the frame of the grain rotation matrices.
+    pub fn direct_matrix(&self) -> Array2<f64> {
+        let (alpha, beta, gamma) = (
+            self.alpha.to_radians(),
+            self.beta.to_radians(),
+            self.gamma.to_radians(),
+        );
+        let c_x = beta.cos();
+        let c_y = (alpha.cos() - beta.cos() * gamma.cos()) / gamma.sin();
+        let c_z = (1. - c_x * c_x - c_y * c_y).max(0.).sqrt();
+        let mut matrix = Array2::zeros((3, 3));
+        matrix[[0, 0]] = self.a;
+        matrix[[0, 1]] = self.b * gamma.cos();
+        matrix[[1, 1]] = self.b * gamma.sin();
+        matrix[[0, 2]] = self.c * c_x;
+        matrix[[1, 2]] = self.c * c_y;
+        matrix[[2, 2]] = self.c * c_z;
+        matrix
+    }
+
+    /// Return the unit vector in the Cartesian crystal frame of the direction with Miller indices [uvw].
+    pub fn direction(&self, indices: &[i32; 3]) -> Array1<f64> {
+        let indices = arr1(&[indices[0] as f64, indices[1] as f64, indices[2] as f64]);
+        normalize(self.direct_matrix().dot(&indices))
+    }
+
+    /// Return the unit vector in the Cartesian crystal frame of the pole to the plane with Miller indices (hkl),
+    /// which is the reciprocal lattice vector `h a* + k b* + l c*`.
+    pub fn plane_normal(&self, indices: &[i32; 3]) -> Array1<f64> {
+        // The reciprocal lattice vectors are the rows of the inverse of the direct matrix. Since the direct
+        // matrix is upper triangular, the inverse is computed by back substitution.
+        let direct = self.direct_matrix();
+        let mut inverse: Array2<f64> = Array2::zeros((3, 3));
+        for column in 0..3 {
+            for row in (0..3).rev() {
+                let identity = if row == column { 1. } else { 0. };
+                let sum: f64 = (row + 1..3)
+                    .map(|k| direct[[row, k]] * inverse[[k, column]])
+                    .sum();
+                inverse[[row, column]] = (identity - sum) / direct[[row, row]];
+            }
+        }
+        let indices = arr1(&[indices[0] as f64, indices[1] as f64, indices[2] as f64]);
+        normalize(inverse.t().dot(&indices))
+    }
+}
+
+/// Return the vector scaled to unit length.
+fn normalize(vector: Array1<f64>) -> Array1<f64> {
+    let length = vector.dot(&vector).sqrt();
+    vector / length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_miller_index_vectors() {
+        // forsterite
+        let orthorhombic = LatticeParameters {
+            a: 4.756,
+            b: 10.207,
+            c: 5.980,
+            alpha: 90.,
+            beta: 90.,
+            gamma: 90.,
+        };
+        let direction = orthorhombic.direction(&[1, 0, 1]);
+        let expected = normalize(arr1(&[4.756, 0., 5.980]));
+        assert!((&direction - &expected).iter().all(|x| x.abs() < 1e-12));
+        let normal = orthorhombic.plane_normal(&[1, 1, 0]);
+        let expected = normalize(arr1(&[1. / 4.756, 1. / 10.207, 0.]));
+        assert!((&normal - &expected).iter().all(|x| x.abs() < 1e-12));
+
+        // the pole to a plane is normal to all the directions in the plane, also for a triclinic lattice.
+        let triclinic = LatticeParameters {
+            a: 8.,
+            b: 12.8,
+            c: 7.1,
+            alpha: 93.,
+            beta: 116.,
+            gamma: 90.,
+        };
+        let normal = triclinic.plane_normal(&[1, 1, 0]);
+        assert!(normal.dot(&triclinic.direction(&[1, -1, 0])).abs() < 1e-12);
+        assert!(normal.dot(&triclinic.direction(&[0, 0, 1])).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,376 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use crate::texture::symmetry::CrystalSymmetry;
+
+use ndarray::Array2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Compute the trace of the product of two 3x3 matrices without computing the product itself.
+fn trace_of_product(a: &Array2<f64>, b: &Array2<f64>) -> f64 {
+    let mut trace = 0.0;
+    for i in 0..3 {
+        for j in 0..3 {
+            trace += a[[i, j]] * b[[j, i]];
+        }
+    }
+    trace
+}
+
+/// Compute the rotation angle in degree of a rotation matrix from its trace.
+fn rotation_angle_from_trace(trace: f64) -> f64 {
+    ((trace - 1.) / 2.).clamp(-1., 1.).acos().to_degrees()
+}
+
+/// Compute the misorientation angle in degree between two grains. The angle is reduced by the provided
+/// symmetry rotations (see [`CrystalSymmetry::rotations`]), so that the smallest of all the physically
+/// equivalent angles is returned.
+pub fn misorientation_angle(
+    rotation_matrix_a: &Array2<f64>,
+    rotation_matrix_b: &Array2<f64>,
+    symmetry_rotations: &[Array2<f64>],
+) -> f64 {
+    let misorientation = rotation_matrix_b.dot(&rotation_matrix_a.t());
+
+    // The trace of the misorientation combined with symmetry rotations on both sides only depends on the
+    // product of the two symmetry rotations, which is also part of the group. So it is enough to only
+    // apply them on one side.
+    let max_trace = symmetry_rotations
+        .iter()
+        .map(|symmetry_rotation| trace_of_product(&misorientation, symmetry_rotation))
+        .fold(-1., f64::max);
+
+    rotation_angle_from_trace(max_trace)
+}
+
+/// Compute the misorientation angles in degree between uncorrelated pairs of grains. If the number of
+/// possible pairs is smaller than `max_pairs`, all pairs are used. Otherwise `max_pairs` random pairs are
+/// used. The random pairs are generated with a fixed seed, so the results are reproducible.
+pub fn uncorrelated_misorientation_angles(
+    rotation_matrices: &[Array2<f64>],
+    symmetry: CrystalSymmetry,
+    max_pairs: usize,
+) -> Vec<f64> {
+    let n_grains = rotation_matrices.len();
+    if n_grains < 2 {
+        return vec![];
+    }
+
+    let symmetry_rotations = symmetry.rotations();
+    let n_pairs = n_grains * (n_grains - 1) / 2;
+
+    if n_pairs <= max_pairs {
+        let mut angles = Vec::with_capacity(n_pairs);
+        for i in 0..n_grains {
+            for j in i + 1..n_grains {
+                angles.push(misorientation_angle(
+                    &rotation_matrices[i],
+                    &rotation_matrices[j],
+                    &symmetry_rotations,
+                ));
+            }
+        }
+        angles
+    } else {
+        let mut rng = StdRng::seed_from_u64(0);
+        (0..max_pairs)
+            .map(|_| {
+                let i = rng.gen_range(0..n_grains);
+                let mut j = rng.gen_range(0..n_grains - 1);
+                if j >= i {
+                    j += 1;
+                }
+                misorientation_angle(
+                    &rotation_matrices[i],
+                    &rotation_matrices[j],
+                    &symmetry_rotations,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Compute a normalized histogram of misorientation angles in degree, with `bins` bins of equal width
+/// between zero and `max_angle`. The values of the bins add up to one.
+pub fn misorientation_histogram(angles: &[f64], bins: usize, max_angle: f64) -> Vec<f64> {
+    let mut histogram = vec![0.0; bins];
+    if angles.is_empty() {
+        return histogram;
+    }
+
+    for angle in angles {
+        let bin = ((angle / max_angle) * bins as f64) as usize;
+        histogram[bin.min(bins - 1)] += 1.0;
+    }
+
+    let total = angles.len() as f64;
+    histogram.iter_mut().for_each(|value| *value /= total);
+    histogram
+}
+
+/// Compute the theoretical distribution of misorientation angles between randomly oriented grains with
+/// the provided symmetry, with `bins` bins of equal width between zero and the maximum misorientation angle
+/// of the symmetry. The values of the bins add up to one.
+///
+/// Without symmetry, the rotation angle of a random rotation is distributed as `1 - cos(angle)`. A rotation
+/// only contributes to the misorientation angle distribution at its own angle if no symmetrically equivalent
+/// rotation has a smaller angle. The fraction of rotation axes for which that is the case is computed for each
+/// angle by checking a set of evenly distributed axes.
+pub fn random_misorientation_distribution(symmetry: CrystalSymmetry, bins: usize) -> Vec<f64> {
+    let symmetry_rotations = symmetry.rotations();
+    let max_angle = symmetry.max_misorientation_angle().to_radians();
+    let n_axes = 1000;
+    let n_sub_angles = 4;
+
+    // evenly distributed axes on the upper half of the sphere (Fibonacci lattice). The
+    // lower half is not needed, since a rotation around -n is a rotation around n by the
+    // negative angle.
+    let golden_angle = std::f64::consts::PI * (3. - 5.0_f64.sqrt());
+    let axes: Vec<[f64; 3]> = (0..n_axes)
+        .map(|i| {
+            let z = 1. - (i as f64 + 0.5) / n_axes as f64;
+            let radius = (1. - z * z).sqrt();
+            let phi = golden_angle * i as f64;
+            [radius * phi.cos(), radius * phi.sin(), z]
+        })
+        .collect();
+
+    let mut distribution = vec![0.0; bins];
+    let bin_width = max_angle / bins as f64;
+    for (bin, value) in distribution.iter_mut().enumerate() {
+        for sub_angle in 0..n_sub_angles {
+            let angle = (bin as f64 + (sub_angle as f64 + 0.5) / n_sub_angles as f64) * bin_width;
+            let (sin, cos) = angle.sin_cos();
+            let trace = 1. + 2. * cos;
+
+            let n_in_fundamental_zone = axes
+                .iter()
+                .filter(|axis| {
+                    let rotation = axis_angle_rotation_matrix(axis, sin, cos);
+                    symmetry_rotations.iter().all(|symmetry_rotation| {
+                        trace_of_product(&rotation, symmetry_rotation) <= trace + 1e-12
+                    })
+                })
+                .count();
+
+            *value += (1. - cos) * n_in_fundamental_zone as f64;
+        }
+    }
+
+    let total: f64 = distribution.iter().sum();
+    distribution.iter_mut().for_each(|value| *value /= total);
+    distribution
+}
+
+/// Compute the rotation matrix of a rotation around a unit axis, with the sine and cosine of the
+/// rotation angle provided.
+fn axis_angle_rotation_matrix(axis: &[f64; 3], sin: f64, cos: f64) -> Array2<f64> {
+    let [x, y, z] = *axis;
+    let c = 1. - cos;
+    ndarray::arr2(&[
+        [cos + x * x * c, x * y * c - z * sin, x * z * c + y * sin],
+        [y * x * c + z * sin, cos + y * y * c, y * z * c - x * sin],
+        [z * x * c - y * sin, z * y * c + x * sin, cos + z * z * c],
+    ])
+}
+
+/// Create a random rotation matrix, uniformly distributed over all orientations, through a random
+/// unit quaternion (Shoemake, 1992).
+pub fn random_rotation_matrix<R: Rng>(rng: &mut R) -> Array2<f64> {
+    let u1: f64 = rng.gen();
+    let u2: f64 = rng.gen();
+    let u3: f64 = rng.gen();
+    let two_pi = 2. * std::f64::consts::PI;
+
+    let w = (1. - u1).sqrt() * (two_pi * u2).sin();
+    let x = (1. - u1).sqrt() * (two_pi * u2).cos();
+    let y = u1.sqrt() * (two_pi * u3).sin();
+    let z = u1.sqrt() * (two_pi * u3).cos();
+
+    ndarray::arr2(&[
+        [
+            1. - 2. * (y * y + z * z),
+            2. * (x * y - z * w),
+            2. * (x * z + y * w),
+        ],
+        [
+            2. * (x * y + z * w),
+            1. - 2. * (x * x + z * z),
+            2. * (y * z - x * w),
+        ],
+        [
+            2. * (x * z - y * w),
+            2. * (y * z + x * w),
+            1. - 2. * (x * x + y * y),
+        ],
+    ])
+}
+
+/// Stores the distribution of uncorrelated misorientation angles of a set of grains, together with the
+/// theoretical distribution for randomly oriented grains with the same symmetry.
+#[derive(Clone, Debug)]
+pub struct MisorientationDistribution {
+    /// The number of grain pairs used for the distribution.
+    pub n_pairs: usize,
+    /// The width of the bins in degree.
+    pub bin_width: f64,
+    /// The maximum misorientation angle in degree for the symmetry of the mineral.
+    pub max_angle: f64,
+    /// The fraction of the pairs in each bin. The fractions add up to one if there are any pairs.
+    pub observed: Vec<f64>,
+    /// The fraction of randomly oriented pairs in each bin.
+    pub random: Vec<f64>,
+}
+
+impl MisorientationDistribution {
+    /// Compute the distribution from misorientation angles in degree, as computed by
+    /// [`uncorrelated_misorientation_angles`], in bins with approximately the provided width in degree. The
+    /// number of bins is rounded so that the bins exactly cover the range up to the maximum misorientation angle.
+    pub fn from_angles(
+        angles: &[f64],
+        symmetry: CrystalSymmetry,
+        bin_width: f64,
+    ) -> MisorientationDistribution {
+        let max_angle = symmetry.max_misorientation_angle();
+        let bins = ((max_angle / bin_width).round() as usize).max(1);
+        MisorientationDistribution {
+            n_pairs: angles.len(),
+            bin_width: max_angle / bins as f64,
+            max_angle,
+            observed: misorientation_histogram(angles, bins, max_angle),
+            random: random_misorientation_distribution(symmetry, bins),
+        }
+    }
+
+    /// Return half the sum of the absolute differences between the observed and random distributions. With
+    /// bins of one degree this is the M-index (Skemer et al., 2005). Returns NaN if there are no pairs.
+    pub fn m_index(&self) -> f64 {
+        if self.n_pairs == 0 {
+            return f64::NAN;
+        }
+        0.5 * self
+            .observed
+            .iter()
+            .zip(self.random.iter())
+            .map(|(observed, random)| (observed - random).abs())
+            .sum::<f64>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_misorientation_distribution() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let grains: Vec<Array2<f64>> = (0..200).map(|_| random_rotation_matrix(&mut rng)).collect();
+        let symmetry = CrystalSymmetry::Orthorhombic;
+        let angles = uncorrelated_misorientation_angles(&grains, symmetry, 5000);
+        let distribution = MisorientationDistribution::from_angles(&angles, symmetry, 7.);
+
+        // 120 degree can not be divided in bins of 7 degree, so the width is adjusted.
+        assert_eq!(distribution.observed.len(), 17);
+        assert!((distribution.bin_width * 17. - 120.).abs() < 1e-12);
+        assert_eq!(distribution.n_pairs, 5000);
+        assert!((distribution.observed.iter().sum::<f64>() - 1.).abs() < 1e-12);
+        assert!((distribution.random.iter().sum::<f64>() - 1.).abs() < 1e-6);
+        for (observed, random) in distribution.observed.iter().zip(&distribution.random) {
+            assert!((observed - random).abs() < 0.02);
+        }
+
+        // the M-index is defined with bins of one degree.
+        let m_index_random =
+            MisorientationDistribution::from_angles(&angles, symmetry, 1.).m_index();
+        assert!(m_index_random < 0.1, "M-index random: {}", m_index_random);
+        let single_crystal: Vec<Array2<f64>> = vec![grains[0].clone(); 200];
+        let single_crystal_angles =
+            uncorrelated_misorientation_angles(&single_crystal, symmetry, 5000);
+        let m_index_single_crystal =
+            MisorientationDistribution::from_angles(&single_crystal_angles, symmetry, 1.).m_index();
+        assert!(
+            m_index_single_crystal > 0.99,
+            "M-index single crystal: {}",
+            m_index_single_crystal
+        );
+    }
+
+    #[test]
+    fn test_misorientation_angle() {
+        let angle = 30.0_f64.to_radians();
+        let rotation_a: Array2<f64> = Array2::eye(3);
+        let rotation_b = axis_angle_rotation_matrix(&[0., 0., 1.], angle.sin(), angle.cos());
+
+        let triclinic = CrystalSymmetry::Triclinic.rotations();
+        let orthorhombic = CrystalSymmetry::Orthorhombic.rotations();
+        assert!((misorientation_angle(&rotation_a, &rotation_b, &triclinic) - 30.).abs() < 1e-8);
+        assert!((misorientation_angle(&rotation_a, &rotation_b, &orthorhombic) - 30.).abs() < 1e-8);
+
+        // a 180 degree rotation around the c-axis is a symmetry operation of orthorhombic crystals
+        let rotation_c = axis_angle_rotation_matrix(&[0., 0., 1.], 0., -1.);
+        assert!((misorientation_angle(&rotation_a, &rotation_c, &triclinic) - 180.).abs() < 1e-6);
+        assert!(misorientation_angle(&rotation_a, &rotation_c, &orthorhombic).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_random_misorientation_distribution() {
+        let bins = 36;
+        let triclinic = random_misorientation_distribution(CrystalSymmetry::Triclinic, bins);
+        assert!((triclinic.iter().sum::<f64>() - 1.).abs() < 1e-12);
+        // without symmetry the density is proportional to 1 - cos(angle)
+        let bin_width = std::f64::consts::PI / bins as f64;
+        for (bin, value) in triclinic.iter().enumerate() {
+            let lower = bin as f64 * bin_width;
+            let upper = lower + bin_width;
+            let expected = (bin_width - (upper.sin() - lower.sin())) / std::f64::consts::PI;
+            assert!(
+                (value - expected).abs() < 1e-3,
+                "bin {}: {} != {}",
+                bin,
+                value,
+                expected
+            );
+        }
+
+        let orthorhombic = random_misorientation_distribution(CrystalSymmetry::Orthorhombic, 120);
+        assert!((orthorhombic.iter().sum::<f64>() - 1.).abs() < 1e-12);
+
+        // compare with the misorientation angles of random grains
+        let mut rng = StdRng::seed_from_u64(1);
+        let rotations: Vec<Array2<f64>> = (0..2000)
+            .map(|_| random_rotation_matrix(&mut rng))
+            .collect();
+        let angles =
+            uncorrelated_misorientation_angles(&rotations, CrystalSymmetry::Orthorhombic, 100000);
+        assert!(angles.iter().all(|angle| *angle <= 120. + 1e-8));
+        let histogram = misorientation_histogram(&angles, 12, 120.);
+        let theoretical = random_misorientation_distribution(CrystalSymmetry::Orthorhombic, 12);
+        for bin in 0..12 {
+            assert!(
+                (histogram[bin] - theoretical[bin]).abs() < 0.01,
+                "bin {}: {} != {}",
+                bin,
+                histogram[bin],
+                theoretical[bin]
+            );
+        }
+    }
+}
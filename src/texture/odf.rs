@@ -0,0 +1,269 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use crate::euler_angles_to_rotation_matrix;
+use crate::texture::symmetry::CrystalSymmetry;
+
+use ndarray::{Array, Array1, Array2, Axis};
+
+/// An orientation distribution function (ODF) on the rotation group SO(3), estimated from the rotation matrices
+/// of a set of grains with a de la Vallée Poussin kernel, and discretized on the cell centers of a regular grid
+/// in Z-X-Z Euler space. The ODF is symmetrized with the crystal symmetry and normalized so that a random fabric
+/// has a value of one everywhere.
+#[derive(Clone, Debug)]
+pub struct Odf {
+    /// The crystal symmetry used to symmetrize the ODF.
+    pub symmetry: CrystalSymmetry,
+    /// The resolution in degree of the grid in Euler space.
+    pub resolution: f64,
+    /// The halfwidth in degree of the kernel.
+    pub halfwidth: f64,
+    /// The Euler angles in radians of the grid cell centers, one row per cell.
+    pub euler_angles: Array2<f64>,
+    /// The value of the ODF in each grid cell, in multiples of a random distribution.
+    pub values: Array1<f64>,
+    /// The volume fraction of each grid cell. The weights add up to one.
+    pub weights: Array1<f64>,
+    /// All the symmetrically equivalent orientations of the grains, flattened to one column of 9 values per
+    /// orientation.
+    grains: Array2<f64>,
+    /// The exponent of the kernel.
+    kappa: f64,
+    /// The factor by which the sum of the kernels is multiplied to get the normalized ODF.
+    normalization: f64,
+}
+
+impl Odf {
+    /// Estimate the memory in bytes used to compute the ODF of `n_grains` grains on a grid with the provided
+    /// resolution in degree: the grid, the symmetrically equivalent orientations of the grains and the kernels of
+    /// a chunk of grid orientations.
+    pub fn memory(n_grains: usize, symmetry: CrystalSymmetry, resolution: f64) -> usize {
+        let n_phi = ((360. / resolution).round() as usize).max(1);
+        let n_theta = ((180. / resolution).round() as usize).max(1);
+        let n_cells = n_phi * n_theta * n_phi;
+        let n_orientations = n_grains * symmetry.rotations().len();
+        // the Euler angles, the flattened rotation matrices, the values and the weights of the cells.
+        let grid = n_cells * (3 + 9 + 2);
+        let kernels = n_cells.min(KERNEL_CHUNK_SIZE) * n_orientations;
+        (grid + 9 * n_orientations + kernels) * std::mem::size_of::<f64>()
+    }
+
+    /// Estimate the ODF from the rotation matrices of the grains, as computed by `euler_angles_to_rotation_matrix`,
+    /// on a grid with the provided resolution in degree with a kernel with the provided halfwidth in degree.
+    pub fn from_rotation_matrices(
+        rotation_matrices: &[Array2<f64>],
+        symmetry: CrystalSymmetry,
+        resolution: f64,
+        halfwidth: f64,
+    ) -> Result<Odf, Box<dyn std::error::Error>> {
+        let pi = std::f64::consts::PI;
+        let n_phi = ((360. / resolution).round() as usize).max(1);
+        let n_theta = ((180. / resolution).round() as usize).max(1);
+        let d_phi = 2. * pi / n_phi as f64;
+        let d_theta = pi / n_theta as f64;
+
+        // The grid orientations, flattened to one row of 9 values per orientation, and the volume of
+        // the cells normalized so that all the volumes add up to one.
+        let n_cells = n_phi * n_theta * n_phi;
+        let mut euler_angles = Array2::zeros((n_cells, 3));
+        let mut grid = Array2::zeros((n_cells, 9));
+        let mut weights = Array::zeros(n_cells);
+        let mut cell = 0;
+        for i in 0..n_phi {
+            for j in 0..n_theta {
+                for k in 0..n_phi {
+                    let cell_euler_angles = Array::from(vec![
+                        (i as f64 + 0.5) * d_phi,
+                        (j as f64 + 0.5) * d_theta,
+                        (k as f64 + 0.5) * d_phi,
+                    ]);
+                    euler_angles.row_mut(cell).assign(&cell_euler_angles);
+                    let rotation_matrix = euler_angles_to_rotation_matrix(cell_euler_angles)?;
+                    for (value, grid_value) in rotation_matrix.iter().zip(grid.row_mut(cell)) {
+                        *grid_value = *value;
+                    }
+                    weights[cell] = d_phi
+                        * d_phi
+                        * ((j as f64 * d_theta).cos() - ((j + 1) as f64 * d_theta).cos())
+                        / (8. * pi * pi);
+                    cell += 1;
+                }
+            }
+        }
+
+        // all the symmetrically equivalent orientations of the grains, flattened to one column per orientation.
+        let symmetry_rotations = symmetry.rotations();
+        let mut grains = Array2::zeros((9, rotation_matrices.len() * symmetry_rotations.len()));
+        let mut column = 0;
+        for rotation_matrix in rotation_matrices {
+            for symmetry_rotation in &symmetry_rotations {
+                let equivalent = symmetry_rotation.dot(rotation_matrix);
+                for (value, grain_value) in equivalent.iter().zip(grains.column_mut(column)) {
+                    *grain_value = *value;
+                }
+                column += 1;
+            }
+        }
+
+        // The de la Vallée Poussin kernel is cos(angle/2)^(2 kappa), where cos(angle/2)^2 = (1 + trace)/4 and the
+        // trace of the misorientation between two orientations is the dot product of the flattened matrices.
+        let kappa = 0.5_f64.ln() / (2. * (halfwidth.to_radians() / 2.).cos().ln());
+        let mut values = kernel_sum(&grid, &grains, kappa);
+
+        let total = values.dot(&weights);
+        let normalization = if total > 0. { 1. / total } else { 1. };
+        values *= normalization;
+
+        Ok(Odf {
+            symmetry,
+            resolution,
+            halfwidth,
+            euler_angles,
+            values,
+            weights,
+            grains,
+            kappa,
+            normalization,
+        })
+    }
+
+    /// Evaluate the ODF at the orientation given by a rotation matrix.
+    pub fn evaluate(&self, rotation_matrix: &Array2<f64>) -> f64 {
+        let orientation = Array2::from_shape_fn((1, 9), |(_, i)| rotation_matrix[[i / 3, i % 3]]);
+        kernel_sum(&orientation, &self.grains, self.kappa)[0] * self.normalization
+    }
+
+    /// The texture index (J-index, Bunge 1982), which is the integral of the square of the ODF. It is one for a
+    /// random fabric and goes to infinity for a single crystal.
+    pub fn texture_index(&self) -> f64 {
+        self.values
+            .iter()
+            .zip(self.weights.iter())
+            .map(|(value, weight)| value * value * weight)
+            .sum()
+    }
+
+    /// The entropy of the ODF, `-∫ f ln f`. It is zero for a random fabric and becomes more negative for
+    /// stronger fabrics.
+    pub fn entropy(&self) -> f64 {
+        -self
+            .values
+            .iter()
+            .zip(self.weights.iter())
+            .filter(|(value, _)| **value > 0.)
+            .map(|(value, weight)| value * value.ln() * weight)
+            .sum::<f64>()
+    }
+
+    /// The maximum density of the ODF on the grid, in multiples of a random distribution.
+    pub fn max_density(&self) -> f64 {
+        self.values.iter().cloned().fold(0., f64::max)
+    }
+
+    /// The Euler angles in radians of the grid cell with the maximum density of the ODF.
+    pub fn max_density_orientation(&self) -> Array1<f64> {
+        let cell = self
+            .values
+            .iter()
+            .enumerate()
+            .fold((0, f64::MIN), |max, (i, value)| {
+                if *value > max.1 {
+                    (i, *value)
+                } else {
+                    max
+                }
+            })
+            .0;
+        self.euler_angles.row(cell).to_owned()
+    }
+}
+
+/// The number of orientations for which the kernels of all the grains are computed at once.
+const KERNEL_CHUNK_SIZE: usize = 1024;
+
+/// Compute the sum of the kernels of all grains for each orientation. Both are flattened to 9 values, the
+/// orientations in rows and the grains in columns. The orientations are processed in chunks to limit the
+/// memory use of the orientation-grain matrix.
+fn kernel_sum(orientations: &Array2<f64>, grains: &Array2<f64>, kappa: f64) -> Array1<f64> {
+    let mut sum = Array::zeros(orientations.nrows());
+    let chunk_size = KERNEL_CHUNK_SIZE;
+    for (chunk, orientation_chunk) in orientations
+        .axis_chunks_iter(Axis(0), chunk_size)
+        .enumerate()
+    {
+        let mut kernel = orientation_chunk.dot(grains);
+        kernel.par_mapv_inplace(|trace| ((1. + trace) / 4.).clamp(0., 1.).powf(kappa));
+        sum.slice_mut(ndarray::s![
+            chunk * chunk_size..chunk * chunk_size + orientation_chunk.nrows()
+        ])
+        .assign(&kernel.sum_axis(Axis(1)));
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::misorientation::random_rotation_matrix;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_odf() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let random_grains: Vec<Array2<f64>> =
+            (0..500).map(|_| random_rotation_matrix(&mut rng)).collect();
+        let single_crystal: Vec<Array2<f64>> = vec![random_grains[0].clone(); 500];
+        let symmetry = CrystalSymmetry::Orthorhombic;
+
+        let random = Odf::from_rotation_matrices(&random_grains, symmetry, 15., 15.).unwrap();
+        let single = Odf::from_rotation_matrices(&single_crystal, symmetry, 15., 15.).unwrap();
+
+        assert!((random.values.dot(&random.weights) - 1.).abs() < 1e-12);
+        assert!((single.values.dot(&single.weights) - 1.).abs() < 1e-12);
+
+        assert!(
+            random.entropy() > -0.5,
+            "entropy random: {}",
+            random.entropy()
+        );
+        assert!(single.entropy() < random.entropy() - 1.);
+        assert!(single.max_density() > 10. * random.max_density());
+
+        let j_index_random = random.texture_index();
+        assert!(j_index_random >= 1.0, "J-index random: {}", j_index_random);
+        assert!(j_index_random < 2.0, "J-index random: {}", j_index_random);
+        assert!(
+            single.texture_index() > 10. * j_index_random,
+            "J-index single crystal: {}",
+            single.texture_index()
+        );
+
+        // the ODF of the single crystal peaks at its orientation and its symmetric equivalents.
+        let peak = single.evaluate(&single_crystal[0]);
+        assert!(peak >= single.max_density());
+        let equivalent = symmetry.rotations()[1].dot(&single_crystal[0]);
+        assert!((single.evaluate(&equivalent) - peak).abs() < 1e-8 * peak);
+
+        let max_orientation =
+            euler_angles_to_rotation_matrix(single.max_density_orientation()).unwrap();
+        assert!(single.evaluate(&max_orientation) > 0.5 * peak);
+    }
+}
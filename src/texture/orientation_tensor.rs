@@ -0,0 +1,128 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use crate::linear_algebra::symmetric_eigen;
+
+use ndarray::{Array1, Array2};
+
+/// Stores the orientation (scatter) tensor of a set of axial unit vectors, such as the a-axes of all the
+/// olivine grains of a particle, and its eigenvalues and eigenvectors.
+#[derive(Clone, Debug)]
+pub struct OrientationTensor {
+    /// The orientation tensor, which is the average of the outer products of the vectors with themselves.
+    pub tensor: Array2<f64>,
+    /// The eigenvalues of the tensor, sorted from large to small. They add up to one.
+    pub eigenvalues: Array1<f64>,
+    /// The eigenvectors of the tensor, stored in the columns in the same order as the eigenvalues. Since the
+    /// data is axial the sign of an eigenvector has no meaning, so it is chosen such that the y component (the
+    /// direction in which the pole figures are viewed) is positive.
+    pub eigenvectors: Array2<f64>,
+}
+
+impl OrientationTensor {
+    /// Compute the orientation tensor from an array with one unit vector per row, as returned by
+    /// [`ParticleGrains::axis_vectors`](crate::texture::grains::ParticleGrains::axis_vectors).
+    pub fn from_axis_vectors(axis_vectors: &Array2<f64>) -> OrientationTensor {
+        let n_vectors = axis_vectors.nrows().max(1);
+        let tensor = axis_vectors.t().dot(axis_vectors) / n_vectors as f64;
+
+        let (eigenvalues, mut eigenvectors) = symmetric_eigen(&tensor);
+        for i in 0..3 {
+            if eigenvectors[[1, i]] < 0. {
+                eigenvectors.column_mut(i).mapv_inplace(|x| -x);
+            }
+        }
+
+        OrientationTensor {
+            tensor,
+            eigenvalues,
+            eigenvectors,
+        }
+    }
+
+    /// The point fabric proportion of Vollmer (1990): `P = e1 - e2`.
+    pub fn point(&self) -> f64 {
+        self.eigenvalues[0] - self.eigenvalues[1]
+    }
+
+    /// The girdle fabric proportion of Vollmer (1990): `G = 2 (e2 - e3)`.
+    pub fn girdle(&self) -> f64 {
+        2. * (self.eigenvalues[1] - self.eigenvalues[2])
+    }
+
+    /// The random fabric proportion of Vollmer (1990): `R = 3 e3`.
+    pub fn random(&self) -> f64 {
+        3. * self.eigenvalues[2]
+    }
+
+    /// The cylindricity index of Vollmer (1990): `B = P + G = 1 - R`.
+    pub fn cylindricity(&self) -> f64 {
+        self.point() + self.girdle()
+    }
+
+    /// Return the trend and plunge in degree of an eigenvector. The trend is measured clockwise from the Z axis
+    /// in the X-Z plane of the pole figure, and the plunge is the angle with that plane.
+    pub fn trend_and_plunge(&self, eigenvector: usize) -> (f64, f64) {
+        let vector = self.eigenvectors.column(eigenvector);
+        let trend = vector[0].atan2(vector[2]).to_degrees();
+        let plunge = vector[1].clamp(-1., 1.).asin().to_degrees();
+        (if trend < 0. { trend + 360. } else { trend }, plunge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vollmer_fabric_shapes() {
+        // all vectors in the same direction: a point fabric.
+        let mut point = Array2::zeros((10, 3));
+        point.column_mut(0).fill(1.);
+        let tensor = OrientationTensor::from_axis_vectors(&point);
+        assert!((tensor.point() - 1.).abs() < 1e-12);
+        assert!(tensor.girdle().abs() < 1e-12);
+        assert!(tensor.random().abs() < 1e-12);
+        assert!((tensor.eigenvectors[[0, 0]].abs() - 1.).abs() < 1e-12);
+
+        // vectors evenly distributed in the X-Z plane: a girdle fabric.
+        let n = 360;
+        let mut girdle = Array2::zeros((n, 3));
+        for i in 0..n {
+            let angle = (i as f64).to_radians();
+            girdle[[i, 0]] = angle.cos();
+            girdle[[i, 2]] = angle.sin();
+        }
+        let tensor = OrientationTensor::from_axis_vectors(&girdle);
+        assert!(tensor.point().abs() < 1e-12);
+        assert!((tensor.girdle() - 1.).abs() < 1e-12);
+        assert!(tensor.random().abs() < 1e-12);
+        assert!((tensor.cylindricity() - 1.).abs() < 1e-12);
+        // the pole of the girdle is the Y axis
+        assert!((tensor.eigenvectors[[1, 2]] - 1.).abs() < 1e-12);
+        assert!((tensor.trend_and_plunge(2).1 - 90.).abs() < 1e-6);
+
+        // the three coordinate axes: a random fabric.
+        let random = Array2::eye(3);
+        let tensor = OrientationTensor::from_axis_vectors(&random);
+        assert!((tensor.random() - 1.).abs() < 1e-12);
+        assert!(tensor.cylindricity().abs() < 1e-12);
+    }
+}
@@ -0,0 +1,114 @@
+/*
+  Copyright (C) 2021 by the authors of the CPO Analyzer code.
+
+  This file is part of the CPO Analyzer.
+
+  The CPO Analyzer is free software; you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation; either version 2, or (at your option)
+  any later version.
+
+  The CPO Analyzer is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with the CPO Analyzer; see the file LICENSE.  If not see
+  <http://www.gnu.org/licenses/>.
+*/
+
+use ndarray::{arr2, Array1, Array2};
+use serde_derive::Deserialize;
+
+/// A enum to define the crystal symmetry of a mineral.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum CrystalSymmetry {
+    Triclinic,
+    Orthorhombic,
+}
+
+impl CrystalSymmetry {
+    /// Return the proper rotations of the point group, as rotation matrices in the crystal frame. Applying
+    /// any of these rotations to a grain results in a physically identical orientation.
+    pub fn rotations(&self) -> Vec<Array2<f64>> {
+        match self {
+            CrystalSymmetry::Triclinic => vec![Array2::eye(3)],
+            // Identity and the 180 degree rotations around the three crystal axes.
+            CrystalSymmetry::Orthorhombic => vec![
+                Array2::eye(3),
+                arr2(&[[1., 0., 0.], [0., -1., 0.], [0., 0., -1.]]),
+                arr2(&[[-1., 0., 0.], [0., 1., 0.], [0., 0., -1.]]),
+                arr2(&[[-1., 0., 0.], [0., -1., 0.], [0., 0., 1.]]),
+            ],
+        }
+    }
+
+    /// Return the symmetrically equivalent directions of a unit vector in the crystal frame. Since the
+    /// directions are used as axial data, a direction and its opposite are counted only once.
+    pub fn equivalent_directions(&self, direction: &Array1<f64>) -> Vec<Array1<f64>> {
+        let mut equivalent_directions: Vec<Array1<f64>> = Vec::new();
+        for rotation in self.rotations() {
+            let equivalent = rotation.dot(direction);
+            if !equivalent_directions
+                .iter()
+                .any(|other| other.dot(&equivalent).abs() > 1. - 1e-10)
+            {
+                equivalent_directions.push(equivalent);
+            }
+        }
+        equivalent_directions
+    }
+
+    /// Return the maximum misorientation angle in degree between two grains with this symmetry.
+    pub fn max_misorientation_angle(&self) -> f64 {
+        match self {
+            CrystalSymmetry::Triclinic => 180.,
+            CrystalSymmetry::Orthorhombic => 120.,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::arr1;
+
+    #[test]
+    fn test_equivalent_directions() {
+        let symmetry = CrystalSymmetry::Orthorhombic;
+        // a crystal axis is its own equivalent, a general direction in a mirror plane has two axial equivalents
+        // and a general direction has four.
+        assert_eq!(
+            symmetry.equivalent_directions(&arr1(&[0., 1., 0.])).len(),
+            1
+        );
+        assert_eq!(
+            symmetry.equivalent_directions(&arr1(&[0.6, 0.8, 0.])).len(),
+            2
+        );
+        let general = arr1(&[1., 2., 2.]) / 3.;
+        assert_eq!(symmetry.equivalent_directions(&general).len(), 4);
+        assert_eq!(
+            CrystalSymmetry::Triclinic
+                .equivalent_directions(&general)
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_symmetry_rotations_are_rotations() {
+        for symmetry in &[CrystalSymmetry::Triclinic, CrystalSymmetry::Orthorhombic] {
+            for rotation in symmetry.rotations() {
+                let identity = rotation.dot(&rotation.t());
+                for i in 0..3 {
+                    for j in 0..3 {
+                        let expected = if i == j { 1. } else { 0. };
+                        assert!((identity[[i, j]] - expected).abs() < 1e-12);
+                    }
+                }
+            }
+        }
+    }
+}
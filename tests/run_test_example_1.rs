@@ -18,13 +18,50 @@
   <http://www.gnu.org/licenses/>.
 */
 
+use cpo_analyzer::color_gradients::ColorGradient;
+use cpo_analyzer::configuration::animation_configuration::{
+    AnimationConfiguration, AnimationFormat,
+};
+use cpo_analyzer::configuration::comparison_configuration::{
+    ComparisonConfiguration, ComparisonDimension,
+};
+use cpo_analyzer::configuration::elasticity_configuration::ElasticityConfiguration;
+use cpo_analyzer::configuration::fabric_configuration::FabricConfiguration;
+use cpo_analyzer::configuration::layout_configuration::LayoutConfiguration;
+use cpo_analyzer::configuration::pole_figure_configuration::{
+    ColorBarOrientation, ColorScaleRange, ColorScaling, OutputFormat,
+};
+use cpo_analyzer::configuration::sks_splitting_configuration::SksSplittingConfiguration;
 use cpo_analyzer::load_configuration_file;
+use cpo_analyzer::pole_figures::minerals::Mineral;
 use cpo_analyzer::process_configuration;
 
 use assert_cmd::prelude::*;
 use std::path::PathBuf;
 use std::process::Command;
 
+/// Return all the elements with the given name in the contents of an SVG file, from the attributes up to the end of
+/// the element.
+fn svg_elements<'a>(contents: &'a str, name: &str) -> Vec<&'a str> {
+    contents
+        .split(&format!("<{} ", name))
+        .skip(1)
+        .map(|element| {
+            let end = ["/>", "</"]
+                .iter()
+                .filter_map(|end| element.find(end))
+                .min()
+                .unwrap_or(element.len());
+            &element[..end]
+        })
+        .collect()
+}
+
+/// Return the text of an SVG element.
+fn svg_text(element: &str) -> &str {
+    element.split_once('>').map_or("", |(_, text)| text.trim())
+}
+
 #[test]
 fn test_library_run() -> Result<(), Box<dyn std::error::Error>> {
     let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
@@ -91,3 +128,605 @@ fn test_binary() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn test_fabric_strength_output() -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
+
+    let mut configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    let pole_figure_configuration = configuration.pole_figures.as_mut().unwrap();
+    pole_figure_configuration.figure_output_dir = "test_results_fabric/".to_string();
+    pole_figure_configuration.fabric_strength_header = true;
+    configuration.fabric = Some(FabricConfiguration {
+        strength_output_file: "test_results_fabric/fabric_strength.dat".to_string(),
+        orientation_tensor_output_file: "test_results_fabric/orientation_tensor.dat".to_string(),
+        fabric_type_output_file: "test_results_fabric/fabric_type.dat".to_string(),
+        misorientation_output_file: "test_results_fabric/misorientation.dat".to_string(),
+        misorientation_figures: true,
+        ..FabricConfiguration::default()
+    });
+    process_configuration(configuration).unwrap();
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b' ')
+        .from_path("examples/example_experiment_1/test_results_fabric/fabric_strength.dat")?;
+    let records = reader
+        .records()
+        .collect::<Result<Vec<csv::StringRecord>, csv::Error>>()?;
+
+    // two times and two minerals for a single particle
+    assert_eq!(records.len(), 4);
+    for record in records {
+        assert_eq!(&record[0], "0");
+        assert_eq!(&record[4], "1");
+        // the J-index of a single grain is larger than one, the M-index is not defined.
+        assert!(record[5].parse::<f64>()? > 1.);
+        assert!(record[6].parse::<f64>()?.is_nan());
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b' ')
+        .from_path("examples/example_experiment_1/test_results_fabric/orientation_tensor.dat")?;
+    let records = reader
+        .records()
+        .collect::<Result<Vec<csv::StringRecord>, csv::Error>>()?;
+
+    // two times, two minerals and three axes for a single particle
+    assert_eq!(records.len(), 12);
+    for record in records {
+        // a single grain is a perfect point fabric.
+        assert!((record[5].parse::<f64>()? - 1.).abs() < 1e-10);
+        assert!((record[17].parse::<f64>()? - 1.).abs() < 1e-10);
+        assert!(record[19].parse::<f64>()?.abs() < 1e-10);
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b' ')
+        .from_path("examples/example_experiment_1/test_results_fabric/fabric_type.dat")?;
+    let records = reader
+        .records()
+        .collect::<Result<Vec<csv::StringRecord>, csv::Error>>()?;
+
+    // two times for a single particle
+    assert_eq!(records.len(), 2);
+    for record in records {
+        assert_eq!(&record[0], "0");
+        assert!(["A", "B", "C", "D", "E", "AG", "Unclassified"].contains(&&record[4]));
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b' ')
+        .from_path("examples/example_experiment_1/test_results_fabric/misorientation.dat")?;
+    let records = reader
+        .records()
+        .collect::<Result<Vec<csv::StringRecord>, csv::Error>>()?;
+
+    // two times and two minerals with 24 bins of 5 degree for a single particle, which has no grain pairs.
+    assert_eq!(records.len(), 96);
+    for record in records {
+        assert_eq!(&record[4], "0");
+        assert_eq!(record[7].parse::<f64>()?, 0.);
+    }
+    assert!(PathBuf::from(
+        "examples/example_experiment_1/test_results_fabric/misorientation_oli_ens_t00001.00000.png"
+    )
+    .exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_drawing_error() {
+    let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
+
+    let mut configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    let pole_figures = configuration.pole_figures.as_mut().unwrap();
+    pole_figures.figure_output_dir = "test_results_drawing_error/".to_string();
+    // the figures can not be written in a directory which does not exist
+    pole_figures.figure_output_prefix = "missing_dir/weighted_LPO".to_string();
+    assert!(process_configuration(configuration).is_err());
+}
+
+#[test]
+fn test_miller_index_pole_figures() -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = PathBuf::from("tests/test_example_1_miller.toml");
+
+    let configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    process_configuration(configuration).unwrap();
+
+    assert!(PathBuf::from(
+        "examples/example_experiment_1/test_results_miller/weighted_LPO_elastic_oli_ens_hkl010-uvw101-HKL110-Axis_Batlow_g1_sp301_t00001.00000.png"
+    )
+    .exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_inverse_pole_figures() -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = PathBuf::from("tests/test_example_1_inverse.toml");
+
+    let configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    process_configuration(configuration).unwrap();
+
+    assert!(PathBuf::from(
+        "examples/example_experiment_1/test_results_inverse/weighted_IPF_oli_ens_X-Z-1_0_1_Batlow_g1_sp301_t00001.00000.png"
+    )
+    .exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_scatter_pole_figures() -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
+
+    let mut configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    let pole_figures = configuration.pole_figures.as_mut().unwrap();
+    pole_figures.figure_output_dir = "test_results_scatter/".to_string();
+    pole_figures.output_format = OutputFormat::Svg;
+    pole_figures.times = vec![1.0];
+    pole_figures.scatter = true;
+    pole_figures.scatter_size_by_volume_fraction = true;
+    pole_figures.scatter_color_by_mineral = true;
+    let point_size = pole_figures.scatter_point_size;
+    process_configuration(configuration).unwrap();
+
+    let contents = std::fs::read_to_string(
+        "examples/example_experiment_1/test_results_scatter/weighted_LPO_elastic_oli_ens_A-B-C-Axis_Batlow_g1_sp301_t00002.00000.svg",
+    )?;
+    // the example particle has one grain of every mineral, so every pole figure has one point with the average
+    // size in the color of its mineral.
+    let circles = svg_elements(&contents, "circle");
+    assert_eq!(circles.len(), 6);
+    for mineral in &[Mineral::Olivine, Mineral::Enstatite] {
+        let (red, green, blue) = mineral.scatter_color();
+        let fill = format!("fill=\"#{:02X}{:02X}{:02X}\"", red, green, blue);
+        assert_eq!(
+            circles
+                .iter()
+                .filter(|circle| circle.contains(&fill))
+                .count(),
+            3
+        );
+    }
+    let radius = format!("r=\"{}\"", point_size.round());
+    assert!(circles.iter().all(|circle| circle.contains(&radius)));
+
+    Ok(())
+}
+
+#[test]
+fn test_global_color_scale_pole_figures() -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
+
+    let mut configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    let pole_figures = configuration.pole_figures.as_mut().unwrap();
+    pole_figures.figure_output_dir = "test_results_global/".to_string();
+    pole_figures.color_scale_range = ColorScaleRange::Global;
+    // a memory limit which only fits a single task at a time
+    configuration.max_memory_mb = Some(1);
+    process_configuration(configuration).unwrap();
+
+    for time_step in 1..=2 {
+        assert!(PathBuf::from(format!(
+            "examples/example_experiment_1/test_results_global/weighted_LPO_elastic_oli_ens_A-B-C-Axis_Batlow_g1_sp301_t{:05}.00000.png",
+            time_step
+        ))
+        .exists());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_contour_pole_figures() -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
+
+    let mut configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    let pole_figures = configuration.pole_figures.as_mut().unwrap();
+    pole_figures.figure_output_dir = "test_results_contours/".to_string();
+    pole_figures.output_format = OutputFormat::Svg;
+    pole_figures.times = vec![1.0];
+    pole_figures.contour_levels = vec![1.0, 2.0];
+    pole_figures.contour_labels = true;
+    pole_figures.contours_only = true;
+    process_configuration(configuration).unwrap();
+
+    let contents = std::fs::read_to_string(
+        "examples/example_experiment_1/test_results_contours/weighted_LPO_elastic_oli_ens_A-B-C-Axis_Batlow_g1_sp301_t00002.00000.svg",
+    )?;
+    // without the density field there is no embedded image
+    assert!(!contents.contains("<image"));
+    // both levels are labelled on a white circle in all six pole figures
+    assert_eq!(svg_elements(&contents, "circle").len(), 2 * 6);
+    let labels = svg_elements(&contents, "text")
+        .into_iter()
+        .map(svg_text)
+        .filter(|text| *text == "1" || *text == "2")
+        .count();
+    assert_eq!(labels, 2 * 6);
+
+    Ok(())
+}
+
+#[test]
+fn test_log_color_scale_pole_figures() -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
+
+    let mut configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    let pole_figures = configuration.pole_figures.as_mut().unwrap();
+    pole_figures.figure_output_dir = "test_results_log/".to_string();
+    pole_figures.output_format = OutputFormat::Svg;
+    pole_figures.times = vec![1.0];
+    pole_figures.color_scaling = ColorScaling::Log;
+    pole_figures.color_bar_orientation = ColorBarOrientation::Horizontal;
+    process_configuration(configuration).unwrap();
+
+    let contents = std::fs::read_to_string(
+        "examples/example_experiment_1/test_results_log/weighted_LPO_elastic_oli_ens_A-B-C-Axis_Batlow_log_sp301_t00002.00000.svg",
+    )?;
+    let texts: Vec<&str> = svg_elements(&contents, "text")
+        .into_iter()
+        .map(svg_text)
+        .collect();
+    // the horizontal color bars below the pole figures are named by mineral
+    assert!(texts.contains(&"olivine MUD"));
+    assert!(texts.contains(&"enstatite MUD"));
+    // the ticks of a logarithmic color bar are 1, 2 and 5 times a power of ten
+    let ticks: Vec<f64> = texts.iter().filter_map(|text| text.parse().ok()).collect();
+    assert!(ticks.len() >= 2 * 3, "ticks: {:?}", ticks);
+    for tick in &ticks {
+        let mantissa = tick / 10_f64.powf(tick.log10().floor());
+        assert!(
+            [1.0, 2.0, 5.0]
+                .iter()
+                .any(|value| (mantissa - value).abs() < 1e-9),
+            "tick: {}",
+            tick
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_color_map_file_pole_figures() -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
+    let color_map_file = std::env::temp_dir().join("cpo_analyzer_test_gray.cpt");
+    std::fs::write(
+        &color_map_file,
+        "# COLOR_MODEL = RGB\n0 0 0 0 1 128 128 128\n1 128/128/128 2 255/255/255\nB 0 0 0\n",
+    )?;
+
+    let mut configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    let pole_figures = configuration.pole_figures.as_mut().unwrap();
+    pole_figures.figure_output_dir = "test_results_color_map/".to_string();
+    pole_figures.output_format = OutputFormat::Svg;
+    pole_figures.times = vec![1.0];
+    pole_figures.color_scale = ColorGradient::File {
+        path: color_map_file.to_string_lossy().into_owned(),
+    };
+    pole_figures.reverse_color_scale = true;
+    process_configuration(configuration).unwrap();
+
+    let contents = std::fs::read_to_string(
+        "examples/example_experiment_1/test_results_color_map/weighted_LPO_elastic_oli_ens_A-B-C-Axis_cpo_analyzer_test_gray_r_g1_sp301_t00002.00000.svg",
+    )?;
+    // The color bars of both minerals are drawn as rectangles from the start to the end of the color scale, after
+    // the white background. The reversed map runs from white through gray to black.
+    let color_bar_colors: Vec<(u8, u8, u8)> = svg_elements(&contents, "rect")
+        .iter()
+        .skip(1)
+        .filter_map(|rect| {
+            let fill = rect.split("fill=\"#").nth(1)?;
+            let channel = |i: usize| u8::from_str_radix(&fill[2 * i..2 * i + 2], 16).unwrap();
+            Some((channel(0), channel(1), channel(2)))
+        })
+        .collect();
+    assert_eq!(color_bar_colors.len(), 2 * 150);
+    for color_bar in color_bar_colors.chunks(150) {
+        assert!(color_bar
+            .iter()
+            .all(|(red, green, blue)| red == green && green == blue));
+        assert_eq!(color_bar[0].0, 255);
+        assert!(color_bar[149].0 < 16);
+        assert!(color_bar.windows(2).all(|pair| pair[1].0 <= pair[0].0));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_gif_animation() -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
+
+    let mut configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    configuration
+        .pole_figures
+        .as_mut()
+        .unwrap()
+        .figure_output_dir = "test_results_gif_animation/".to_string();
+    configuration.animation = Some(AnimationConfiguration {
+        particle_id: 0,
+        ..AnimationConfiguration::default()
+    });
+    process_configuration(configuration).unwrap();
+
+    let contents = std::fs::read(
+        "examples/example_experiment_1/test_results_gif_animation/weighted_LPO_animation_00000.gif",
+    )?;
+    assert!(contents.starts_with(b"GIF89a"));
+
+    Ok(())
+}
+
+#[test]
+fn test_png_frames_animation() -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
+
+    let mut configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    configuration
+        .pole_figures
+        .as_mut()
+        .unwrap()
+        .figure_output_dir = "test_results_png_animation/".to_string();
+    configuration.animation = Some(AnimationConfiguration {
+        particle_id: 0,
+        start_time: Some(0.5),
+        time_step_interval: 2,
+        format: AnimationFormat::PngFrames,
+        ..AnimationConfiguration::default()
+    });
+    configuration.max_memory_mb = Some(1);
+    process_configuration(configuration).unwrap();
+
+    let frame_list = std::fs::read_to_string(
+        "examples/example_experiment_1/test_results_png_animation/weighted_LPO_animation_00000_frames.txt",
+    )?;
+    let frames: Vec<&str> = frame_list.lines().collect();
+    assert_eq!(frames.len(), 2);
+    for frame in frames {
+        assert!(PathBuf::from(frame).exists());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_comparison_figures() -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
+
+    let mut configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    configuration
+        .pole_figures
+        .as_mut()
+        .unwrap()
+        .figure_output_dir = "test_results_comparison/".to_string();
+    configuration.comparison = Some(ComparisonConfiguration {
+        rows: ComparisonDimension::Axis,
+        columns: ComparisonDimension::Time,
+    });
+    process_configuration(configuration).unwrap();
+
+    for mineral in &["oli", "ens"] {
+        assert!(PathBuf::from(format!(
+            "examples/example_experiment_1/test_results_comparison/weighted_LPO_comparison_Axis-Time_00000_example_experiment_1_{}.png",
+            mineral
+        ))
+        .exists());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_comparison_figures_same_rows_and_columns() {
+    let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
+
+    let mut configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    configuration
+        .pole_figures
+        .as_mut()
+        .unwrap()
+        .figure_output_dir = "test_results_comparison_error/".to_string();
+    configuration.comparison = Some(ComparisonConfiguration {
+        rows: ComparisonDimension::Time,
+        columns: ComparisonDimension::Time,
+    });
+    assert!(process_configuration(configuration).is_err());
+}
+
+#[test]
+fn test_layout_pole_figures() -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
+
+    let mut configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    let pole_figures = configuration.pole_figures.as_mut().unwrap();
+    pole_figures.figure_output_dir = "test_results_layout/".to_string();
+    pole_figures.elastisity_header = false;
+    pole_figures.layout = LayoutConfiguration {
+        panel_size: Some(300),
+        scale: 2.0,
+        header_template: Some("particle {id} at {time}".to_string()),
+        ..LayoutConfiguration::default()
+    };
+    process_configuration(configuration).unwrap();
+
+    let contents = std::fs::read(
+        "examples/example_experiment_1/test_results_layout/weighted_LPO_no-elastic_oli_ens_A-B-C-Axis_Batlow_g1_sp301_t00001.00000.png",
+    )?;
+    // three pole figures of 600 pixels, a margin of 20 pixels and a color bar of 300 pixels
+    let width = u32::from_be_bytes([contents[16], contents[17], contents[18], contents[19]]);
+    assert_eq!(width, 3 * 600 + 20 + 300);
+
+    Ok(())
+}
+
+#[test]
+fn test_svg_pole_figures() -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
+
+    let mut configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    let pole_figures = configuration.pole_figures.as_mut().unwrap();
+    pole_figures.figure_output_dir = "test_results_svg/".to_string();
+    pole_figures.output_format = OutputFormat::Svg;
+    process_configuration(configuration).unwrap();
+
+    let svg_file = PathBuf::from(
+        "examples/example_experiment_1/test_results_svg/weighted_LPO_elastic_oli_ens_A-B-C-Axis_Batlow_g1_sp301_t00001.00000.svg",
+    );
+    let contents = std::fs::read_to_string(&svg_file)?;
+    assert!(contents.contains("<svg"));
+    // the density field is embedded as a raster image instead of one polygon per grid cell
+    assert!(contents.contains("<image"));
+    assert!(contents.len() < 5_000_000);
+
+    Ok(())
+}
+
+#[test]
+fn test_elastic_tensor_output() -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
+
+    let mut configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    configuration
+        .pole_figures
+        .as_mut()
+        .unwrap()
+        .figure_output_dir = "test_results_elastic/".to_string();
+    configuration
+        .pole_figures
+        .as_mut()
+        .unwrap()
+        .hexagonal_axis_marker = true;
+    configuration
+        .pole_figures
+        .as_mut()
+        .unwrap()
+        .seismic_anisotropy_header = true;
+    configuration.elasticity = Some(ElasticityConfiguration {
+        output_file: "test_results_elastic/elastic_tensor.dat".to_string(),
+        hexagonal_axis_output_file: "test_results_elastic/hexagonal_axis.dat".to_string(),
+        velocity_figures: true,
+        seismic_anisotropy_output_file: "test_results_elastic/seismic_anisotropy.dat".to_string(),
+        ..ElasticityConfiguration::default()
+    });
+    process_configuration(configuration).unwrap();
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b' ')
+        .from_path("examples/example_experiment_1/test_results_elastic/elastic_tensor.dat")?;
+    let records = reader
+        .records()
+        .collect::<Result<Vec<csv::StringRecord>, csv::Error>>()?;
+
+    // two times and three averages for a single particle
+    assert_eq!(records.len(), 6);
+    for record in records {
+        assert_eq!(record.len(), 25);
+        // C11 is positive and the tensor is between the olivine and enstatite stiffness.
+        let c11 = record[4].parse::<f64>()?;
+        assert!(c11 > 150. && c11 < 330.);
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b' ')
+        .from_path("examples/example_experiment_1/test_results_elastic/hexagonal_axis.dat")?;
+    let records = reader
+        .records()
+        .collect::<Result<Vec<csv::StringRecord>, csv::Error>>()?;
+
+    // one axis for each of the two times
+    assert_eq!(records.len(), 2);
+    for record in records {
+        assert_eq!(record.len(), 9);
+        let vector = (3..6)
+            .map(|i| record[i].parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()?;
+        let length = vector.iter().map(|x| x * x).sum::<f64>().sqrt();
+        assert!((length - 1.).abs() < 1e-6);
+        let plunge = record[7].parse::<f64>()?;
+        assert!((0. ..=90.).contains(&plunge));
+    }
+
+    assert!(PathBuf::from(
+        "examples/example_experiment_1/test_results_elastic/seismic_velocities_t00001.00000.png"
+    )
+    .exists());
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b' ')
+        .from_path("examples/example_experiment_1/test_results_elastic/seismic_anisotropy.dat")?;
+    let records = reader
+        .records()
+        .collect::<Result<Vec<csv::StringRecord>, csv::Error>>()?;
+
+    assert_eq!(records.len(), 2);
+    for record in records {
+        assert_eq!(record.len(), 23);
+        // xi, phi and eta are close to one for the weakly anisotropic aggregate.
+        for i in 3..6 {
+            let value = record[i].parse::<f64>()?;
+            assert!(value > 0.5 && value < 1.5);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sks_splitting_output() -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = PathBuf::from("examples/example_experiment_1/example_1_config.toml");
+
+    let mut configuration =
+        load_configuration_file(config_file).expect("Could not load configuration file");
+    configuration
+        .pole_figures
+        .as_mut()
+        .unwrap()
+        .figure_output_dir = "test_results_sks/".to_string();
+    // the example only has one particle, so the thickness of its layer is set.
+    configuration.sks_splitting = Some(SksSplittingConfiguration {
+        output_file: "test_results_sks/sks_splitting.dat".to_string(),
+        layer_thickness: Some(200e3),
+        ..SksSplittingConfiguration::default()
+    });
+    process_configuration(configuration).unwrap();
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b' ')
+        .from_path("examples/example_experiment_1/test_results_sks/sks_splitting.dat")?;
+    let records = reader
+        .records()
+        .collect::<Result<Vec<csv::StringRecord>, csv::Error>>()?;
+
+    // two times and 18 back-azimuths
+    assert_eq!(records.len(), 36);
+    let fast_azimuth = records[0][4].parse::<f64>()?;
+    for record in records.iter().take(18) {
+        assert_eq!(record.len(), 6);
+        // a single layer gives the same splitting for all back-azimuths.
+        assert!((record[4].parse::<f64>()? - fast_azimuth).abs() < 1e-6);
+        let delay_time = record[5].parse::<f64>()?;
+        assert!(delay_time > 0. && delay_time < 10.);
+    }
+
+    Ok(())
+}